@@ -1,60 +1,300 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use serde::Serialize;
 use tauri::State;
 
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
 use std::sync::Mutex;
 
 use calc::address::*;
 use calc::cell::Cell;
-use calc::sheet::Sheet;
-use calc::value::Value;
+use calc::formula::registry::Description;
+use calc::parser::ParseFormulaError;
+use calc::workbook::{Changes, Workbook, WorkbookError};
 
 #[tauri::command]
-fn get_formula(sheet: State<Mutex<Sheet>>, address: CellAddress) -> String {
-    let sheet = sheet.lock().unwrap();
+fn open_file(workbook: State<Mutex<Workbook>>, path: String) -> Result<Changes, String> {
+    let mut workbook = workbook.lock().unwrap();
 
-    sheet.cell(&address).map_or("", Cell::input).to_string()
+    let file = File::open(path).map_err(|error| error.to_string())?;
+    workbook
+        .load(BufReader::new(file))
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+fn save_file(workbook: State<Mutex<Workbook>>, path: String) -> Result<(), String> {
+    let workbook = workbook.lock().unwrap();
+
+    let file = File::create(path).map_err(|error| error.to_string())?;
+    workbook
+        .save(BufWriter::new(file))
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+fn list_sheets(workbook: State<Mutex<Workbook>>) -> Vec<String> {
+    let workbook = workbook.lock().unwrap();
+
+    workbook.sheet_names().map(str::to_string).collect()
+}
+
+#[tauri::command]
+fn list_functions(workbook: State<Mutex<Workbook>>) -> Vec<Description> {
+    let workbook = workbook.lock().unwrap();
+
+    workbook.functions().descriptions().into_iter().cloned().collect()
+}
+
+#[tauri::command]
+fn add_sheet(workbook: State<Mutex<Workbook>>, name: String) -> Result<Changes, String> {
+    let mut workbook = workbook.lock().unwrap();
+
+    workbook.add_sheet(name).map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+fn rename_sheet(
+    workbook: State<Mutex<Workbook>>,
+    from: String,
+    to: String,
+) -> Result<Changes, String> {
+    let mut workbook = workbook.lock().unwrap();
+
+    workbook
+        .rename_sheet(&from, &to)
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+fn delete_sheet(workbook: State<Mutex<Workbook>>, name: String) -> Result<Changes, String> {
+    let mut workbook = workbook.lock().unwrap();
+
+    workbook
+        .delete_sheet(&name)
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+fn list_names(
+    workbook: State<Mutex<Workbook>>,
+    sheet: String,
+) -> Result<BTreeMap<String, String>, String> {
+    let workbook = workbook.lock().unwrap();
+
+    let sheet = workbook
+        .sheet(&sheet)
+        .ok_or_else(|| format!("there is no sheet named '{}'", sheet))?;
+    let names = sheet
+        .defined_names()
+        .into_iter()
+        .map(|name| (name.name().to_string(), name.definition()))
+        .collect();
+    Ok(names)
+}
+
+#[tauri::command]
+fn define_name(
+    workbook: State<Mutex<Workbook>>,
+    sheet: String,
+    name: String,
+    definition: String,
+) -> Result<Changes, String> {
+    let mut workbook = workbook.lock().unwrap();
+
+    workbook
+        .define_name(&sheet, &name, &definition)
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+fn rename_name(
+    workbook: State<Mutex<Workbook>>,
+    sheet: String,
+    from: String,
+    to: String,
+) -> Result<Changes, String> {
+    let mut workbook = workbook.lock().unwrap();
+
+    workbook
+        .rename_name(&sheet, &from, &to)
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+fn delete_name(
+    workbook: State<Mutex<Workbook>>,
+    sheet: String,
+    name: String,
+) -> Result<Changes, String> {
+    let mut workbook = workbook.lock().unwrap();
+
+    workbook
+        .delete_name(&sheet, &name)
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+fn get_formula(
+    workbook: State<Mutex<Workbook>>,
+    sheet: String,
+    address: CellAddress,
+) -> Result<String, String> {
+    let workbook = workbook.lock().unwrap();
+
+    let sheet = workbook
+        .sheet(&sheet)
+        .ok_or_else(|| format!("there is no sheet named '{}'", sheet))?;
+    Ok(sheet.cell(&address).map_or("", Cell::input).to_string())
+}
+
+/// An error setting a cell's formula. For an invalid formula, `syntax` tells where and why it is invalid.
+#[derive(Serialize)]
+struct SetFormulaError {
+    message: String,
+    syntax: Option<ParseFormulaError>,
+}
+
+impl From<WorkbookError> for SetFormulaError {
+    fn from(error: WorkbookError) -> Self {
+        let syntax = match &error {
+            WorkbookError::Parse(error) => Some(error.clone()),
+            _ => None,
+        };
+        Self {
+            message: error.to_string(),
+            syntax,
+        }
+    }
 }
 
 #[tauri::command]
 fn set_formula(
-    sheet: State<Mutex<Sheet>>,
+    workbook: State<Mutex<Workbook>>,
+    sheet: String,
     address: CellAddress,
     formula: String,
-) -> Result<HashMap<CellAddress, Value>, String> {
-    let mut sheet = sheet.lock().unwrap();
+) -> Result<Changes, SetFormulaError> {
+    let mut workbook = workbook.lock().unwrap();
 
-    sheet
-      .set_cell(address, formula)
-      .map_err(|error| error.to_string())
+    Ok(workbook.set_cell(&sheet, address, formula)?)
 }
 
-fn main() {
-    fn sum(values: &[Value]) -> Value {
-        use bigdecimal::BigDecimal;
-        use calc::value::Error;
+#[tauri::command]
+fn undo(workbook: State<Mutex<Workbook>>) -> Option<Changes> {
+    let mut workbook = workbook.lock().unwrap();
 
-        fn inner(values: &[Value]) -> Result<Value, Error> {
-            let mut sum = BigDecimal::default();
-            for value in values {
-                sum += value.as_number()?;
-            }
+    workbook.undo()
+}
 
-            Ok(Value::Number(sum))
-        }
+#[tauri::command]
+fn redo(workbook: State<Mutex<Workbook>>) -> Option<Changes> {
+    let mut workbook = workbook.lock().unwrap();
 
-        inner(values).unwrap_or_else(Value::Error)
-    }
+    workbook.redo()
+}
+
+#[tauri::command]
+fn copy_range(
+    workbook: State<Mutex<Workbook>>,
+    sheet: String,
+    source: CellRange,
+    destination: CellRange,
+) -> Result<Changes, String> {
+    let mut workbook = workbook.lock().unwrap();
 
-    let mut sheet = Sheet::new();
-    sheet.set_function("sum", sum);
+    workbook
+        .copy_range(&sheet, source, destination)
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+fn insert_rows(
+    workbook: State<Mutex<Workbook>>,
+    sheet: String,
+    at: RowAddress,
+    count: u32,
+) -> Result<Changes, String> {
+    let mut workbook = workbook.lock().unwrap();
+
+    workbook
+        .insert_rows(&sheet, at, count)
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+fn insert_cols(
+    workbook: State<Mutex<Workbook>>,
+    sheet: String,
+    at: ColAddress,
+    count: u32,
+) -> Result<Changes, String> {
+    let mut workbook = workbook.lock().unwrap();
+
+    workbook
+        .insert_cols(&sheet, at, count)
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+fn delete_rows(
+    workbook: State<Mutex<Workbook>>,
+    sheet: String,
+    at: RowAddress,
+    count: u32,
+) -> Result<Changes, String> {
+    let mut workbook = workbook.lock().unwrap();
+
+    workbook
+        .delete_rows(&sheet, at, count)
+        .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+fn delete_cols(
+    workbook: State<Mutex<Workbook>>,
+    sheet: String,
+    at: ColAddress,
+    count: u32,
+) -> Result<Changes, String> {
+    let mut workbook = workbook.lock().unwrap();
+
+    workbook
+        .delete_cols(&sheet, at, count)
+        .map_err(|error| error.to_string())
+}
+
+fn main() {
+    let workbook = Workbook::with_standard_library();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
-        .manage(Mutex::new(sheet))
-        .invoke_handler(tauri::generate_handler![get_formula, set_formula])
+        .manage(Mutex::new(workbook))
+        .invoke_handler(tauri::generate_handler![
+            open_file,
+            save_file,
+            list_sheets,
+            add_sheet,
+            rename_sheet,
+            delete_sheet,
+            get_formula,
+            set_formula,
+            undo,
+            redo,
+            copy_range,
+            insert_rows,
+            delete_rows,
+            insert_cols,
+            delete_cols,
+            list_functions,
+            list_names,
+            define_name,
+            rename_name,
+            delete_name
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
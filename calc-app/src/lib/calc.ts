@@ -4,7 +4,34 @@ export type Address = string;
 export type Value =
 	| { type: 'Number'; value: string }
 	| { type: 'String'; value: string }
-	| { type: 'Error'; value: string };
+	| { type: 'Boolean'; value: boolean }
+	| { type: 'DateTime'; value: string }
+	| { type: 'Error'; value: ErrorKind };
+
+/** the kinds of error values; see `errorCode` for how they are displayed */
+export type ErrorKind =
+	| 'Type'
+	| 'Undefined'
+	| 'Cycle'
+	| 'DivisionByZero'
+	| 'Number'
+	| 'Reference'
+	| 'NotAvailable';
+
+const ERROR_CODES: { [kind in ErrorKind]: string } = {
+	Type: '#VALUE!',
+	Undefined: '#NAME?',
+	Cycle: '#CYCLE',
+	DivisionByZero: '#DIV/0!',
+	Number: '#NUM!',
+	Reference: '#REF!',
+	NotAvailable: '#N/A',
+};
+
+/** the code an error is displayed as, such as `#DIV/0!` */
+export function errorCode(kind: ErrorKind): string {
+	return ERROR_CODES[kind];
+}
 
 export type AnyValue = Value | { type: 'Empty' };
 
@@ -36,13 +63,132 @@ export function getCellAddress(rowIndex: number, colIndex: number): string {
 	return `${getColAddress(colIndex)}${getRowAddress(rowIndex)}`;
 }
 
-export async function getFormula(address: string): Promise<string> {
-	return invoke('get_formula', { address });
+/** the new values of changed cells, by sheet name and address */
+export type Changes = { [sheet: string]: { [address: Address]: AnyValue } };
+
+/** replaces the workbook by the one in the file; returns the values of all its cells */
+export async function openFile(path: string): Promise<Changes> {
+	return invoke('open_file', { path });
+}
+
+export async function saveFile(path: string): Promise<void> {
+	return invoke('save_file', { path });
+}
+
+export async function listSheets(): Promise<string[]> {
+	return invoke('list_sheets');
+}
+
+export type ParameterType = 'any' | 'number' | 'text' | 'boolean' | 'date' | 'range';
+
+export type Parameter = {
+	name: string;
+	type: ParameterType;
+	/** whether the argument may be left out */
+	optional: boolean;
+	/** whether the argument may be repeated */
+	variadic: boolean;
+};
+
+export type FunctionDescription = {
+	name: string;
+	parameters: Parameter[];
+	/** a description of the result, such as `number` */
+	returns: string;
+	/** whether the result may change whenever anything is recalculated, as for `today()` */
+	volatile: boolean;
+	help: string;
+};
+
+/** all functions that can be called from formulas, ordered by name */
+export async function listFunctions(): Promise<FunctionDescription[]> {
+	return invoke('list_functions');
+}
+
+export async function addSheet(name: string): Promise<Changes> {
+	return invoke('add_sheet', { name });
+}
+
+export async function renameSheet(from: string, to: string): Promise<Changes> {
+	return invoke('rename_sheet', { from, to });
+}
+
+export async function deleteSheet(name: string): Promise<Changes> {
+	return invoke('delete_sheet', { name });
+}
+
+/** the definitions of the names defined on a sheet, such as `$B$1` or `0.19`, by name */
+export async function listNames(sheet: string): Promise<{ [name: string]: string }> {
+	return invoke('list_names', { sheet });
+}
+
+/** defines or redefines a name; returns the new values of the cells using it */
+export async function defineName(sheet: string, name: string, definition: string): Promise<Changes> {
+	return invoke('define_name', { sheet, name, definition });
+}
+
+export async function renameName(sheet: string, from: string, to: string): Promise<Changes> {
+	return invoke('rename_name', { sheet, from, to });
+}
+
+export async function deleteName(sheet: string, name: string): Promise<Changes> {
+	return invoke('delete_name', { sheet, name });
+}
+
+export async function getFormula(sheet: string, address: string): Promise<string> {
+	return invoke('get_formula', { sheet, address });
+}
+
+/** where and why a formula could not be parsed; `offset` counts bytes, `column` characters from 1 */
+export type SyntaxError = {
+	offset: number;
+	column: number;
+	/** the text found at the error, or null at the end of the formula */
+	token: string | null;
+	expected: string;
+};
+
+/** the error `setFormula` rejects with; `syntax` is set if the formula is invalid */
+export type SetFormulaError = {
+	message: string;
+	syntax: SyntaxError | null;
+};
+
+/** sets a cell's formula or value; rejects with a `SetFormulaError` */
+export async function setFormula(sheet: string, address: string, formula: string): Promise<Changes> {
+	return invoke('set_formula', { sheet, address, formula });
+}
+
+/** reverts the most recent edit; returns null if there is nothing to undo */
+export async function undo(): Promise<Changes | null> {
+	return invoke('undo');
+}
+
+/** applies the most recently undone edit again; returns null if there is nothing to redo */
+export async function redo(): Promise<Changes | null> {
+	return invoke('redo');
+}
+
+export async function copyRange(
+	sheet: string,
+	source: string,
+	destination: string,
+): Promise<Changes> {
+	return invoke('copy_range', { sheet, source, destination });
+}
+
+export async function insertRows(sheet: string, at: string, count: number): Promise<Changes> {
+	return invoke('insert_rows', { sheet, at, count });
+}
+
+export async function insertCols(sheet: string, at: string, count: number): Promise<Changes> {
+	return invoke('insert_cols', { sheet, at, count });
+}
+
+export async function deleteRows(sheet: string, at: string, count: number): Promise<Changes> {
+	return invoke('delete_rows', { sheet, at, count });
 }
 
-export async function setFormula(
-	address: string,
-	formula: string,
-): Promise<{ [address: Address]: AnyValue }> {
-	return invoke('set_formula', { address, formula });
+export async function deleteCols(sheet: string, at: string, count: number): Promise<Changes> {
+	return invoke('delete_cols', { sheet, at, count });
 }
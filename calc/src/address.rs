@@ -1,5 +1,6 @@
 //! Types for row, column and cell addresses.
 
+use std::cmp;
 use std::fmt;
 use std::num::NonZeroU32;
 use std::str::FromStr;
@@ -9,9 +10,12 @@ use serde::de::Visitor;
 use serde::{Deserialize, Serialize, Serializer};
 
 use crate::parser::range::{
-    parse_cell_address_complete, parse_col_address_complete, parse_row_address_complete,
+    parse_cell_address_complete, parse_cell_range_complete, parse_cell_reference_complete,
+    parse_col_address_complete, parse_range_reference_complete, parse_row_address_complete,
+};
+use crate::parser::{
+    ParseCellAddressError, ParseCellRangeError, ParseColumnAddressError, ParseRowAddressError,
 };
-use crate::parser::{ParseCellAddressError, ParseColumnAddressError, ParseRowAddressError};
 
 /// A row address, which is a positive integer
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -21,6 +25,18 @@ impl RowAddress {
     pub fn new(address: NonZeroU32) -> Self {
         Self(address)
     }
+
+    pub fn get(&self) -> u32 {
+        self.0.get()
+    }
+
+    /// moves the address by the given (possibly negative) number of rows,
+    /// or returns `None` if that would result in an invalid address
+    pub fn offset(&self, offset: i64) -> Option<Self> {
+        let address = i64::from(self.get()).checked_add(offset)?;
+        let address = u32::try_from(address).ok()?;
+        Self::try_from(address).ok()
+    }
 }
 
 impl FromStr for RowAddress {
@@ -95,6 +111,18 @@ impl ColAddress {
     pub fn new(address: NonZeroU32) -> Self {
         Self(address)
     }
+
+    pub fn get(&self) -> u32 {
+        self.0.get()
+    }
+
+    /// moves the address by the given (possibly negative) number of columns,
+    /// or returns `None` if that would result in an invalid address
+    pub fn offset(&self, offset: i64) -> Option<Self> {
+        let address = i64::from(self.get()).checked_add(offset)?;
+        let address = u32::try_from(address).ok()?;
+        Self::try_from(address).ok()
+    }
 }
 
 impl FromStr for ColAddress {
@@ -192,7 +220,8 @@ impl<'de> Deserialize<'de> for ColAddress {
 /// Address for a single cell, which consists of a row and column address.
 /// This is mainly used for identifying cells in internal use;
 /// most user-facing uses of cell addresses are actually single-cell ranges.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Cell addresses are ordered row by row.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct CellAddress {
     row: RowAddress,
     col: ColAddress,
@@ -210,6 +239,12 @@ impl CellAddress {
     pub fn row(&self) -> RowAddress {
         self.row
     }
+
+    /// moves the address by the given (possibly negative) number of rows and columns,
+    /// or returns `None` if that would result in an invalid address
+    pub fn offset(&self, rows: i64, cols: i64) -> Option<Self> {
+        Some(Self::new(self.row.offset(rows)?, self.col.offset(cols)?))
+    }
 }
 
 impl FromStr for CellAddress {
@@ -261,11 +296,292 @@ impl<'de> Deserialize<'de> for CellAddress {
     }
 }
 
+/// A rectangular range of cells such as `A1:C10`, given by its top left and bottom right corners.
+/// A range can be constructed from any two opposite corners; e.g. `C10:A1` results in the same range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CellRange {
+    start: CellAddress,
+    end: CellAddress,
+}
+
+impl CellRange {
+    pub fn new(corner: CellAddress, opposite: CellAddress) -> Self {
+        let start = CellAddress::new(
+            cmp::min(corner.row, opposite.row),
+            cmp::min(corner.col, opposite.col),
+        );
+        let end = CellAddress::new(
+            cmp::max(corner.row, opposite.row),
+            cmp::max(corner.col, opposite.col),
+        );
+        Self { start, end }
+    }
+
+    /// the top left cell of the range
+    pub fn start(&self) -> CellAddress {
+        self.start
+    }
+
+    /// the bottom right cell of the range
+    pub fn end(&self) -> CellAddress {
+        self.end
+    }
+
+    /// the number of rows in this range
+    pub fn height(&self) -> u32 {
+        self.end.row.0.get() - self.start.row.0.get() + 1
+    }
+
+    /// the number of columns in this range
+    pub fn width(&self) -> u32 {
+        self.end.col.0.get() - self.start.col.0.get() + 1
+    }
+
+    /// the number of cells in this range
+    pub fn size(&self) -> u64 {
+        u64::from(self.height()) * u64::from(self.width())
+    }
+
+    pub fn contains(&self, address: &CellAddress) -> bool {
+        (self.start.row..=self.end.row).contains(&address.row)
+            && (self.start.col..=self.end.col).contains(&address.col)
+    }
+
+    /// iterates over all cells of the range, row by row
+    pub fn iter(&self) -> impl Iterator<Item = CellAddress> {
+        let cols = self.start.col.0.get()..=self.end.col.0.get();
+        let rows = self.start.row.0.get()..=self.end.row.0.get();
+
+        rows.flat_map(move |row| {
+            let row = RowAddress::try_from(row).expect("row is within the range");
+            cols.clone().map(move |col| {
+                let col = ColAddress::try_from(col).expect("column is within the range");
+                CellAddress::new(row, col)
+            })
+        })
+    }
+}
+
+impl From<CellAddress> for CellRange {
+    fn from(address: CellAddress) -> Self {
+        Self::new(address, address)
+    }
+}
+
+impl FromStr for CellRange {
+    type Err = ParseCellRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_cell_range_complete(s)
+    }
+}
+
+impl fmt::Display for CellRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.start, self.end)
+    }
+}
+
+impl Serialize for CellRange {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct("$tauri_calc::cell_range", &self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for CellRange {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct CellRangeVisitor;
+
+        impl<'de> Visitor<'de> for CellRangeVisitor {
+            type Value = CellRange;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a cell range such as \"A1:C10\" as a string is expected")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                v.parse().map_err(Error::custom)
+            }
+        }
+
+        deserializer.deserialize_str(CellRangeVisitor)
+    }
+}
+
+/// A reference to a single cell as it appears in a formula, such as `A1`, `$A$1`, `A$1` or `$A1`.
+/// Apart from the referenced address, this records for each axis whether it is absolute (marked by `$`),
+/// i.e. whether it should stay fixed when the formula is moved to a different cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CellReference {
+    address: CellAddress,
+    row_absolute: bool,
+    col_absolute: bool,
+}
+
+impl CellReference {
+    pub fn new(address: CellAddress, row_absolute: bool, col_absolute: bool) -> Self {
+        Self {
+            address,
+            row_absolute,
+            col_absolute,
+        }
+    }
+
+    /// a reference where both row and column are relative, such as `A1`
+    pub fn relative(address: CellAddress) -> Self {
+        Self::new(address, false, false)
+    }
+
+    /// a reference where both row and column are absolute, such as `$A$1`
+    pub fn absolute(address: CellAddress) -> Self {
+        Self::new(address, true, true)
+    }
+
+    pub fn address(&self) -> CellAddress {
+        self.address
+    }
+
+    pub fn is_row_absolute(&self) -> bool {
+        self.row_absolute
+    }
+
+    pub fn is_col_absolute(&self) -> bool {
+        self.col_absolute
+    }
+
+    /// moves the relative parts of the reference by the given number of rows and columns,
+    /// as happens when a formula is copied to a different cell.
+    /// Returns `None` if that would result in an invalid address.
+    pub fn offset(&self, rows: i64, cols: i64) -> Option<Self> {
+        let rows = if self.row_absolute { 0 } else { rows };
+        let cols = if self.col_absolute { 0 } else { cols };
+        let address = self.address.offset(rows, cols)?;
+        Some(Self::new(address, self.row_absolute, self.col_absolute))
+    }
+}
+
+impl From<CellAddress> for CellReference {
+    fn from(address: CellAddress) -> Self {
+        Self::relative(address)
+    }
+}
+
+impl FromStr for CellReference {
+    type Err = ParseCellAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_cell_reference_complete(s)
+    }
+}
+
+impl fmt::Display for CellReference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let col_marker = if self.col_absolute { "$" } else { "" };
+        let row_marker = if self.row_absolute { "$" } else { "" };
+        write!(
+            f,
+            "{}{}{}{}",
+            col_marker, self.address.col, row_marker, self.address.row
+        )
+    }
+}
+
+/// A reference to a range of cells as it appears in a formula, such as `A1:C10` or `$A$1:C10`.
+/// Like [`CellRange`], the corners are normalized to be the top left and bottom right ones;
+/// the absolute markers stay with the row or column they were written on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RangeReference {
+    start: CellReference,
+    end: CellReference,
+}
+
+impl RangeReference {
+    pub fn new(corner: CellReference, opposite: CellReference) -> Self {
+        let (start_row, end_row) = if corner.address.row <= opposite.address.row {
+            (corner, opposite)
+        } else {
+            (opposite, corner)
+        };
+        let (start_col, end_col) = if corner.address.col <= opposite.address.col {
+            (corner, opposite)
+        } else {
+            (opposite, corner)
+        };
+
+        let start = CellReference::new(
+            CellAddress::new(start_row.address.row, start_col.address.col),
+            start_row.row_absolute,
+            start_col.col_absolute,
+        );
+        let end = CellReference::new(
+            CellAddress::new(end_row.address.row, end_col.address.col),
+            end_row.row_absolute,
+            end_col.col_absolute,
+        );
+        Self { start, end }
+    }
+
+    /// the reference to the top left cell of the range
+    pub fn start(&self) -> CellReference {
+        self.start
+    }
+
+    /// the reference to the bottom right cell of the range
+    pub fn end(&self) -> CellReference {
+        self.end
+    }
+
+    /// the referenced range, without the absolute markers
+    pub fn range(&self) -> CellRange {
+        CellRange::new(self.start.address, self.end.address)
+    }
+
+    /// moves the relative parts of both corners by the given number of rows and columns,
+    /// as happens when a formula is copied to a different cell.
+    /// Returns `None` if that would result in an invalid address.
+    pub fn offset(&self, rows: i64, cols: i64) -> Option<Self> {
+        Some(Self::new(
+            self.start.offset(rows, cols)?,
+            self.end.offset(rows, cols)?,
+        ))
+    }
+}
+
+impl From<CellRange> for RangeReference {
+    fn from(range: CellRange) -> Self {
+        Self::new(range.start.into(), range.end.into())
+    }
+}
+
+impl FromStr for RangeReference {
+    type Err = ParseCellRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_range_reference_complete(s)
+    }
+}
+
+impl fmt::Display for RangeReference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.start, self.end)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::identity_op)]
     fn test_display_col() {
         fn col_address(col: u32) -> ColAddress {
             ColAddress::new(col.try_into().unwrap())
@@ -278,4 +594,90 @@ mod tests {
         assert_eq!(format!("{}", col_address(26 * 26 + 26)), "ZZ");
         assert_eq!(format!("{}", col_address(1 * 26 * 26 + 1 * 26 + 1)), "AAA");
     }
+
+    #[test]
+    fn test_cell_range() {
+        let range: CellRange = "C10:A1".parse().unwrap();
+        assert_eq!(range, "A1:C10".parse().unwrap());
+        assert_eq!(range, "A10:C1".parse().unwrap());
+        assert_eq!(range.start(), "A1".parse().unwrap());
+        assert_eq!(range.end(), "C10".parse().unwrap());
+        assert_eq!(range.width(), 3);
+        assert_eq!(range.height(), 10);
+        assert_eq!(format!("{}", range), "A1:C10");
+
+        assert!(range.contains(&"B5".parse().unwrap()));
+        assert!(!range.contains(&"D5".parse().unwrap()));
+        assert!(!range.contains(&"B11".parse().unwrap()));
+
+        let range: CellRange = "A1:B2".parse().unwrap();
+        let cells: Vec<_> = range.iter().map(|address| address.to_string()).collect();
+        assert_eq!(cells, ["A1", "B1", "A2", "B2"]);
+    }
+
+    #[test]
+    fn test_serialize_cell_range() {
+        let range: CellRange = "A1:C10".parse().unwrap();
+        assert_eq!(serde_json::to_string(&range).unwrap(), r#""A1:C10""#);
+        assert_eq!(
+            serde_json::from_str::<CellRange>(r#""C10:A1""#).unwrap(),
+            range
+        );
+    }
+
+    #[test]
+    fn test_cell_reference() {
+        let reference: CellReference = "$B$2".parse().unwrap();
+        assert_eq!(reference.address(), "B2".parse().unwrap());
+        assert!(reference.is_row_absolute());
+        assert!(reference.is_col_absolute());
+
+        let reference: CellReference = "B$2".parse().unwrap();
+        assert!(reference.is_row_absolute());
+        assert!(!reference.is_col_absolute());
+
+        let reference: CellReference = "$B2".parse().unwrap();
+        assert!(!reference.is_row_absolute());
+        assert!(reference.is_col_absolute());
+
+        for reference in ["B2", "$B$2", "B$2", "$B2"] {
+            assert_eq!(
+                format!("{}", reference.parse::<CellReference>().unwrap()),
+                reference
+            );
+        }
+    }
+
+    #[test]
+    fn test_range_reference() {
+        let reference: RangeReference = "$C10:A$1".parse().unwrap();
+        assert_eq!(reference.range(), "A1:C10".parse().unwrap());
+        // the markers stay with their row and column
+        assert_eq!(format!("{}", reference), "A$1:$C10");
+
+        let reference: RangeReference = "$A$1:C10".parse().unwrap();
+        assert_eq!(format!("{}", reference), "$A$1:C10");
+    }
+
+    #[test]
+    fn test_offset() {
+        let reference: CellReference = "B2".parse().unwrap();
+        assert_eq!(reference.offset(1, 2).unwrap(), "D3".parse().unwrap());
+        assert_eq!(reference.offset(-1, -1).unwrap(), "A1".parse().unwrap());
+        assert_eq!(reference.offset(-2, 0), None);
+        assert_eq!(reference.offset(0, -2), None);
+        assert_eq!(reference.offset(i64::from(u32::MAX), 0), None);
+
+        // absolute parts stay the same
+        let reference: CellReference = "$B$2".parse().unwrap();
+        assert_eq!(reference.offset(-5, -5).unwrap(), reference);
+        let reference: CellReference = "B$2".parse().unwrap();
+        assert_eq!(reference.offset(3, 3).unwrap(), "E$2".parse().unwrap());
+        let reference: CellReference = "$B2".parse().unwrap();
+        assert_eq!(reference.offset(3, 3).unwrap(), "$B5".parse().unwrap());
+
+        let reference: RangeReference = "$A1:B2".parse().unwrap();
+        assert_eq!(reference.offset(1, 1).unwrap(), "$A2:C3".parse().unwrap());
+        assert_eq!(reference.offset(0, -2), None);
+    }
 }
@@ -6,6 +6,8 @@ pub struct Cell {
     pub(crate) input: String,
     pub(crate) formula: Formula,
     pub(crate) value: Value,
+    /// whether the value was calculated using a volatile function, so that it needs to be recalculated every time
+    pub(crate) volatile: bool,
 }
 
 impl Cell {
@@ -0,0 +1,410 @@
+//! Importing and exporting sheets as CSV (comma separated values), as described in RFC 4180.
+//!
+//! On import, each field becomes a cell: fields that are numbers or ISO 8601 dates (as they would be entered
+//! into a cell) become number or date cells, all other non-empty fields become text cells. Fields are never interpreted as formulas.
+
+use std::collections::HashMap;
+use std::io;
+
+use nom::combinator::all_consuming;
+use thiserror::Error;
+
+use crate::address::{CellAddress, CellRange};
+use crate::cell::Cell;
+use crate::formula::Formula;
+use crate::parser::datetime::parse_datetime;
+use crate::parser::number::parse_number;
+use crate::sheet::Sheet;
+use crate::value::Value;
+
+#[derive(Debug, Error)]
+pub enum CsvError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("a quoted field starting in line {0} is not terminated")]
+    UnterminatedQuote(usize),
+    #[error("the data does not fit into the sheet at the given origin")]
+    OutOfBounds,
+}
+
+/// How the first record of a CSV file is treated on import
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Header {
+    /// there is no header; the first record is data like all others
+    #[default]
+    None,
+    /// the first record is a header, which is imported as text even if it contains numbers
+    Text,
+    /// the first record is a header, which is not imported
+    Skip,
+}
+
+/// What is exported for each cell
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Content {
+    /// the cells' values, e.g. `2` for a cell containing `=1+1`
+    #[default]
+    Values,
+    /// the cells' inputs, e.g. `=1+1`
+    Inputs,
+}
+
+#[derive(Clone, Debug)]
+pub struct ImportOptions {
+    /// the character separating fields, `,` by default
+    pub delimiter: char,
+    /// the character enclosing fields that contain delimiters, quotes or line breaks, `"` by default.
+    /// Inside a quoted field, the quote character is written twice. `None` disables quoting.
+    pub quote: Option<char>,
+    pub header: Header,
+    /// the cell the first field is imported into; `A1` by default
+    pub origin: CellAddress,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quote: Some('"'),
+            header: Header::default(),
+            origin: "A1".parse().expect("A1 is a valid address"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    /// the character separating fields, `,` by default
+    pub delimiter: char,
+    /// the character enclosing fields that contain delimiters, quotes or line breaks, `"` by default.
+    /// `None` disables quoting, which makes such fields ambiguous.
+    pub quote: Option<char>,
+    pub content: Content,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quote: Some('"'),
+            content: Content::default(),
+        }
+    }
+}
+
+/// Splits CSV data into records of fields.
+/// Records are separated by `\n` or `\r\n`; a line break at the end of the data does not start another record.
+fn read_records(
+    data: &str,
+    delimiter: char,
+    quote: Option<char>,
+) -> Result<Vec<Vec<String>>, CsvError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+
+    let mut chars = data.chars().peekable();
+    while let Some(c) = chars.next() {
+        if Some(c) == quote && field.is_empty() {
+            // a quoted field; read up to the closing quote
+            let start = line;
+            loop {
+                match chars.next() {
+                    Some(c) if Some(c) == quote => {
+                        if chars.peek() == Some(&c) {
+                            // an escaped quote
+                            chars.next();
+                            field.push(c);
+                        } else {
+                            break;
+                        }
+                    }
+                    Some(c) => {
+                        if c == '\n' {
+                            line += 1;
+                        }
+                        field.push(c);
+                    }
+                    None => return Err(CsvError::UnterminatedQuote(start)),
+                }
+            }
+        } else if c == delimiter {
+            record.push(std::mem::take(&mut field));
+        } else if c == '\n' || (c == '\r' && chars.peek() == Some(&'\n')) {
+            if c == '\r' {
+                chars.next();
+            }
+            line += 1;
+            record.push(std::mem::take(&mut field));
+            records.push(std::mem::take(&mut record));
+        } else {
+            field.push(c);
+        }
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// The input and formula of a cell imported from a field, or `None` if the field is empty
+fn import_field(field: String, as_text: bool) -> Option<(String, Formula)> {
+    if field.is_empty() {
+        return None;
+    }
+
+    let trimmed = field.trim();
+    if !as_text {
+        if let Ok((_, serial)) = all_consuming(parse_datetime)(trimmed) {
+            return Some((
+                trimmed.to_string(),
+                Formula::Literal(Value::DateTime(serial)),
+            ));
+        }
+        if let Ok((_, number)) = all_consuming(parse_number)(trimmed) {
+            return Some((trimmed.to_string(), Formula::Literal(Value::Number(number))));
+        }
+    }
+
+    let value = Value::String(field);
+    Some((value.to_string(), Formula::Literal(value)))
+}
+
+/// Imports CSV data into a sheet, overwriting the cells in the imported area
+/// and recalculating all affected cells at once. The import can be undone as a single step.
+/// Returns the new values of all imported and recalculated cells.
+pub fn import<R: io::Read>(
+    sheet: &mut Sheet,
+    mut reader: R,
+    options: &ImportOptions,
+) -> Result<HashMap<CellAddress, Value>, CsvError> {
+    let mut data = String::new();
+    reader.read_to_string(&mut data)?;
+
+    let mut records = read_records(&data, options.delimiter, options.quote)?;
+    if options.header == Header::Skip && !records.is_empty() {
+        records.remove(0);
+    }
+
+    // determine all cells before changing the sheet, so that nothing is imported if the data doesn't fit
+    let mut cells = Vec::new();
+    for (row, record) in records.into_iter().enumerate() {
+        let as_text = row == 0 && options.header == Header::Text;
+        for (col, field) in record.into_iter().enumerate() {
+            let address = options
+                .origin
+                .offset(row as i64, col as i64)
+                .ok_or(CsvError::OutOfBounds)?;
+            let (input, formula) = import_field(field, as_text).unwrap_or_default();
+            cells.push((address, input, formula));
+        }
+    }
+
+    Ok(sheet.edit(cells))
+}
+
+/// Writes a single field, quoting it if necessary
+fn write_field<W: io::Write>(
+    writer: &mut W,
+    field: &str,
+    options: &ExportOptions,
+) -> io::Result<()> {
+    let quote = match options.quote {
+        Some(quote) => quote,
+        None => return write!(writer, "{}", field),
+    };
+
+    let needs_quotes = field.contains([options.delimiter, quote, '\n', '\r']);
+    if !needs_quotes {
+        return write!(writer, "{}", field);
+    }
+
+    let escaped = field.replace(quote, &format!("{}{}", quote, quote));
+    write!(writer, "{}{}{}", quote, escaped, quote)
+}
+
+/// Exports a range of a sheet as CSV, one record per row, with records separated by `\r\n`.
+pub fn export<W: io::Write>(
+    sheet: &Sheet,
+    range: CellRange,
+    mut writer: W,
+    options: &ExportOptions,
+) -> Result<(), CsvError> {
+    let delimiter = options.delimiter.to_string();
+
+    for (i, address) in range.iter().enumerate() {
+        let col = i as u32 % range.width();
+        if col > 0 {
+            writer.write_all(delimiter.as_bytes())?;
+        } else if i > 0 {
+            writer.write_all(b"\r\n")?;
+        }
+
+        let field = match (options.content, sheet.cell(&address)) {
+            (_, None) => String::new(),
+            (Content::Inputs, Some(cell)) => cell.input().to_string(),
+            // text is written without quotes and escapes
+            (
+                Content::Values,
+                Some(Cell {
+                    value: Value::String(value),
+                    ..
+                }),
+            ) => value.clone(),
+            (Content::Values, Some(cell)) => cell.value().to_string(),
+        };
+        write_field(&mut writer, &field, options)?;
+    }
+    writer.write_all(b"\r\n")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::value::Error;
+
+    fn records(data: &str) -> Vec<Vec<String>> {
+        read_records(data, ',', Some('"')).unwrap()
+    }
+
+    #[test]
+    fn test_read_records() {
+        assert_eq!(records("a,b\r\n1,2\n"), [["a", "b"], ["1", "2"]]);
+        assert_eq!(
+            records("a,,\n\nb"),
+            [vec!["a", "", ""], vec![""], vec!["b"]]
+        );
+        assert_eq!(
+            records("\"a,b\",\"say \"\"hi\"\"\",\"line\nbreak\""),
+            [["a,b", "say \"hi\"", "line\nbreak"]]
+        );
+        // quotes inside unquoted fields are kept
+        assert_eq!(records("a\"b"), [["a\"b"]]);
+        assert_eq!(
+            read_records("a;'b;c'", ';', Some('\'')).unwrap(),
+            [["a", "b;c"]]
+        );
+        assert_eq!(read_records("\"a\"", ',', None).unwrap(), [["\"a\""]]);
+        assert!(matches!(
+            read_records("a\n\"b", ',', Some('"')),
+            Err(CsvError::UnterminatedQuote(2))
+        ));
+    }
+
+    fn value(sheet: &Sheet, address: &str) -> Value {
+        sheet.value(&address.parse().unwrap()).into()
+    }
+
+    #[test]
+    fn test_import() {
+        let mut sheet = Sheet::new();
+        sheet
+            .set_cell("A1".parse().unwrap(), "=B2 + B3".to_string())
+            .unwrap();
+
+        let options = ImportOptions {
+            header: Header::Text,
+            origin: "B1".parse().unwrap(),
+            ..Default::default()
+        };
+        let values = import(
+            &mut sheet,
+            "1,x\n 2 ,=A1\n0b11,\"\"\n2024-02-29,".as_bytes(),
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(value(&sheet, "B1"), Value::String("1".into()));
+        assert_eq!(value(&sheet, "B2"), Value::Number(2.into()));
+        assert_eq!(value(&sheet, "C2"), Value::String("=A1".into()));
+        assert_eq!(value(&sheet, "B3"), Value::Number(3.into()));
+        assert_eq!(value(&sheet, "B4"), Value::DateTime(45351.into()));
+        assert!(sheet.cell(&"C3".parse().unwrap()).is_none());
+        assert_eq!(
+            sheet.cell(&"C2".parse().unwrap()).unwrap().input(),
+            "\"=A1\""
+        );
+
+        // dependent cells were recalculated
+        assert_eq!(values[&"A1".parse().unwrap()], Value::Number(5.into()));
+    }
+
+    #[test]
+    fn test_import_skip_header() {
+        let mut sheet = Sheet::new();
+        let options = ImportOptions {
+            header: Header::Skip,
+            ..Default::default()
+        };
+        import(
+            &mut sheet,
+            "a\tb\n1\t2".as_bytes(),
+            &ImportOptions {
+                delimiter: '\t',
+                ..options
+            },
+        )
+        .unwrap();
+        assert_eq!(value(&sheet, "A1"), Value::Number(1.into()));
+        assert_eq!(value(&sheet, "B1"), Value::Number(2.into()));
+        assert!(sheet.cell(&"A2".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_import_out_of_bounds() {
+        let mut sheet = Sheet::new();
+        let origin = CellAddress::new(1.try_into().unwrap(), u32::MAX.try_into().unwrap());
+        sheet.set_cell(origin, "1".to_string()).unwrap();
+
+        // the first field still fits into the last column, but the second doesn't
+        let options = ImportOptions {
+            origin,
+            ..Default::default()
+        };
+        let result = import(&mut sheet, "2,3".as_bytes(), &options);
+        assert!(matches!(result, Err(CsvError::OutOfBounds)));
+        assert_eq!(sheet.value(&origin), Some(&Value::Number(1.into())));
+    }
+
+    #[test]
+    fn test_export() {
+        let mut sheet = Sheet::new();
+        for (address, input) in [
+            ("A1", "1"),
+            ("B1", "\"a, \\\"b\\\"\""),
+            ("A2", "=A1 * 2"),
+            ("B2", "=1/0"),
+        ] {
+            sheet
+                .set_cell(address.parse().unwrap(), input.to_string())
+                .unwrap();
+        }
+        assert_eq!(value(&sheet, "B2"), Value::Error(Error::DivisionByZero));
+
+        let range = "A1:C2".parse().unwrap();
+        let mut buffer = Vec::new();
+        export(&sheet, range, &mut buffer, &Default::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "1,\"a, \"\"b\"\"\",\r\n2,#DIV/0!,\r\n"
+        );
+
+        let options = ExportOptions {
+            delimiter: ';',
+            content: Content::Inputs,
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        export(&sheet, range, &mut buffer, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "1;\"\"\"a, \\\"\"b\\\"\"\"\"\";\r\n=A1 * 2;=1/0;\r\n"
+        );
+    }
+}
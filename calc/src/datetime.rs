@@ -0,0 +1,118 @@
+//! Dates and times, which are represented by serial numbers as in other spreadsheet applications:
+//! the integer part counts days since 1899-12-30, and the fractional part is the time of day,
+//! e.g. `0.5` for noon. This way, dates and times can be used in calculations like other numbers,
+//! e.g. `A1 + 7` is a week after the date in `A1`.
+//!
+//! Unlike in some other applications, 1900 is not treated as a leap year,
+//! so serial numbers only agree with theirs for dates after February 1900.
+
+use bigdecimal::{BigDecimal, ToPrimitive, Zero};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+/// The number of seconds per day, which is the denominator of times
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// The largest serial number of a date, which is that of 9999-12-31
+pub const MAX_SERIAL: i64 = 2_958_465;
+
+/// The date with serial number 0
+pub fn epoch() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1899, 12, 30).expect("the epoch is a valid date")
+}
+
+/// The serial number of a date
+pub fn date_serial(date: NaiveDate) -> i64 {
+    (date - epoch()).num_days()
+}
+
+/// The serial number of a time of day, which is a fraction of a day
+pub fn time_serial(time: NaiveTime) -> BigDecimal {
+    BigDecimal::from(time.num_seconds_from_midnight()) / BigDecimal::from(SECONDS_PER_DAY)
+}
+
+/// The serial number of a date and time
+pub fn serial(datetime: NaiveDateTime) -> BigDecimal {
+    BigDecimal::from(date_serial(datetime.date())) + time_serial(datetime.time())
+}
+
+/// The date and time with the given serial number, rounded to whole seconds,
+/// or `None` if it is outside of the supported range from 1899-12-30 to 9999-12-31.
+pub fn from_serial(serial: &BigDecimal) -> Option<NaiveDateTime> {
+    if serial < &BigDecimal::zero() || serial >= &BigDecimal::from(MAX_SERIAL + 1) {
+        return None;
+    }
+
+    // rounding may result in the next day; `round` can't handle the long results of divisions,
+    // but truncating works for rounding as serial numbers aren't negative
+    let seconds = (serial * BigDecimal::from(SECONDS_PER_DAY) + BigDecimal::new(5.into(), 1))
+        .with_scale(0)
+        .to_i64()?;
+    let datetime = epoch().and_time(NaiveTime::MIN) + Duration::seconds(seconds);
+    (datetime.year() <= 9999).then_some(datetime)
+}
+
+/// Formats a serial number according to ISO 8601, e.g. `2024-02-29`, `13:30:00` or `2024-02-29 13:30:00`:
+/// serial numbers below 1 are times only, and those without a fractional part are dates only.
+/// Returns `None` for serial numbers outside of the supported range.
+pub fn format(serial: &BigDecimal) -> Option<String> {
+    let datetime = from_serial(serial)?;
+    let format = if serial < &BigDecimal::from(1) && !serial.is_zero() {
+        "%H:%M:%S"
+    } else if datetime.time() == NaiveTime::MIN {
+        "%Y-%m-%d"
+    } else {
+        "%Y-%m-%d %H:%M:%S"
+    };
+    Some(datetime.format(format).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(value: &str) -> BigDecimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_serial() {
+        let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+
+        assert_eq!(date_serial(date(1899, 12, 30)), 0);
+        assert_eq!(date_serial(date(1900, 3, 1)), 61);
+        assert_eq!(date_serial(date(2024, 2, 29)), 45351);
+        assert_eq!(date_serial(date(9999, 12, 31)), MAX_SERIAL);
+        assert_eq!(
+            time_serial(NaiveTime::from_hms_opt(18, 0, 0).unwrap()),
+            number("0.75")
+        );
+        assert_eq!(
+            serial(date(2024, 2, 29).and_hms_opt(12, 0, 0).unwrap()),
+            number("45351.5")
+        );
+
+        assert_eq!(
+            from_serial(&number("45351.75")),
+            Some(date(2024, 2, 29).and_hms_opt(18, 0, 0).unwrap())
+        );
+        // rounded to seconds
+        assert_eq!(
+            from_serial(&number("0.9999999")),
+            Some(date(1899, 12, 31).and_hms_opt(0, 0, 0).unwrap())
+        );
+        assert_eq!(from_serial(&number("-1")), None);
+        assert_eq!(from_serial(&number("2958466")), None);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(&number("45351")).unwrap(), "2024-02-29");
+        assert_eq!(
+            format(&number("45351.5625")).unwrap(),
+            "2024-02-29 13:30:00"
+        );
+        assert_eq!(format(&number("0.5625")).unwrap(), "13:30:00");
+        assert_eq!(format(&number("0")).unwrap(), "1899-12-30");
+        assert_eq!(format(&number("3000000")), None);
+    }
+}
@@ -0,0 +1,185 @@
+//! The native file format, which stores each cell's input as JSON.
+//! Formulas and values are not stored; they are restored by parsing and evaluating the inputs when loading.
+//!
+//! A file looks like this:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "sheets": [
+//!     { "name": "Sheet1", "cells": { "A1": "1", "A2": "=A1 * TaxRate" }, "names": { "TaxRate": "0.19" } }
+//!   ]
+//! }
+//! ```
+//!
+//! Files written by older versions are migrated when loading; files written by newer versions are rejected.
+
+use std::collections::BTreeMap;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::address::CellAddress;
+use crate::formula::Formula;
+use crate::workbook::WorkbookError;
+
+/// The version of the file format that is written.
+///
+/// - version 1 stores a list of named sheets
+pub const FORMAT_VERSION: u64 = 1;
+
+#[derive(Debug, Error)]
+pub enum FileError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("the file is not valid: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("the file has no format version")]
+    MissingVersion,
+    #[error("the file's format version {0} is not supported")]
+    UnsupportedVersion(u64),
+    #[error("cell {address} on sheet '{sheet}' contains an invalid formula")]
+    InvalidCell { sheet: String, address: CellAddress },
+    #[error("the name '{name}' on sheet '{sheet}' has an invalid definition")]
+    InvalidName { sheet: String, name: String },
+    #[error(transparent)]
+    Workbook(#[from] WorkbookError),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct SheetFile {
+    pub(crate) name: String,
+    /// the cells' inputs; empty cells are omitted
+    pub(crate) cells: BTreeMap<CellAddress, String>,
+    /// the definitions of the sheet's names; omitted if there are none
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) names: BTreeMap<String, String>,
+}
+
+impl SheetFile {
+    /// Parses the inputs of all cells, failing at the first invalid one.
+    pub(crate) fn parse(self) -> Result<Vec<(CellAddress, String, Formula)>, FileError> {
+        let sheet = self.name;
+        self.cells
+            .into_iter()
+            .map(|(address, input)| match input.parse() {
+                Ok(formula) => Ok((address, input, formula)),
+                Err(_) => Err(FileError::InvalidCell {
+                    sheet: sheet.clone(),
+                    address,
+                }),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct WorkbookFile {
+    version: u64,
+    pub(crate) sheets: Vec<SheetFile>,
+}
+
+impl WorkbookFile {
+    pub(crate) fn new(sheets: Vec<SheetFile>) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            sheets,
+        }
+    }
+
+    pub(crate) fn write<W: io::Write>(&self, writer: W) -> Result<(), FileError> {
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Reads a file of any supported version, migrating it to the current version.
+    ///
+    /// A new format version adds an arm for the previous version, which migrates its JSON to the current version.
+    pub(crate) fn read<R: io::Read>(reader: R) -> Result<Self, FileError> {
+        let file: serde_json::Value = serde_json::from_reader(reader)?;
+
+        let version = file
+            .get("version")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or(FileError::MissingVersion)?;
+
+        let file = match version {
+            FORMAT_VERSION => file,
+            version => return Err(FileError::UnsupportedVersion(version)),
+        };
+
+        Ok(serde_json::from_value(file)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn test_round_trip() {
+        let file = WorkbookFile::new(vec![SheetFile {
+            name: "Sheet1".to_string(),
+            cells: [("A1", "1"), ("A10", "=A1"), ("A2", "\"foo\"")]
+                .into_iter()
+                .map(|(address, input)| (address.parse().unwrap(), input.to_string()))
+                .collect(),
+            names: BTreeMap::new(),
+        }]);
+
+        let mut buffer = Vec::new();
+        file.write(&mut buffer).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "version": 1,
+                "sheets": [{ "name": "Sheet1", "cells": { "A1": "1", "A2": "\"foo\"", "A10": "=A1" } }],
+            })
+        );
+
+        let file = WorkbookFile::read(buffer.as_slice()).unwrap();
+        assert_eq!(file.sheets.len(), 1);
+        assert_eq!(file.sheets[0].cells.len(), 3);
+    }
+
+    #[test]
+    fn test_read_v1() {
+        let file = WorkbookFile::read(
+            r#"{
+                "version": 1,
+                "sheets": [
+                    { "name": "Sheet1", "cells": { "A1": "1", "A2": "=A1 * TaxRate" }, "names": { "TaxRate": "0.19" } },
+                    { "name": "Sheet2", "cells": {} }
+                ]
+            }"#
+            .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(file.version, FORMAT_VERSION);
+        assert_eq!(file.sheets.len(), 2);
+        assert_eq!(file.sheets[0].name, "Sheet1");
+        assert_eq!(
+            file.sheets[0].cells[&"A2".parse().unwrap()],
+            "=A1 * TaxRate"
+        );
+        assert_eq!(file.sheets[0].names["TaxRate"], "0.19");
+        assert!(file.sheets[1].cells.is_empty());
+        assert!(file.sheets[1].names.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_version() {
+        assert!(matches!(
+            WorkbookFile::read(r#"{ "sheets": [] }"#.as_bytes()),
+            Err(FileError::MissingVersion)
+        ));
+        assert!(matches!(
+            WorkbookFile::read(r#"{ "version": 2, "sheets": [] }"#.as_bytes()),
+            Err(FileError::UnsupportedVersion(2))
+        ));
+    }
+}
@@ -3,28 +3,117 @@
 //! If `1` is put into a cell, the "formula" is a literal value
 
 pub mod expression;
+pub mod function;
+pub mod operator;
+pub mod registry;
+pub mod signature;
 
+use std::fmt;
 use std::str::FromStr;
 
-use crate::address::CellAddress;
+use crate::address::{CellAddress, CellReference, RangeReference};
 use crate::parser::{parse_cell_complete, ParseFormulaError};
-use crate::sheet::Sheet;
-use crate::value::Value;
+use crate::value::{Error, Value};
 
 use self::expression::Expression;
+use self::registry::Definition;
+
+/// The environment in which formulas are evaluated, providing cell values and functions.
+pub trait Context {
+    /// The value of a cell. `sheet` is the name of the referenced sheet for references such as `Sheet2!A1`,
+    /// or `None` for references to the sheet the formula is in.
+    /// If the sheet doesn't exist, the result is a `#REF!` error.
+    fn value(&self, sheet: Option<&str>, address: &CellAddress) -> Value;
+
+    /// Whether there is a sheet with the given name that references such as `Sheet2!A1:B3` can refer to.
+    fn has_sheet(&self, sheet: &str) -> bool;
+
+    /// The cell whose formula is evaluated, or `None` if the expression is not evaluated as part of a cell.
+    fn cell(&self) -> Option<CellAddress>;
+
+    /// The function with the given name, if there is one. Function names are case-insensitive.
+    fn function(&self, name: &str) -> Option<&Definition>;
+
+    /// The expression a name such as `TaxRate` stands for, if the name is defined. Names are case-insensitive.
+    fn named_expression(&self, name: &str) -> Option<&Expression>;
+}
+
+/// Whether two names of sheets, defined names or functions are the same; all of them are case-insensitive.
+pub(crate) fn same_name(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
 
 pub trait Evaluate {
+    /// visits all cells on the formula's own sheet that the formula depends on
     fn visit_dependecies<F: FnMut(CellAddress)>(&self, visitor: &mut F);
 
-    fn evaluate(&self, context: &Sheet) -> Value;
+    /// visits all cells on other sheets that the formula depends on, together with the sheets' names
+    fn visit_external_dependencies<F: FnMut(&str, CellAddress)>(&self, visitor: &mut F);
+
+    /// visits all names that the formula uses
+    fn visit_names<F: FnMut(&str)>(&self, visitor: &mut F);
+
+    fn evaluate(&self, context: &dyn Context) -> Value;
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Formula {
     Literal(Value),
     Formula(Expression),
 }
 
+impl Formula {
+    /// Moves all relative references in this formula by the given number of rows and columns,
+    /// as happens when a formula is copied to a different cell.
+    /// See [`Expression::offset`].
+    pub fn offset(&self, rows: i64, cols: i64) -> Self {
+        match self {
+            Self::Literal(value) => Self::Literal(value.clone()),
+            Self::Formula(expression) => Self::Formula(expression.offset(rows, cols)),
+        }
+    }
+
+    /// Replaces all references in this formula using the given functions.
+    /// See [`Expression::map_references`].
+    pub fn map_references<C, R>(&self, cell: &C, range: &R) -> Self
+    where
+        C: Fn(Option<&str>, CellReference) -> Option<CellReference>,
+        R: Fn(Option<&str>, RangeReference) -> Option<RangeReference>,
+    {
+        match self {
+            Self::Literal(value) => Self::Literal(value.clone()),
+            Self::Formula(expression) => Self::Formula(expression.map_references(cell, range)),
+        }
+    }
+
+    /// Replaces the sheet name `from` by `to` in all references to another sheet.
+    /// See [`Expression::rename_sheet`].
+    pub fn rename_sheet(&self, from: &str, to: &str) -> Self {
+        match self {
+            Self::Literal(value) => Self::Literal(value.clone()),
+            Self::Formula(expression) => Self::Formula(expression.rename_sheet(from, to)),
+        }
+    }
+
+    /// Replaces the name `from` by `to`.
+    /// See [`Expression::rename_name`].
+    pub fn rename_name(&self, from: &str, to: &str) -> Self {
+        match self {
+            Self::Literal(value) => Self::Literal(value.clone()),
+            Self::Formula(expression) => Self::Formula(expression.rename_name(from, to)),
+        }
+    }
+
+    /// Replaces the names of called functions using the given function.
+    /// See [`Expression::rename_functions`].
+    pub fn rename_functions<F: Fn(&str) -> Option<String>>(&self, rename: &F) -> Self {
+        match self {
+            Self::Literal(value) => Self::Literal(value.clone()),
+            Self::Formula(expression) => Self::Formula(expression.rename_functions(rename)),
+        }
+    }
+}
+
 impl FromStr for Formula {
     type Err = ParseFormulaError;
 
@@ -33,6 +122,15 @@ impl FromStr for Formula {
     }
 }
 
+impl fmt::Display for Formula {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Literal(value) => write!(f, "{}", value),
+            Self::Formula(expression) => write!(f, "={}", expression),
+        }
+    }
+}
+
 impl Default for Formula {
     fn default() -> Self {
         Self::Literal(Value::default())
@@ -47,10 +145,31 @@ impl Evaluate for Formula {
         }
     }
 
-    fn evaluate(&self, context: &Sheet) -> Value {
+    fn visit_external_dependencies<F: FnMut(&str, CellAddress)>(&self, visitor: &mut F) {
+        match self {
+            Self::Literal(_value) => {}
+            Self::Formula(expression) => expression.visit_external_dependencies(visitor),
+        }
+    }
+
+    fn visit_names<F: FnMut(&str)>(&self, visitor: &mut F) {
+        match self {
+            Self::Literal(_value) => {}
+            Self::Formula(expression) => expression.visit_names(visitor),
+        }
+    }
+
+    fn evaluate(&self, context: &dyn Context) -> Value {
         match self {
             Self::Literal(value) => value.clone(),
-            Self::Formula(expression) => expression.evaluate(context),
+            Self::Formula(expression) => match expression.evaluate(context) {
+                // a cell can only hold a single value, not a whole range
+                Value::Array(array) => match array.values() {
+                    [value] => value.clone(),
+                    _ => Value::Error(Error::Value),
+                },
+                value => value,
+            },
         }
     }
 }
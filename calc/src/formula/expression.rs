@@ -1,40 +1,488 @@
-use crate::address::CellAddress;
-use crate::sheet::Sheet;
-use crate::value::Value;
+use std::fmt;
 
-use super::Evaluate;
+use crate::address::{CellAddress, CellReference, RangeReference};
+use crate::parser::format_sheet_name;
+use crate::value::{Array, Error, Value};
 
-#[derive(Clone, Debug)]
+use super::operator::{BinaryOperator, UnaryOperator};
+use super::{same_name, Context, Evaluate};
+
+/// Ranges with more cells than this evaluate to `#NUM!` instead of an array, and cells don't depend on them.
+pub const MAX_RANGE_SIZE: u64 = 100_000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Expression {
     Literal(Value),
-    Reference(CellAddress),
+    /// a reference to a single cell such as `A1`.
+    /// If `sheet` is given, the cell is on another sheet, as in `Sheet2!A1`.
+    Reference {
+        sheet: Option<String>,
+        reference: CellReference,
+    },
+    /// a reference to a range of cells such as `A1:B3`.
+    /// If `sheet` is given, the range is on another sheet, as in `Sheet2!A1:B3`.
+    Range {
+        sheet: Option<String>,
+        reference: RangeReference,
+    },
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<Expression>,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
     Call {
         name: String,
         arguments: Vec<Expression>,
     },
+    /// a name such as `TaxRate`, which stands for a reference, range or constant defined on the sheet
+    Name(String),
+}
+
+impl Expression {
+    /// Moves all relative references in this expression by the given number of rows and columns,
+    /// as happens when a formula is copied to a different cell.
+    /// References that would point outside the sheet are replaced by a `#REF!` error.
+    pub fn offset(&self, rows: i64, cols: i64) -> Self {
+        self.map_references(
+            &|_sheet, reference| reference.offset(rows, cols),
+            &|_sheet, reference| reference.offset(rows, cols),
+        )
+    }
+
+    /// Replaces all references in this expression using the given functions,
+    /// one for single cell references and one for range references.
+    /// The functions also receive the sheet the reference refers to, if it is not the formula's own sheet.
+    /// References for which the functions return `None` are replaced by a `#REF!` error.
+    pub fn map_references<C, R>(&self, cell: &C, range: &R) -> Self
+    where
+        C: Fn(Option<&str>, CellReference) -> Option<CellReference>,
+        R: Fn(Option<&str>, RangeReference) -> Option<RangeReference>,
+    {
+        let invalid = || Self::Literal(Value::Error(Error::Reference));
+
+        match self {
+            Self::Literal(value) => Self::Literal(value.clone()),
+            Self::Reference { sheet, reference } => {
+                cell(sheet.as_deref(), *reference).map_or_else(invalid, |reference| {
+                    Self::Reference {
+                        sheet: sheet.clone(),
+                        reference,
+                    }
+                })
+            }
+            Self::Range { sheet, reference } => {
+                range(sheet.as_deref(), *reference).map_or_else(invalid, |reference| Self::Range {
+                    sheet: sheet.clone(),
+                    reference,
+                })
+            }
+            Self::Unary { operator, operand } => Self::Unary {
+                operator: *operator,
+                operand: Box::new(operand.map_references(cell, range)),
+            },
+            Self::Binary {
+                operator,
+                left,
+                right,
+            } => Self::Binary {
+                operator: *operator,
+                left: Box::new(left.map_references(cell, range)),
+                right: Box::new(right.map_references(cell, range)),
+            },
+            Self::Call { name, arguments } => Self::Call {
+                name: name.clone(),
+                arguments: arguments
+                    .iter()
+                    .map(|arg| arg.map_references(cell, range))
+                    .collect(),
+            },
+            Self::Name(name) => Self::Name(name.clone()),
+        }
+    }
+
+    /// Replaces the sheet name `from` by `to` in all references to another sheet,
+    /// as happens when a sheet is renamed. Sheet names are compared case-insensitively.
+    pub fn rename_sheet(&self, from: &str, to: &str) -> Self {
+        let rename = |sheet: &Option<String>| match sheet {
+            Some(sheet) if same_name(sheet, from) => Some(to.to_string()),
+            sheet => sheet.clone(),
+        };
+
+        match self {
+            Self::Literal(value) => Self::Literal(value.clone()),
+            Self::Reference { sheet, reference } => Self::Reference {
+                sheet: rename(sheet),
+                reference: *reference,
+            },
+            Self::Range { sheet, reference } => Self::Range {
+                sheet: rename(sheet),
+                reference: *reference,
+            },
+            Self::Unary { operator, operand } => Self::Unary {
+                operator: *operator,
+                operand: Box::new(operand.rename_sheet(from, to)),
+            },
+            Self::Binary {
+                operator,
+                left,
+                right,
+            } => Self::Binary {
+                operator: *operator,
+                left: Box::new(left.rename_sheet(from, to)),
+                right: Box::new(right.rename_sheet(from, to)),
+            },
+            Self::Call { name, arguments } => Self::Call {
+                name: name.clone(),
+                arguments: arguments
+                    .iter()
+                    .map(|arg| arg.rename_sheet(from, to))
+                    .collect(),
+            },
+            Self::Name(name) => Self::Name(name.clone()),
+        }
+    }
+
+    /// Replaces the name `from` by `to`, as happens when a name is renamed. Names are compared case-insensitively.
+    pub fn rename_name(&self, from: &str, to: &str) -> Self {
+        match self {
+            Self::Literal(_) | Self::Reference { .. } | Self::Range { .. } => self.clone(),
+            Self::Unary { operator, operand } => Self::Unary {
+                operator: *operator,
+                operand: Box::new(operand.rename_name(from, to)),
+            },
+            Self::Binary {
+                operator,
+                left,
+                right,
+            } => Self::Binary {
+                operator: *operator,
+                left: Box::new(left.rename_name(from, to)),
+                right: Box::new(right.rename_name(from, to)),
+            },
+            Self::Call { name, arguments } => Self::Call {
+                name: name.clone(),
+                arguments: arguments
+                    .iter()
+                    .map(|arg| arg.rename_name(from, to))
+                    .collect(),
+            },
+            Self::Name(name) if same_name(name, from) => Self::Name(to.to_string()),
+            Self::Name(name) => Self::Name(name.clone()),
+        }
+    }
+
+    /// Replaces the names of called functions using the given function; names for which it returns `None` are kept.
+    pub fn rename_functions<F: Fn(&str) -> Option<String>>(&self, rename: &F) -> Self {
+        match self {
+            Self::Literal(_) | Self::Reference { .. } | Self::Range { .. } | Self::Name(_) => {
+                self.clone()
+            }
+            Self::Unary { operator, operand } => Self::Unary {
+                operator: *operator,
+                operand: Box::new(operand.rename_functions(rename)),
+            },
+            Self::Binary {
+                operator,
+                left,
+                right,
+            } => Self::Binary {
+                operator: *operator,
+                left: Box::new(left.rename_functions(rename)),
+                right: Box::new(right.rename_functions(rename)),
+            },
+            Self::Call { name, arguments } => Self::Call {
+                name: rename(name).unwrap_or_else(|| name.clone()),
+                arguments: arguments
+                    .iter()
+                    .map(|arg| arg.rename_functions(rename))
+                    .collect(),
+            },
+        }
+    }
+
+    /// The precedence of the expression's outermost operator, used for deciding where parentheses are needed.
+    /// Expressions without operators bind most tightly.
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Unary { operator, .. } => operator.precedence(),
+            Self::Binary { operator, .. } => operator.precedence(),
+            _ => u8::MAX,
+        }
+    }
+}
+
+/// The cells a range depends on; ranges that are too large to be evaluated don't depend on any cells.
+fn dependencies(reference: &RangeReference) -> impl Iterator<Item = CellAddress> {
+    let range = reference.range();
+    (range.size() <= MAX_RANGE_SIZE)
+        .then(|| range.iter())
+        .into_iter()
+        .flatten()
 }
 
 impl Evaluate for Expression {
     fn visit_dependecies<F: FnMut(CellAddress)>(&self, visitor: &mut F) {
         match self {
             Self::Literal(_value) => {}
-            Self::Reference(address) => visitor(*address),
+            Self::Reference {
+                sheet: None,
+                reference,
+            } => visitor(reference.address()),
+            Self::Range {
+                sheet: None,
+                reference,
+            } => dependencies(reference).for_each(visitor),
+            Self::Reference { .. } | Self::Range { .. } => {}
+            Self::Unary { operand, .. } => operand.visit_dependecies(visitor),
+            Self::Binary { left, right, .. } => {
+                left.visit_dependecies(visitor);
+                right.visit_dependecies(visitor);
+            }
             Self::Call { arguments, .. } => {
                 for arg in arguments {
                     arg.visit_dependecies(visitor);
                 }
             }
+            Self::Name(_) => {}
+        }
+    }
+
+    fn visit_external_dependencies<F: FnMut(&str, CellAddress)>(&self, visitor: &mut F) {
+        match self {
+            Self::Literal(_value) => {}
+            Self::Reference {
+                sheet: Some(sheet),
+                reference,
+            } => visitor(sheet, reference.address()),
+            Self::Range {
+                sheet: Some(sheet),
+                reference,
+            } => {
+                for address in dependencies(reference) {
+                    visitor(sheet, address);
+                }
+            }
+            Self::Reference { .. } | Self::Range { .. } => {}
+            Self::Unary { operand, .. } => operand.visit_external_dependencies(visitor),
+            Self::Binary { left, right, .. } => {
+                left.visit_external_dependencies(visitor);
+                right.visit_external_dependencies(visitor);
+            }
+            Self::Call { arguments, .. } => {
+                for arg in arguments {
+                    arg.visit_external_dependencies(visitor);
+                }
+            }
+            Self::Name(_) => {}
+        }
+    }
+
+    fn visit_names<F: FnMut(&str)>(&self, visitor: &mut F) {
+        match self {
+            Self::Literal(_) | Self::Reference { .. } | Self::Range { .. } => {}
+            Self::Unary { operand, .. } => operand.visit_names(visitor),
+            Self::Binary { left, right, .. } => {
+                left.visit_names(visitor);
+                right.visit_names(visitor);
+            }
+            Self::Call { arguments, .. } => {
+                for arg in arguments {
+                    arg.visit_names(visitor);
+                }
+            }
+            Self::Name(name) => visitor(name),
         }
     }
 
-    fn evaluate(&self, context: &Sheet) -> Value {
+    fn evaluate(&self, context: &dyn Context) -> Value {
         match self {
             Self::Literal(value) => value.clone(),
-            Self::Reference(address) => context.value(address).into(),
+            Self::Reference { sheet, reference } => {
+                context.value(sheet.as_deref(), &reference.address())
+            }
+            Self::Range { sheet, reference } => {
+                // the whole range is invalid if the sheet doesn't exist, while errors in its cells are kept
+                if let Some(name) = sheet {
+                    if !context.has_sheet(name) {
+                        return Value::Error(Error::Reference);
+                    }
+                }
+                let range = reference.range();
+                if range.size() > MAX_RANGE_SIZE {
+                    return Value::Error(Error::Number);
+                }
+                let values = range
+                    .iter()
+                    .map(|address| context.value(sheet.as_deref(), &address))
+                    .collect();
+                Value::Array(Array::new(range.height(), range.width(), values))
+            }
+            Self::Unary { operator, operand } => operator.apply(&operand.evaluate(context)),
+            Self::Binary {
+                operator,
+                left,
+                right,
+            } => operator.apply(&left.evaluate(context), &right.evaluate(context)),
+            Self::Call { name, arguments } => match context.function(name) {
+                Some(function) => function.call(arguments, context),
+                None => Value::Error(Error::Name),
+            },
+            Self::Name(name) => match context.named_expression(name) {
+                Some(expression) => expression.evaluate(context),
+                None => Value::Error(Error::Name),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        /// writes an operand, wrapped in parentheses if it binds less tightly than required
+        fn operand(f: &mut fmt::Formatter, operand: &Expression, precedence: u8) -> fmt::Result {
+            if operand.precedence() < precedence {
+                write!(f, "({})", operand)
+            } else {
+                write!(f, "{}", operand)
+            }
+        }
+
+        match self {
+            Self::Literal(value) => write!(f, "{}", value),
+            Self::Reference { sheet, reference } => {
+                if let Some(sheet) = sheet {
+                    write!(f, "{}!", format_sheet_name(sheet))?;
+                }
+                write!(f, "{}", reference)
+            }
+            Self::Range { sheet, reference } => {
+                if let Some(sheet) = sheet {
+                    write!(f, "{}!", format_sheet_name(sheet))?;
+                }
+                write!(f, "{}", reference)
+            }
+            Self::Unary {
+                operator: operator @ UnaryOperator::Percent,
+                operand: inner,
+            } => {
+                operand(f, inner, operator.precedence())?;
+                write!(f, "{}", operator)
+            }
+            Self::Unary {
+                operator,
+                operand: inner,
+            } => {
+                write!(f, "{}", operator)?;
+                operand(f, inner, operator.precedence())
+            }
+            Self::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                // binary operators are left associative,
+                // so the right operand needs parentheses even at the same precedence
+                operand(f, left, operator.precedence())?;
+                write!(f, " {} ", operator)?;
+                operand(f, right, operator.precedence() + 1)
+            }
             Self::Call { name, arguments } => {
-                let arguments: Vec<_> = arguments.iter().map(|arg| arg.evaluate(context)).collect();
-                context.call(name, &arguments)
+                write!(f, "{}(", name)?;
+                for (i, arg) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
             }
+            Self::Name(name) => write!(f, "{}", name),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::formula::Formula;
+
+    fn expression(input: &str) -> Expression {
+        match input.parse().unwrap() {
+            Formula::Formula(expression) => expression,
+            formula => panic!("not a formula: {:?}", formula),
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        for (input, output) in [
+            ("=1", "1"),
+            ("=\"foo\"", "\"foo\""),
+            ("=$A1", "$A1"),
+            ("=A1:$B$2", "A1:$B$2"),
+            ("=1+2*3", "1 + 2 * 3"),
+            ("=(1+2)*3", "(1 + 2) * 3"),
+            ("=1-(2-3)", "1 - (2 - 3)"),
+            ("=(1-2)-3", "1 - 2 - 3"),
+            ("=2^(3^2)", "2 ^ (3 ^ 2)"),
+            ("=-A1^2", "-A1 ^ 2"),
+            ("=-(A1^2)", "-(A1 ^ 2)"),
+            ("=(-A1)%", "-A1%"),
+            ("=-(A1%)", "-(A1%)"),
+            ("=(A1+1)%", "(A1 + 1)%"),
+            ("=sum( A1:B2 ,(1) )", "sum(A1:B2, 1)"),
+            ("=Sheet2!A1 + sheet_2!$B$2", "Sheet2!A1 + sheet_2!$B$2"),
+            ("='My Sheet'!A1:B2", "'My Sheet'!A1:B2"),
+            ("='It''s'!A1", "'It''s'!A1"),
+            ("='2024'!A1", "'2024'!A1"),
+            ("=true", "TRUE"),
+            ("=A1+1>=2*B1", "A1 + 1 >= 2 * B1"),
+            ("=(1<2)=FALSE", "1 < 2 = FALSE"),
+            ("=1=(2<>3)", "1 = (2 <> 3)"),
+            ("=A1&\"x\"&(1+2)", "A1 & \"x\" & 1 + 2"),
+            ("=(A1=1)&B1", "(A1 = 1) & B1"),
+            ("=sum(Revenue)*TaxRate", "sum(Revenue) * TaxRate"),
+        ] {
+            assert_eq!(expression(input).to_string(), output);
+        }
+    }
+
+    #[test]
+    fn test_offset() {
+        for (input, rows, cols, output) in [
+            ("=A1 + $A$1 + A$1 + $A1", 1, 2, "C2 + $A$1 + C$1 + $A2"),
+            ("=sum(A1:$B2)", 2, 1, "sum(B3:$B4)"),
+            ("=A2 * 2", -1, 0, "A1 * 2"),
+            ("=A1 * 2", -1, 0, "#REF! * 2"),
+            ("=sum(A1:B2)", 0, -1, "sum(#REF!)"),
+            ("=$A$1", -1, -1, "$A$1"),
+            ("=Sheet2!A1", 1, 1, "Sheet2!B2"),
+        ] {
+            assert_eq!(expression(input).offset(rows, cols).to_string(), output);
+        }
+    }
+
+    #[test]
+    fn test_rename_name() {
+        assert_eq!(
+            expression("=sum(Revenue) * taxrate + TaxRates")
+                .rename_name("TaxRate", "Vat")
+                .to_string(),
+            "sum(Revenue) * Vat + TaxRates"
+        );
+    }
+
+    #[test]
+    fn test_rename_sheet() {
+        assert_eq!(
+            expression("=Sheet1!A1 + SHEET1!A2 + Sheet2!A3 + A4")
+                .rename_sheet("sheet1", "My Sheet")
+                .to_string(),
+            "'My Sheet'!A1 + 'My Sheet'!A2 + Sheet2!A3 + A4"
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Functions that can be called from formulas, such as `sum(A1:B3)`.
+//!
+//! There are two kinds of functions: most only need the values of their arguments, which are evaluated before
+//! the function is called. Lazy functions instead receive their arguments unevaluated and decide themselves
+//! which arguments to evaluate, and can also look at the arguments' expressions, e.g. to find out which
+//! cells a reference refers to.
+
+use std::fmt;
+
+use crate::value::Value;
+
+use super::expression::Expression;
+use super::{Context, Evaluate};
+
+/// A function that receives the values of its arguments
+pub type ValueFunction = dyn Send + Sync + Fn(&[Value]) -> Value;
+
+/// A function that receives its unevaluated arguments and the context the formula is evaluated in
+pub type LazyFunction = dyn Send + Sync + Fn(&[Argument], &dyn Context) -> Value;
+
+/// A function that can be called from formulas
+pub enum Function {
+    Value(Box<ValueFunction>),
+    Lazy(Box<LazyFunction>),
+}
+
+impl Function {
+    /// Calls the function with the given (unevaluated) arguments.
+    pub fn call(&self, arguments: &[Expression], context: &dyn Context) -> Value {
+        match self {
+            Self::Value(function) => {
+                let arguments: Vec<_> = arguments.iter().map(|arg| arg.evaluate(context)).collect();
+                function(&arguments)
+            }
+            Self::Lazy(function) => {
+                let arguments: Vec<_> = arguments
+                    .iter()
+                    .map(|expression| Argument::new(expression, context))
+                    .collect();
+                function(&arguments, context)
+            }
+        }
+    }
+
+    /// Calls the function with arguments that have already been evaluated.
+    pub fn call_with_values(&self, arguments: &[Value], context: &dyn Context) -> Value {
+        match self {
+            Self::Value(function) => function(arguments),
+            Self::Lazy(_) => {
+                let arguments: Vec<_> =
+                    arguments.iter().cloned().map(Expression::Literal).collect();
+                self.call(&arguments, context)
+            }
+        }
+    }
+}
+
+/// An unevaluated argument of a lazy function.
+/// It is evaluated each time [`Argument::evaluate`] is called.
+#[derive(Clone, Copy)]
+pub struct Argument<'a> {
+    expression: &'a Expression,
+    context: &'a dyn Context,
+}
+
+impl<'a> Argument<'a> {
+    pub fn new(expression: &'a Expression, context: &'a dyn Context) -> Self {
+        Self {
+            expression,
+            context,
+        }
+    }
+
+    /// the argument as it was written in the formula
+    pub fn expression(&self) -> &'a Expression {
+        self.expression
+    }
+
+    pub fn evaluate(&self) -> Value {
+        self.expression.evaluate(self.context)
+    }
+}
+
+impl fmt::Debug for Argument<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Argument").field(self.expression).finish()
+    }
+}
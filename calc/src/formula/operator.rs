@@ -0,0 +1,419 @@
+//! Unary and binary operators that can be used in expressions, such as `-A1` or `A1 + B1 * 2`.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use bigdecimal::{BigDecimal, Zero};
+
+use crate::functions::math::{inexact, power_of};
+use crate::functions::text::limited;
+use crate::value::{Error, Value};
+
+/// An operator with a single operand.
+/// Apart from the prefix `+` and `-`, this includes the postfix `%`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOperator {
+    /// `+x`, which is the number itself
+    Plus,
+    /// `-x`, which negates the number
+    Minus,
+    /// `x%`, which divides the number by 100
+    Percent,
+}
+
+impl UnaryOperator {
+    /// The precedence of the operator; operators with higher precedence bind more tightly.
+    /// Unary operators bind more tightly than all binary operators.
+    pub fn precedence(self) -> u8 {
+        match self {
+            Self::Percent => 5,
+            Self::Plus | Self::Minus => 6,
+        }
+    }
+
+    pub fn apply(self, operand: &Value) -> Value {
+        fn inner(operator: UnaryOperator, operand: &Value) -> Result<BigDecimal, Error> {
+            let operand = operand.as_number()?;
+
+            let result = match operator {
+                UnaryOperator::Plus => operand.clone(),
+                UnaryOperator::Minus => -operand,
+                UnaryOperator::Percent => operand / BigDecimal::from(100),
+            };
+            Ok(result)
+        }
+
+        inner(self, operand).map_or_else(Value::Error, Value::Number)
+    }
+}
+
+impl fmt::Display for UnaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Plus => write!(f, "+"),
+            Self::Minus => write!(f, "-"),
+            Self::Percent => write!(f, "%"),
+        }
+    }
+}
+
+/// An operator with two operands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOperator {
+    /// `x + y`
+    Add,
+    /// `x - y`
+    Subtract,
+    /// `x * y`
+    Multiply,
+    /// `x / y`
+    Divide,
+    /// `x ^ y`
+    Power,
+    /// `x & y`, which joins the operands as text
+    Concatenate,
+    /// `x = y`
+    Equal,
+    /// `x <> y`
+    NotEqual,
+    /// `x < y`
+    Less,
+    /// `x <= y`
+    LessEqual,
+    /// `x > y`
+    Greater,
+    /// `x >= y`
+    GreaterEqual,
+}
+
+impl BinaryOperator {
+    /// The precedence of the operator; operators with higher precedence bind more tightly.
+    /// All binary operators are left associative, i.e. `2^3^2` is `(2^3)^2`.
+    pub fn precedence(self) -> u8 {
+        match self {
+            Self::Equal
+            | Self::NotEqual
+            | Self::Less
+            | Self::LessEqual
+            | Self::Greater
+            | Self::GreaterEqual => 0,
+            Self::Concatenate => 1,
+            Self::Add | Self::Subtract => 2,
+            Self::Multiply | Self::Divide => 3,
+            Self::Power => 4,
+        }
+    }
+
+    /// Applies the operator. Comparison operators result in booleans (see [`Value::compare`]),
+    /// `&` joins its operands as text (see [`Value::to_text`]), up to [`MAX_LENGTH`] characters,
+    /// and all others require numbers, which may be dates: adding days or a time to a date,
+    /// or subtracting days from it, results in a date.
+    ///
+    /// [`MAX_LENGTH`]: crate::functions::text::MAX_LENGTH
+    pub fn apply(self, left: &Value, right: &Value) -> Value {
+        if let Some(matches) = self.comparison() {
+            return left
+                .compare(right)
+                .map_or_else(Value::Error, |ordering| Value::Boolean(matches(ordering)));
+        }
+        if self == Self::Concatenate {
+            let concatenate = || limited(left.to_text()?.into_owned() + &right.to_text()?);
+            return concatenate().map_or_else(Value::Error, Value::String);
+        }
+
+        fn inner(
+            operator: BinaryOperator,
+            left: &Value,
+            right: &Value,
+        ) -> Result<BigDecimal, Error> {
+            let left = left.as_number()?;
+            let right = right.as_number()?;
+
+            match operator {
+                BinaryOperator::Add => Ok(left + right),
+                BinaryOperator::Subtract => Ok(left - right),
+                BinaryOperator::Multiply => Ok(left * right),
+                BinaryOperator::Divide => {
+                    if right.is_zero() {
+                        return Err(Error::DivisionByZero);
+                    }
+                    Ok(left / right)
+                }
+                BinaryOperator::Power => power(left, right),
+                _ => unreachable!("comparison and text operators are handled separately"),
+            }
+        }
+
+        // adding days or a time to a date, or subtracting days from it, results in a date again
+        let date = matches!(
+            (self, left, right),
+            (
+                Self::Add,
+                Value::DateTime(_),
+                Value::Number(_) | Value::DateTime(_) | Value::Empty
+            ) | (
+                Self::Add,
+                Value::Number(_) | Value::Empty,
+                Value::DateTime(_)
+            ) | (
+                Self::Subtract,
+                Value::DateTime(_),
+                Value::Number(_) | Value::Empty
+            )
+        );
+        match inner(self, left, right) {
+            Ok(result) if date => Value::DateTime(result),
+            result => result.map_or_else(Value::Error, Value::Number),
+        }
+    }
+
+    /// for comparison operators, which orderings of the operands make the comparison true
+    fn comparison(self) -> Option<fn(Ordering) -> bool> {
+        match self {
+            Self::Equal => Some(Ordering::is_eq),
+            Self::NotEqual => Some(Ordering::is_ne),
+            Self::Less => Some(Ordering::is_lt),
+            Self::LessEqual => Some(Ordering::is_le),
+            Self::Greater => Some(Ordering::is_gt),
+            Self::GreaterEqual => Some(Ordering::is_ge),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Add => write!(f, "+"),
+            Self::Subtract => write!(f, "-"),
+            Self::Multiply => write!(f, "*"),
+            Self::Divide => write!(f, "/"),
+            Self::Power => write!(f, "^"),
+            Self::Concatenate => write!(f, "&"),
+            Self::Equal => write!(f, "="),
+            Self::NotEqual => write!(f, "<>"),
+            Self::Less => write!(f, "<"),
+            Self::LessEqual => write!(f, "<="),
+            Self::Greater => write!(f, ">"),
+            Self::GreaterEqual => write!(f, ">="),
+        }
+    }
+}
+
+/// Raises `base` to the power of `exponent`, rounded like other results that can't be calculated exactly.
+/// See [`power_of`] for the errors.
+pub(crate) fn power(base: &BigDecimal, exponent: &BigDecimal) -> Result<BigDecimal, Error> {
+    power_of(base, exponent).map(inexact)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(value: &str) -> Value {
+        Value::Number(value.parse().unwrap())
+    }
+
+    #[test]
+    fn test_unary() {
+        assert_eq!(UnaryOperator::Plus.apply(&number("2")), number("2"));
+        assert_eq!(UnaryOperator::Minus.apply(&number("2")), number("-2"));
+        assert_eq!(UnaryOperator::Percent.apply(&number("50")), number("0.5"));
+        assert_eq!(
+            UnaryOperator::Minus.apply(&Value::String("foo".into())),
+            Value::Error(Error::Value)
+        );
+        assert_eq!(
+            UnaryOperator::Minus.apply(&Value::Error(Error::Cycle)),
+            Value::Error(Error::Cycle)
+        );
+    }
+
+    #[test]
+    fn test_binary() {
+        assert_eq!(
+            BinaryOperator::Add.apply(&number("1"), &number("2")),
+            number("3")
+        );
+        assert_eq!(
+            BinaryOperator::Subtract.apply(&number("1"), &number("2")),
+            number("-1")
+        );
+        assert_eq!(
+            BinaryOperator::Multiply.apply(&number("1.5"), &number("2")),
+            number("3")
+        );
+        assert_eq!(
+            BinaryOperator::Divide.apply(&number("1"), &number("4")),
+            number("0.25")
+        );
+        assert_eq!(
+            BinaryOperator::Divide.apply(&number("1"), &number("0")),
+            Value::Error(Error::DivisionByZero)
+        );
+        assert_eq!(
+            BinaryOperator::Add.apply(&number("1"), &Value::String("foo".into())),
+            Value::Error(Error::Value)
+        );
+        // empty cells count as zero
+        assert_eq!(
+            BinaryOperator::Add.apply(&Value::Empty, &number("1")),
+            number("1")
+        );
+        assert_eq!(
+            BinaryOperator::Multiply.apply(&number("2"), &Value::Empty),
+            number("0")
+        );
+    }
+
+    #[test]
+    fn test_date_arithmetic() {
+        let date = |serial: i32| Value::DateTime(serial.into());
+
+        assert_eq!(
+            BinaryOperator::Add.apply(&date(45351), &number("7")),
+            date(45358)
+        );
+        assert_eq!(
+            BinaryOperator::Add.apply(&number("0.5"), &date(45351)),
+            Value::DateTime("45351.5".parse().unwrap())
+        );
+        assert_eq!(
+            BinaryOperator::Subtract.apply(&date(45351), &number("1")),
+            date(45350)
+        );
+        assert_eq!(
+            BinaryOperator::Add.apply(&date(45351), &Value::DateTime("0.25".parse().unwrap())),
+            Value::DateTime("45351.25".parse().unwrap())
+        );
+        assert_eq!(
+            BinaryOperator::Subtract.apply(&date(45351), &date(45341)),
+            number("10")
+        );
+        assert_eq!(
+            BinaryOperator::Multiply.apply(&date(2), &number("2")),
+            number("4")
+        );
+    }
+
+    #[test]
+    fn test_comparison() {
+        let string = |value: &str| Value::String(value.into());
+
+        assert_eq!(
+            BinaryOperator::Equal.apply(&number("1"), &number("1.0")),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            BinaryOperator::NotEqual.apply(&string("Foo"), &string("fOO")),
+            Value::Boolean(false)
+        );
+        assert_eq!(
+            BinaryOperator::Less.apply(&number("1"), &number("2")),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            BinaryOperator::LessEqual.apply(&number("2"), &number("2")),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            BinaryOperator::Greater.apply(&string("b"), &string("A")),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            BinaryOperator::GreaterEqual.apply(&Value::Empty, &number("1")),
+            Value::Boolean(false)
+        );
+        assert_eq!(
+            BinaryOperator::Equal.apply(&number("1"), &Value::Error(Error::Reference)),
+            Value::Error(Error::Reference)
+        );
+    }
+
+    #[test]
+    fn test_concatenate() {
+        let string = |value: &str| Value::String(value.into());
+
+        assert_eq!(
+            BinaryOperator::Concatenate.apply(&string("foo"), &string("bar")),
+            string("foobar")
+        );
+        assert_eq!(
+            BinaryOperator::Concatenate.apply(&number("1.50"), &Value::Boolean(true)),
+            string("1.5TRUE")
+        );
+        assert_eq!(
+            BinaryOperator::Concatenate.apply(&Value::Empty, &string("x")),
+            string("x")
+        );
+        assert_eq!(
+            BinaryOperator::Concatenate.apply(&string("x"), &Value::Error(Error::NotAvailable)),
+            Value::Error(Error::NotAvailable)
+        );
+    }
+
+    #[test]
+    fn test_power() {
+        assert_eq!(
+            BinaryOperator::Power.apply(&number("2"), &number("10")),
+            number("1024")
+        );
+        assert_eq!(
+            BinaryOperator::Power.apply(&number("2"), &number("-2")),
+            number("0.25")
+        );
+        assert_eq!(
+            BinaryOperator::Power.apply(&number("5"), &number("0")),
+            number("1")
+        );
+        assert_eq!(
+            BinaryOperator::Power.apply(&number("4"), &number("0.5")),
+            number("2")
+        );
+        assert_eq!(
+            BinaryOperator::Power.apply(&number("0"), &number("-1")),
+            Value::Error(Error::DivisionByZero)
+        );
+        assert_eq!(
+            BinaryOperator::Power.apply(&number("-4"), &number("0.5")),
+            Value::Error(Error::Number)
+        );
+        assert_eq!(
+            BinaryOperator::Power.apply(&number("0"), &number("0")),
+            Value::Error(Error::Number)
+        );
+        assert_eq!(
+            BinaryOperator::Power.apply(&number("0"), &number("0.5")),
+            number("0")
+        );
+        assert_eq!(
+            BinaryOperator::Power.apply(&number("-2"), &number("3")),
+            number("-8")
+        );
+        assert_eq!(
+            BinaryOperator::Power.apply(&number("10"), &number("300")),
+            number("1e300")
+        );
+        // results that are too large or too small aren't calculated
+        assert_eq!(
+            BinaryOperator::Power.apply(&number("9.99"), &number("1024")),
+            Value::Error(Error::Number)
+        );
+        assert_eq!(
+            BinaryOperator::Power.apply(&number("2"), &number("1e300")),
+            Value::Error(Error::Number)
+        );
+        assert_eq!(
+            BinaryOperator::Power.apply(&number("1.0000001"), &number("1e12")),
+            Value::Error(Error::Number)
+        );
+        assert_eq!(
+            BinaryOperator::Power.apply(&number("10"), &number("-1024")),
+            number("0")
+        );
+        assert_eq!(
+            BinaryOperator::Power.apply(&number("2"), &number("0.5")),
+            number("1.41421356237310")
+        );
+    }
+}
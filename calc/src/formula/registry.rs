@@ -0,0 +1,300 @@
+//! The functions available to formulas, together with their signatures and help texts.
+//!
+//! Before a function is called, the number of arguments is checked against its [`Signature`]; for functions
+//! receiving values, the types of the evaluated arguments are checked as well. Calls that don't fit the signature
+//! result in `#VALUE!`, or in the error an argument evaluated to, without calling the function.
+//!
+//! Function names are case-insensitive, so `SUM(1, 2)` calls `sum`. Besides the name it was registered with,
+//! a function can be called by aliases, such as `concatenate` for `concat`, and by names for specific locales,
+//! such as `summe` for `sum` in German. Formulas are stored with the registered names
+//! (see [`FunctionRegistry::canonical_name`]); localized names can be looked up for display.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::value::Value;
+
+use super::expression::Expression;
+use super::function::{Argument, Function};
+use super::signature::Signature;
+use super::{Context, Evaluate};
+
+/// What users are shown about a function
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Description {
+    #[serde(flatten)]
+    pub signature: Signature,
+    pub help: String,
+}
+
+/// A function that can be called from formulas, with its description
+pub struct Definition {
+    description: Description,
+    function: Function,
+}
+
+impl Definition {
+    pub fn new<S: ToString>(signature: Signature, help: S, function: Function) -> Self {
+        Self {
+            description: Description {
+                signature,
+                help: help.to_string(),
+            },
+            function,
+        }
+    }
+
+    /// A function that receives the values of its arguments
+    pub fn value<S, F>(signature: Signature, help: S, function: F) -> Self
+    where
+        S: ToString,
+        F: 'static + Send + Sync + Fn(&[Value]) -> Value,
+    {
+        Self::new(signature, help, Function::Value(Box::new(function)))
+    }
+
+    /// A function that receives its unevaluated arguments, see [`Function::Lazy`]
+    pub fn lazy<S, F>(signature: Signature, help: S, function: F) -> Self
+    where
+        S: ToString,
+        F: 'static + Send + Sync + Fn(&[Argument], &dyn Context) -> Value,
+    {
+        Self::new(signature, help, Function::Lazy(Box::new(function)))
+    }
+
+    pub fn name(&self) -> &str {
+        self.description.signature.name()
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.description.signature
+    }
+
+    pub fn description(&self) -> &Description {
+        &self.description
+    }
+
+    pub fn function(&self) -> &Function {
+        &self.function
+    }
+
+    /// Calls the function with the given (unevaluated) arguments, if they fit the signature.
+    pub fn call(&self, arguments: &[Expression], context: &dyn Context) -> Value {
+        let signature = self.signature();
+        if let Err(error) = signature.check_count(arguments.len()) {
+            return Value::Error(error);
+        }
+        match &self.function {
+            Function::Value(function) => {
+                let arguments: Vec<_> = arguments.iter().map(|arg| arg.evaluate(context)).collect();
+                match signature.check(&arguments) {
+                    Ok(()) => function(&arguments),
+                    Err(error) => Value::Error(error),
+                }
+            }
+            Function::Lazy(_) => self.function.call(arguments, context),
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    #[error("there is no function named {0}")]
+    UnknownFunction(String),
+    #[error("there already is a function named {0}")]
+    NameTaken(String),
+}
+
+/// The functions available to formulas, by name
+#[derive(Default)]
+pub struct FunctionRegistry {
+    /// by lowercase name
+    definitions: HashMap<String, Definition>,
+    /// the lowercase names of functions, by lowercase alias or localized name
+    aliases: HashMap<String, String>,
+    /// the localized names of functions, by locale and lowercase name
+    localized: HashMap<String, HashMap<String, String>>,
+}
+
+impl FunctionRegistry {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The function with the given name, alias or localized name, ignoring case
+    pub fn get(&self, name: &str) -> Option<&Definition> {
+        let name = name.to_lowercase();
+        self.definitions.get(&name).or_else(|| {
+            self.aliases
+                .get(&name)
+                .and_then(|name| self.definitions.get(name))
+        })
+    }
+
+    /// The name a function was registered with, given any of its names in any case,
+    /// e.g. `concat` for `CONCATENATE`
+    pub fn canonical_name(&self, name: &str) -> Option<&str> {
+        self.get(name).map(Definition::name)
+    }
+
+    /// Adds a function, replacing any function with the same name.
+    pub fn register(&mut self, definition: Definition) {
+        let name = definition.name().to_lowercase();
+        self.aliases.remove(&name);
+        self.definitions.insert(name, definition);
+    }
+
+    /// Makes the function `name` callable as `alias` as well.
+    pub fn register_alias(&mut self, alias: &str, name: &str) -> Result<(), RegistryError> {
+        let name = self
+            .canonical_name(name)
+            .ok_or_else(|| RegistryError::UnknownFunction(name.to_string()))?
+            .to_lowercase();
+        if self.get(alias).is_some() {
+            return Err(RegistryError::NameTaken(alias.to_string()));
+        }
+        self.aliases.insert(alias.to_lowercase(), name);
+        Ok(())
+    }
+
+    /// Makes the function `name` callable as `localized`, the function's name in the given locale.
+    pub fn register_localized_name(
+        &mut self,
+        locale: &str,
+        name: &str,
+        localized: &str,
+    ) -> Result<(), RegistryError> {
+        self.register_alias(localized, name)?;
+        let name = self.aliases[&localized.to_lowercase()].clone();
+        self.localized
+            .entry(locale.to_string())
+            .or_default()
+            .insert(name, localized.to_string());
+        Ok(())
+    }
+
+    /// The name of the function `name` in the given locale, if it has one
+    pub fn localized_name(&self, locale: &str, name: &str) -> Option<&str> {
+        let name = self.canonical_name(name)?.to_lowercase();
+        self.localized.get(locale)?.get(&name).map(String::as_str)
+    }
+
+    /// The descriptions of all functions, ordered by name
+    pub fn descriptions(&self) -> Vec<&Description> {
+        let mut descriptions: Vec<_> = self
+            .definitions
+            .values()
+            .map(Definition::description)
+            .collect();
+        descriptions.sort_by(|a, b| a.signature.name().cmp(b.signature.name()));
+        descriptions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::sheet::Sheet;
+    use crate::value::Error;
+
+    fn signature(s: &str) -> Signature {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_checks() {
+        let mut sheet = Sheet::new();
+        sheet.register_function(Definition::value(
+            signature("twice(number: number) -> number"),
+            "The number doubled",
+            |arguments| match arguments {
+                [Value::Number(number)] => Value::Number(number.double()),
+                // the signature guarantees a single number
+                _ => unreachable!(),
+            },
+        ));
+        sheet.register_function(Definition::lazy(
+            signature("first(value: any, [other: any...]) -> any"),
+            "The first value",
+            |arguments, _context| arguments[0].evaluate(),
+        ));
+
+        let evaluate =
+            |formula: &str| sheet.evaluate(&formula.parse::<crate::formula::Formula>().unwrap());
+        assert_eq!(evaluate("=twice(2)"), Value::Number(4.into()));
+        assert_eq!(evaluate("=twice()"), Value::Error(Error::Value));
+        assert_eq!(evaluate("=twice(1, 2)"), Value::Error(Error::Value));
+        assert_eq!(evaluate("=twice(\"2\")"), Value::Error(Error::Value));
+        assert_eq!(evaluate("=twice(#N/A)"), Value::Error(Error::NotAvailable));
+        assert_eq!(evaluate("=first(1, 1 / 0)"), Value::Number(1.into()));
+        assert_eq!(evaluate("=first()"), Value::Error(Error::Value));
+    }
+
+    #[test]
+    fn test_descriptions() {
+        let mut registry = FunctionRegistry::new();
+        registry.register(Definition::value(signature("b() -> number"), "B", |_| {
+            Value::Empty
+        }));
+        registry.register(Definition::value(
+            signature("a(x: number) -> number"),
+            "A",
+            |_| Value::Empty,
+        ));
+        let names: Vec<_> = registry
+            .descriptions()
+            .iter()
+            .map(|description| description.signature.name())
+            .collect();
+        assert_eq!(names, ["a", "b"]);
+
+        let description = serde_json::to_value(registry.get("a").unwrap().description()).unwrap();
+        assert_eq!(
+            description,
+            serde_json::json!({
+                "name": "a",
+                "parameters": [{ "name": "x", "type": "number", "optional": false, "variadic": false }],
+                "returns": "number",
+                "volatile": false,
+                "help": "A",
+            })
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        let mut registry = FunctionRegistry::new();
+        registry.register(Definition::value(
+            signature("concat([value: range...]) -> text"),
+            "",
+            |_| Value::Empty,
+        ));
+        assert_eq!(registry.canonical_name("CONCAT"), Some("concat"));
+        assert_eq!(registry.canonical_name("Concat"), Some("concat"));
+        assert_eq!(registry.canonical_name("concatenate"), None);
+
+        registry.register_alias("concatenate", "concat").unwrap();
+        registry
+            .register_localized_name("de", "CONCAT", "Verketten")
+            .unwrap();
+        assert_eq!(registry.canonical_name("CONCATENATE"), Some("concat"));
+        assert_eq!(registry.canonical_name("verketten"), Some("concat"));
+        assert_eq!(
+            registry.localized_name("de", "concatenate"),
+            Some("Verketten")
+        );
+        assert_eq!(registry.localized_name("fr", "concat"), None);
+
+        assert_eq!(
+            registry.register_alias("Concatenate", "concat"),
+            Err(RegistryError::NameTaken("Concatenate".to_string()))
+        );
+        assert_eq!(
+            registry.register_alias("x", "y"),
+            Err(RegistryError::UnknownFunction("y".to_string()))
+        );
+    }
+}
@@ -0,0 +1,286 @@
+//! Signatures describe the parameters of functions, so that arguments can be checked before a function is called,
+//! and so that users can be shown how to call it.
+//!
+//! Signatures are usually written as text, such as `round(number: number, [digits: number]) -> number`:
+//! optional parameters are in brackets, and the last parameter may be followed by `...` if it can be repeated,
+//! as in `sum(number: range...) -> number`. The text after `->` describes the result.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::parser::{parse_signature_complete, ParseSignatureError};
+use crate::value::{Error, Value};
+
+/// The type of a parameter. Arguments of functions receiving values are checked against it before the function
+/// is called; a mismatch results in `#VALUE!`, or in the argument itself if it is an error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParameterType {
+    /// any value, including ranges; not checked
+    Any,
+    /// a number or a date; empty values count as zero
+    Number,
+    /// any single value, which is used as text
+    Text,
+    /// a boolean or a number, which is used as a condition
+    Boolean,
+    /// a date, which may also be given as text such as `"2024-02-29"`, or left empty
+    Date,
+    /// a range, or a single value used like a range of one cell; not checked
+    Range,
+}
+
+impl ParameterType {
+    fn check(self, value: &Value) -> Result<(), Error> {
+        match (self, value) {
+            (Self::Any | Self::Range, _) => Ok(()),
+            (Self::Number, value) => value.as_number().map(|_| ()),
+            (Self::Text, value) => value.to_text().map(|_| ()),
+            (Self::Boolean, value) => value.as_boolean().map(|_| ()),
+            (Self::Date, Value::String(_) | Value::Empty) => Ok(()),
+            (Self::Date, value) => value.as_number().map(|_| ()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Parameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: ParameterType,
+    /// whether the argument may be left out; only trailing parameters are optional
+    pub optional: bool,
+    /// whether the argument may be repeated; only the last parameter is variadic
+    pub variadic: bool,
+}
+
+impl fmt::Display for Parameter {
+    /// Writes the parameter as in help texts, e.g. `number` or `[digits]`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let repeated = if self.variadic { ", ..." } else { "" };
+        if self.optional {
+            write!(f, "[{}]{}", self.name, repeated)
+        } else {
+            write!(f, "{}{}", self.name, repeated)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Signature {
+    name: String,
+    parameters: Vec<Parameter>,
+    returns: String,
+    /// whether the result may change without any argument changing, see [`Signature::volatile`]
+    volatile: bool,
+}
+
+impl Signature {
+    /// A signature with the given parameters. Returns `None` if a required parameter follows an optional one,
+    /// or if a parameter other than the last one is variadic.
+    pub fn new<S: ToString, R: ToString>(
+        name: S,
+        parameters: Vec<Parameter>,
+        returns: R,
+    ) -> Option<Self> {
+        let required_after_optional = parameters
+            .windows(2)
+            .any(|pair| pair[0].optional && !pair[1].optional);
+        let variadic_before_last = parameters
+            .iter()
+            .rev()
+            .skip(1)
+            .any(|parameter| parameter.variadic);
+        if required_after_optional || variadic_before_last {
+            return None;
+        }
+        Some(Self {
+            name: name.to_string(),
+            parameters,
+            returns: returns.to_string(),
+            volatile: false,
+        })
+    }
+
+    /// Marks the function as volatile: its result may change even if its arguments don't, as for `today()`.
+    /// Cells calling a volatile function are recalculated, together with their dependents, whenever anything
+    /// is recalculated.
+    pub fn volatile(self) -> Self {
+        Self {
+            volatile: true,
+            ..self
+        }
+    }
+
+    /// The signature of a function that takes any number of values, used for functions registered without one
+    pub fn any<S: ToString>(name: S) -> Self {
+        let values = Parameter {
+            name: "value".to_string(),
+            kind: ParameterType::Any,
+            optional: true,
+            variadic: true,
+        };
+        Self::new(name, vec![values], "any").expect("the signature is valid")
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parameters(&self) -> &[Parameter] {
+        &self.parameters
+    }
+
+    /// A description of the result, such as `number`
+    pub fn returns(&self) -> &str {
+        &self.returns
+    }
+
+    pub fn is_volatile(&self) -> bool {
+        self.volatile
+    }
+
+    /// The parameter receiving the argument at the given position, taking repeated arguments into account
+    fn parameter(&self, index: usize) -> Option<&Parameter> {
+        self.parameters.get(index).or_else(|| {
+            self.parameters
+                .last()
+                .filter(|parameter| parameter.variadic)
+        })
+    }
+
+    /// Checks that the number of arguments fits the parameters; otherwise, the result is `#VALUE!`
+    pub fn check_count(&self, count: usize) -> Result<(), Error> {
+        let required = self
+            .parameters
+            .iter()
+            .filter(|parameter| !parameter.optional)
+            .count();
+        let too_many = count > 0 && self.parameter(count - 1).is_none();
+        if count < required || too_many {
+            return Err(Error::Value);
+        }
+        Ok(())
+    }
+
+    /// Checks the number and types of evaluated arguments.
+    pub fn check(&self, arguments: &[Value]) -> Result<(), Error> {
+        self.check_count(arguments.len())?;
+        for (index, argument) in arguments.iter().enumerate() {
+            if let Some(parameter) = self.parameter(index) {
+                parameter.kind.check(argument)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Signature {
+    type Err = ParseSignatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_signature_complete(s)
+    }
+}
+
+impl fmt::Display for Signature {
+    /// Writes the signature as in help texts, e.g. `round(number, [digits])`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}(", self.name)?;
+        for (index, parameter) in self.parameters.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", parameter)?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature(s: &str) -> Signature {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            signature("round(number: number, [digits: number]) -> number").to_string(),
+            "round(number, [digits])"
+        );
+        assert_eq!(
+            signature("sum(number: range...) -> number").to_string(),
+            "sum(number, ...)"
+        );
+        assert_eq!(
+            signature("concat([value: range...]) -> text").to_string(),
+            "concat([value], ...)"
+        );
+        assert_eq!(signature("pi() -> number").to_string(), "pi()");
+    }
+
+    #[test]
+    fn test_check_count() {
+        let round = signature("round(number: number, [digits: number]) -> number");
+        assert_eq!(round.check_count(0), Err(Error::Value));
+        assert_eq!(round.check_count(1), Ok(()));
+        assert_eq!(round.check_count(2), Ok(()));
+        assert_eq!(round.check_count(3), Err(Error::Value));
+
+        let sum = signature("sum(number: range...) -> number");
+        assert_eq!(sum.check_count(0), Err(Error::Value));
+        assert_eq!(sum.check_count(5), Ok(()));
+
+        let pi = signature("pi() -> number");
+        assert_eq!(pi.check_count(0), Ok(()));
+        assert_eq!(pi.check_count(1), Err(Error::Value));
+
+        assert_eq!(Signature::any("f").check_count(0), Ok(()));
+        assert_eq!(Signature::any("f").check_count(3), Ok(()));
+    }
+
+    #[test]
+    fn test_check() {
+        let number = |n: i32| Value::Number(n.into());
+        let string = |s: &str| Value::String(s.into());
+
+        let left = signature("left(text: text, [count: number]) -> text");
+        assert_eq!(left.check(&[string("foo"), number(1)]), Ok(()));
+        assert_eq!(left.check(&[number(12), number(1)]), Ok(()));
+        assert_eq!(left.check(&[string("foo"), Value::Empty]), Ok(()));
+        assert_eq!(left.check(&[string("foo"), string("1")]), Err(Error::Value));
+        assert_eq!(
+            left.check(&[Value::Error(Error::NotAvailable)]),
+            Err(Error::NotAvailable)
+        );
+
+        let year = signature("year(date: date) -> number");
+        assert_eq!(year.check(&[string("2024-02-29")]), Ok(()));
+        assert_eq!(year.check(&[Value::DateTime(45351.into())]), Ok(()));
+        assert_eq!(year.check(&[Value::Boolean(true)]), Err(Error::Value));
+
+        let and = signature("and(condition: boolean...) -> boolean");
+        assert_eq!(and.check(&[Value::Boolean(true), number(0)]), Ok(()));
+        assert_eq!(
+            and.check(&[Value::Boolean(true), string("x")]),
+            Err(Error::Value)
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!("f([a: number], b: number) -> number"
+            .parse::<Signature>()
+            .is_err());
+        assert!("f(a: number..., b: number) -> number"
+            .parse::<Signature>()
+            .is_err());
+        assert!("f(a: foo) -> number".parse::<Signature>().is_err());
+        assert!("f(a: number)".parse::<Signature>().is_err());
+    }
+}
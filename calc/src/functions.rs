@@ -0,0 +1,269 @@
+//! Built-in functions that can be called from formulas, grouped by topic.
+//!
+//! Most functions take the evaluated arguments and return the result,
+//! so that they can be registered using [`Sheet::set_function`](crate::sheet::Sheet::set_function).
+//! Functions that only evaluate some of their arguments are lazy functions, registered using
+//! [`Sheet::set_lazy_function`](crate::sheet::Sheet::set_lazy_function).
+//!
+//! [`Sheet::with_standard_library`](crate::sheet::Sheet::with_standard_library) creates a sheet with all
+//! functions registered, together with their signatures and help texts (see [`crate::formula::registry`]),
+//! so that their arguments are checked before they're called.
+
+pub mod date;
+pub mod finance;
+pub mod information;
+pub mod logical;
+pub mod lookup;
+pub mod math;
+pub mod statistics;
+pub mod text;
+
+use bigdecimal::BigDecimal;
+
+use crate::formula::function::{Function, LazyFunction, ValueFunction};
+use crate::formula::registry::{Definition, FunctionRegistry};
+use crate::formula::signature::Signature;
+use crate::value::{Error, Value};
+
+/// All functions of this module, with their signatures and help texts
+pub(crate) fn standard_library() -> FunctionRegistry {
+    let value_functions: &[(&str, &str, &'static ValueFunction)] = &[
+        // information
+        ("iserror(value: any) -> boolean", "Whether the value is any error.", &information::iserror),
+        ("iserr(value: any) -> boolean", "Whether the value is an error other than #N/A.", &information::iserr),
+        ("isna(value: any) -> boolean", "Whether the value is the #N/A error.", &information::isna),
+        ("errortype(value: any) -> number", "The number identifying the kind of error, or #N/A if the value is not an error.", &information::errortype),
+        // math
+        ("sum(number: range...) -> number", "The sum of the numbers; in ranges, other values are ignored.", &math::sum),
+        ("product(number: range...) -> number", "The product of the numbers; in ranges, other values are ignored.", &math::product),
+        ("abs(number: number) -> number", "The absolute value of the number.", &math::abs),
+        ("sign(number: number) -> number", "-1, 0 or 1, depending on the sign of the number.", &math::sign),
+        ("round(number: number, [digits: number]) -> number", "The number rounded to the given number of decimal places (default 0).", &math::round),
+        ("roundup(number: number, [digits: number]) -> number", "The number rounded away from zero to the given number of decimal places.", &math::roundup),
+        ("rounddown(number: number, [digits: number]) -> number", "The number rounded towards zero to the given number of decimal places.", &math::rounddown),
+        ("trunc(number: number, [digits: number]) -> number", "The number truncated to the given number of decimal places.", &math::trunc),
+        ("int(number: number) -> number", "The number rounded down to the next integer.", &math::int),
+        ("ceiling(number: number, [significance: number]) -> number", "The number rounded up to a multiple of the significance (default 1).", &math::ceiling),
+        ("floor(number: number, [significance: number]) -> number", "The number rounded down to a multiple of the significance (default 1).", &math::floor),
+        ("mod(number: number, divisor: number) -> number", "The remainder of dividing the number by the divisor, with the sign of the divisor.", &math::r#mod),
+        ("power(base: number, exponent: number) -> number", "The base raised to the power of the exponent.", &math::power),
+        ("gcd(number: range...) -> number", "The greatest common divisor of the integer parts of the numbers.", &math::gcd),
+        ("lcm(number: range...) -> number", "The least common multiple of the integer parts of the numbers.", &math::lcm),
+        ("sqrt(number: number) -> number", "The square root of the number.", &math::sqrt),
+        ("exp(number: number) -> number", "e raised to the power of the number.", &math::exp),
+        ("ln(number: number) -> number", "The natural logarithm of the number.", &math::ln),
+        ("log(number: number, [base: number]) -> number", "The logarithm of the number to the given base (default 10).", &math::log),
+        ("log10(number: number) -> number", "The logarithm of the number to base 10.", &math::log10),
+        ("pi() -> number", "The number π.", &math::pi),
+        ("sin(angle: number) -> number", "The sine of an angle in radians.", &math::sin),
+        ("cos(angle: number) -> number", "The cosine of an angle in radians.", &math::cos),
+        ("tan(angle: number) -> number", "The tangent of an angle in radians.", &math::tan),
+        ("asin(number: number) -> number", "The angle in radians whose sine is the number.", &math::asin),
+        ("acos(number: number) -> number", "The angle in radians whose cosine is the number.", &math::acos),
+        ("atan(number: number) -> number", "The angle in radians whose tangent is the number.", &math::atan),
+        ("atan2(x: number, y: number) -> number", "The angle in radians between the x axis and the point (x, y).", &math::atan2),
+        ("degrees(angle: number) -> number", "An angle in radians converted to degrees.", &math::degrees),
+        ("radians(angle: number) -> number", "An angle in degrees converted to radians.", &math::radians),
+        // statistics
+        ("average(number: range...) -> number", "The arithmetic mean of the numbers.", &statistics::average),
+        ("median(number: range...) -> number", "The middle number, or the mean of the two middle numbers.", &statistics::median),
+        ("mode(number: range...) -> number", "The most frequent number.", &statistics::mode),
+        ("min(number: range...) -> number", "The smallest number, or 0 if there are none.", &statistics::min),
+        ("max(number: range...) -> number", "The largest number, or 0 if there are none.", &statistics::max),
+        ("count(value: range...) -> number", "The number of values that are numbers or dates.", &statistics::count),
+        ("counta(value: range...) -> number", "The number of values that are not empty.", &statistics::counta),
+        ("countblank(range: range...) -> number", "The number of empty cells, including those containing empty text.", &statistics::countblank),
+        ("stdev(number: range...) -> number", "The standard deviation of a sample.", &statistics::stdev),
+        ("stdev.p(number: range...) -> number", "The standard deviation of a whole population.", &statistics::stdev_p),
+        ("var(number: range...) -> number", "The variance of a sample.", &statistics::var),
+        ("var.p(number: range...) -> number", "The variance of a whole population.", &statistics::var_p),
+        ("percentile(range: range, k: number) -> number", "The value below which the fraction k (between 0 and 1) of the numbers lie.", &statistics::percentile),
+        ("quartile(range: range, quart: number) -> number", "The minimum (0), a quartile (1 to 3) or the maximum (4) of the numbers.", &statistics::quartile),
+        ("rank(number: number, range: range, [order: number]) -> number", "The position of the number among the numbers in the range, largest first unless the order is non-zero.", &statistics::rank),
+        ("large(range: range, k: number) -> number", "The k-th largest number.", &statistics::large),
+        ("small(range: range, k: number) -> number", "The k-th smallest number.", &statistics::small),
+        ("correl(range1: range, range2: range) -> number", "The correlation coefficient of two ranges of the same size.", &statistics::correl),
+        ("sumproduct(range: range...) -> number", "The sum of the products of corresponding values of ranges of the same size.", &statistics::sumproduct),
+        // text
+        ("concat([value: range...]) -> text", "All values, including those in ranges, joined as text.", &text::concat),
+        ("len(text: text) -> number", "The number of characters of the text.", &text::len),
+        ("left(text: text, [count: number]) -> text", "The first count characters of the text (default 1).", &text::left),
+        ("right(text: text, [count: number]) -> text", "The last count characters of the text (default 1).", &text::right),
+        ("mid(text: text, start: number, count: number) -> text", "count characters of the text, starting at position start.", &text::mid),
+        ("upper(text: text) -> text", "The text in upper case.", &text::upper),
+        ("lower(text: text) -> text", "The text in lower case.", &text::lower),
+        ("proper(text: text) -> text", "The text with the first letter of each word in upper case.", &text::proper),
+        ("trim(text: text) -> text", "The text without leading, trailing and repeated spaces.", &text::trim),
+        ("substitute(text: text, old: text, new: text, [instance: number]) -> text", "The text with occurrences of old replaced by new, or only the given occurrence.", &text::substitute),
+        ("replace(text: text, start: number, count: number, new: text) -> text", "The text with count characters starting at position start replaced by new.", &text::replace),
+        ("find(needle: text, haystack: text, [start: number]) -> number", "The position of the needle in the haystack, case-sensitively.", &text::find),
+        ("search(pattern: text, text: text, [start: number]) -> number", "The position of the pattern in the text, case-insensitively; the pattern may contain the wildcards ? and *.", &text::search),
+        ("rept(text: text, count: number) -> text", "The text repeated count times.", &text::rept),
+        ("text(value: any, format: text) -> text", "The number formatted according to the format, such as 0.00, #,##0 or 0.0%.", &text::text),
+        ("value(text: text) -> number", "The text converted to a number.", &text::value),
+        ("exact(text1: text, text2: text) -> boolean", "Whether the two texts are exactly equal, case-sensitively.", &text::exact),
+        ("textjoin(delimiter: text, ignore_empty: boolean, value: range...) -> text", "All values, including those in ranges, joined as text with the delimiter between them.", &text::textjoin),
+        ("split(text: text, delimiter: text) -> array", "The parts of the text between occurrences of the delimiter, as a row.", &text::split),
+        // lookup
+        ("index(range: range, row: number, [column: number]) -> any", "The value at the given row and column of the range.", &lookup::index),
+        ("match(value: any, range: range, [type: number]) -> number", "The position of the value in a range with a single row or column.", &lookup::r#match),
+        ("vlookup(value: any, table: range, column: number, [approximate: boolean]) -> any", "Searches the first column of the table for the value, and results in the value in the given column of its row.", &lookup::vlookup),
+        ("hlookup(value: any, table: range, row: number, [approximate: boolean]) -> any", "Searches the first row of the table for the value, and results in the value in the given row of its column.", &lookup::hlookup),
+        ("xlookup(value: any, lookup_range: range, result_range: range, [if_not_found: any], [match_mode: number], [search_mode: number]) -> any", "Searches the lookup range for the value, and results in the corresponding value of the result range.", &lookup::xlookup),
+        ("rows(range: range) -> number", "The number of rows of the range.", &lookup::rows),
+        ("columns(range: range) -> number", "The number of columns of the range.", &lookup::columns),
+        ("address(row: number, column: number, [absolute: number], [a1: boolean], [sheet: text]) -> text", "A reference to the cell as text, such as $B$3.", &lookup::address),
+        // date
+        ("date(year: number, month: number, day: number) -> date", "The date with the given year, month and day.", &date::date),
+        ("time(hour: number, minute: number, second: number) -> time", "The time of day with the given hour, minute and second.", &date::time),
+        ("today() -> date", "The current date.", &date::today),
+        ("now() -> date and time", "The current date and time.", &date::now),
+        ("year(date: date) -> number", "The year of the date.", &date::year),
+        ("month(date: date) -> number", "The month of the date, from 1 to 12.", &date::month),
+        ("day(date: date) -> number", "The day of the month of the date.", &date::day),
+        ("weekday(date: date, [type: number]) -> number", "The day of the week of the date, from 1 (Sunday) to 7 (Saturday) by default.", &date::weekday),
+        ("hour(time: date) -> number", "The hour of the time, from 0 to 23.", &date::hour),
+        ("minute(time: date) -> number", "The minute of the time, from 0 to 59.", &date::minute),
+        ("second(time: date) -> number", "The second of the time, from 0 to 59.", &date::second),
+        ("edate(date: date, months: number) -> date", "The date the given number of months before or after the date.", &date::edate),
+        ("eomonth(date: date, months: number) -> date", "The last day of the month the given number of months before or after the date.", &date::eomonth),
+        ("datedif(start: date, end: date, unit: text) -> number", "The difference between two dates in years (Y), months (M), days (D), or ignoring years or months (YM, YD, MD).", &date::datedif),
+        ("networkdays(start: date, end: date, [holidays: range]) -> number", "The number of working days from the start date to the end date.", &date::networkdays),
+        ("workday(start: date, days: number, [holidays: range]) -> date", "The date the given number of working days after the start date.", &date::workday),
+        // finance
+        ("pv(rate: number, nper: number, pmt: number, [fv: number], [type: number]) -> number", "The present value of nper periodic payments and a final value.", &finance::pv),
+        ("fv(rate: number, nper: number, pmt: number, [pv: number], [type: number]) -> number", "The future value of a present value and nper periodic payments.", &finance::fv),
+        ("pmt(rate: number, nper: number, pv: number, [fv: number], [type: number]) -> number", "The periodic payment that pays off a loan in nper periods.", &finance::pmt),
+        ("ipmt(rate: number, per: number, nper: number, pv: number, [fv: number], [type: number]) -> number", "The interest part of the payment in the given period.", &finance::ipmt),
+        ("ppmt(rate: number, per: number, nper: number, pv: number, [fv: number], [type: number]) -> number", "The principal part of the payment in the given period.", &finance::ppmt),
+        ("nper(rate: number, pmt: number, pv: number, [fv: number], [type: number]) -> number", "The number of periods until periodic payments pay off a loan.", &finance::nper),
+        ("rate(nper: number, pmt: number, pv: number, [fv: number], [type: number], [guess: number]) -> number", "The interest rate per period at which periodic payments pay off a loan in nper periods.", &finance::rate),
+        ("npv(rate: number, value: range...) -> number", "The net present value of cash flows at the end of consecutive periods.", &finance::npv),
+        ("irr(values: range, [guess: number]) -> number", "The internal rate of return of cash flows at regular intervals.", &finance::irr),
+        ("xnpv(rate: number, values: range, dates: range) -> number", "The net present value of cash flows at the given dates.", &finance::xnpv),
+        ("xirr(values: range, dates: range, [guess: number]) -> number", "The yearly internal rate of return of cash flows at the given dates.", &finance::xirr),
+        ("sln(cost: number, salvage: number, life: number) -> number", "The straight-line depreciation per period.", &finance::sln),
+        ("db(cost: number, salvage: number, life: number, period: number, [month: number]) -> number", "The fixed-declining balance depreciation in the given period.", &finance::db),
+        ("ddb(cost: number, salvage: number, life: number, period: number, [factor: number]) -> number", "The double-declining balance depreciation in the given period.", &finance::ddb),
+        ("effect(nominal_rate: number, npery: number) -> number", "The effective yearly rate of a nominal rate compounded npery times a year.", &finance::effect),
+        ("nominal(effect_rate: number, npery: number) -> number", "The nominal yearly rate compounded npery times a year that results in the effective rate.", &finance::nominal),
+    ];
+    let lazy_functions: &[(&str, &str, &'static LazyFunction)] = &[
+        // logical
+        ("if(condition: boolean, then: any, [otherwise: any]) -> any", "then if the condition is true, otherwise otherwise (or FALSE).", &logical::if_),
+        ("and(condition: range...) -> boolean", "Whether all conditions are true.", &logical::and),
+        ("or(condition: range...) -> boolean", "Whether any condition is true.", &logical::or),
+        ("not(condition: boolean) -> boolean", "Whether the condition is false.", &logical::not),
+        ("xor(condition: range...) -> boolean", "Whether an odd number of conditions are true.", &logical::xor),
+        ("ifs(condition: boolean, value: any, [more: any...]) -> any", "The value after the first true condition, of several pairs of conditions and values.", &logical::ifs),
+        ("switch(expression: any, value: any, result: any, [more: any...]) -> any", "The result after the first value equal to the expression, or the last argument as the default.", &logical::switch),
+        ("iferror(value: any, fallback: any) -> any", "The fallback if the value is an error, otherwise the value.", &information::iferror),
+        ("ifna(value: any, fallback: any) -> any", "The fallback if the value is #N/A, otherwise the value.", &information::ifna),
+        ("choose(index: number, value: any...) -> any", "The value at the given position among the values.", &lookup::choose),
+        ("row([reference: range]) -> number", "The row number of the reference, or of the formula's cell.", &lookup::row),
+        ("column([reference: range]) -> number", "The column number of the reference, or of the formula's cell.", &lookup::column),
+    ];
+
+    // functions whose results change over time, without their arguments changing
+    let volatile = ["today", "now"];
+    let signature = |signature: &str| -> Signature {
+        let signature: Signature = signature
+            .parse()
+            .expect("signatures of the standard library are valid");
+        if volatile.contains(&signature.name()) {
+            signature.volatile()
+        } else {
+            signature
+        }
+    };
+    let value_functions = value_functions.iter().map(|(spec, help, function)| {
+        Definition::new(signature(spec), help, Function::Value(Box::new(function)))
+    });
+    let lazy_functions = lazy_functions.iter().map(|(spec, help, function)| {
+        Definition::new(signature(spec), help, Function::Lazy(Box::new(function)))
+    });
+
+    let mut registry = FunctionRegistry::new();
+    for definition in value_functions.chain(lazy_functions) {
+        registry.register(definition);
+    }
+    // other names under which other spreadsheets know some of the functions
+    let aliases = [
+        ("concatenate", "concat"),
+        ("stdev.s", "stdev"),
+        ("stdevp", "stdev.p"),
+        ("var.s", "var"),
+        ("varp", "var.p"),
+    ];
+    for (alias, name) in aliases {
+        registry
+            .register_alias(alias, name)
+            .expect("aliases of the standard library are unique");
+    }
+    registry
+}
+
+/// The numbers among the arguments, as most functions taking any number of arguments need them:
+/// values given directly must be numbers, while ranges may contain other values, which are ignored.
+/// Empty values are ignored in both cases, as they result from references to empty cells,
+/// and errors are propagated.
+pub(crate) fn numbers(arguments: &[Value]) -> Result<Vec<&BigDecimal>, Error> {
+    let mut numbers = Vec::new();
+    for argument in arguments {
+        match argument {
+            Value::Empty => {}
+            Value::Array(array) => {
+                for value in array.values() {
+                    match value {
+                        Value::Number(number) | Value::DateTime(number) => numbers.push(number),
+                        Value::Error(error) => return Err(*error),
+                        _ => {}
+                    }
+                }
+            }
+            argument => numbers.push(argument.as_number()?),
+        }
+    }
+    Ok(numbers)
+}
+
+/// A function's numeric result as a value, with errors becoming error values
+pub(crate) fn result(result: Result<BigDecimal, Error>) -> Value {
+    result.map_or_else(Value::Error, Value::Number)
+}
+
+/// The values of an argument: those of a range, or the argument itself if it's a single value
+pub(crate) fn values(argument: &Value) -> &[Value] {
+    match argument {
+        Value::Array(array) => array.values(),
+        value => std::slice::from_ref(value),
+    }
+}
+
+/// Values used in the tests of the function modules
+#[cfg(test)]
+pub(crate) mod testing {
+    use crate::value::{Array, Error, Value};
+
+    pub(crate) fn number(value: &str) -> Value {
+        Value::Number(value.parse().unwrap())
+    }
+
+    pub(crate) fn string(value: &str) -> Value {
+        Value::String(value.into())
+    }
+
+    pub(crate) fn error(error: Error) -> Value {
+        Value::Error(error)
+    }
+
+    /// a column of values, as a range evaluates to
+    pub(crate) fn column(values: &[Value]) -> Value {
+        Value::Array(Array::new(values.len() as u32, 1, values.to_vec()))
+    }
+
+    /// a column of numbers
+    pub(crate) fn numbers(values: &[&str]) -> Value {
+        column(&values.iter().map(|value| number(value)).collect::<Vec<_>>())
+    }
+}
@@ -0,0 +1,551 @@
+//! Date and time functions such as `date`, `year` or `workday`.
+//!
+//! Dates and times are serial numbers (see [`crate::datetime`]); functions creating them result in
+//! [`Value::DateTime`] values, which are displayed as dates. Arguments that are dates may also be
+//! plain numbers, or text in ISO 8601 format such as `"2024-02-29"`.
+//! Dates outside of the supported range from 1899-12-30 to 9999-12-31 result in `#NUM!`.
+
+use std::collections::HashSet;
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, Timelike, Weekday};
+use nom::combinator::all_consuming;
+
+use crate::datetime::{self, date_serial, from_serial};
+use crate::parser::datetime::parse_datetime;
+use crate::value::{Error, Value};
+
+use super::math::integer;
+use super::{numbers, result};
+
+/// The serial number of a date argument
+fn serial_of(value: &Value) -> Result<BigDecimal, Error> {
+    match value {
+        Value::Empty => Ok(BigDecimal::zero()),
+        Value::String(text) => all_consuming(parse_datetime)(text.trim())
+            .map(|(_, serial)| serial)
+            .map_err(|_| Error::Value),
+        value => value.as_number().cloned(),
+    }
+}
+
+/// The date of a date argument, ignoring the time of day
+fn date_of(value: &Value) -> Result<NaiveDate, Error> {
+    let serial = serial_of(value)?;
+    from_serial(&serial.with_scale(0))
+        .map(|datetime| datetime.date())
+        .ok_or(Error::Number)
+}
+
+/// A date as a value, or `#NUM!` if the date is outside of the supported range
+fn date_value(date: Option<NaiveDate>) -> Value {
+    date.map(date_serial)
+        .filter(|serial| (0..=datetime::MAX_SERIAL).contains(serial))
+        .map_or(Value::Error(Error::Number), |serial| {
+            Value::DateTime(serial.into())
+        })
+}
+
+/// Adds a (possibly negative) number of months to a date; days beyond the end of a month are clamped
+fn add_months(date: NaiveDate, months: i64) -> Option<NaiveDate> {
+    let count = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+    if months < 0 {
+        date.checked_sub_months(count)
+    } else {
+        date.checked_add_months(count)
+    }
+}
+
+fn is_weekend(date: NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// The dates of an optional holidays argument, which may be a range
+fn holidays(value: Option<&Value>) -> Result<HashSet<NaiveDate>, Error> {
+    let mut holidays = HashSet::new();
+    if let Some(value) = value {
+        for serial in numbers(std::slice::from_ref(value))? {
+            let date = from_serial(&serial.with_scale(0)).ok_or(Error::Number)?;
+            holidays.insert(date.date());
+        }
+    }
+    Ok(holidays)
+}
+
+/// `date(year, month, day)`: the date with the given year, month and day.
+/// Months and days outside of their usual ranges count on from the year or month,
+/// e.g. `date(2024, 14, 1)` is 2025-02-01 and `date(2024, 3, 0)` is the last day of February.
+pub fn date(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [year, month, day] => {
+            let (year, month, day) = (
+                integer(year.as_number()?)?,
+                integer(month.as_number()?)?,
+                integer(day.as_number()?)?,
+            );
+            // months since year 0, so that months outside of 1 to 12 count on from the year
+            let months = year
+                .checked_mul(12)
+                .and_then(|months| months.checked_add(month - 1))
+                .ok_or(Error::Number)?;
+            let year = i32::try_from(months.div_euclid(12)).map_err(|_| Error::Number)?;
+            let month = months.rem_euclid(12) as u32 + 1;
+            let first = NaiveDate::from_ymd_opt(year, month, 1).ok_or(Error::Number)?;
+            Ok(Duration::try_days(day - 1).and_then(|days| first.checked_add_signed(days)))
+        }
+        _ => Err(Error::Value),
+    };
+    inner().map_or_else(Value::Error, date_value)
+}
+
+/// `time(hour, minute, second)`: the time of day with the given hour, minute and second.
+/// Minutes and seconds outside of their usual ranges count on from the hour or minute,
+/// and times after midnight wrap around, e.g. `time(25, 0, 0)` is 1:00.
+pub fn time(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [hour, minute, second] => {
+            let (hour, minute, second) = (
+                integer(hour.as_number()?)?,
+                integer(minute.as_number()?)?,
+                integer(second.as_number()?)?,
+            );
+            let seconds = hour
+                .checked_mul(3600)
+                .zip(minute.checked_mul(60))
+                .and_then(|(hours, minutes)| hours.checked_add(minutes)?.checked_add(second))
+                .ok_or(Error::Number)?;
+            if seconds < 0 {
+                return Err(Error::Number);
+            }
+            Ok(BigDecimal::from(seconds % 86_400) / BigDecimal::from(86_400))
+        }
+        _ => Err(Error::Value),
+    };
+    inner().map_or_else(Value::Error, Value::DateTime)
+}
+
+/// `today()`: the current date, in the local time zone
+pub fn today(arguments: &[Value]) -> Value {
+    match arguments {
+        [] => date_value(Some(Local::now().date_naive())),
+        _ => Value::Error(Error::Value),
+    }
+}
+
+/// `now()`: the current date and time, in the local time zone
+pub fn now(arguments: &[Value]) -> Value {
+    match arguments {
+        [] => {
+            // whole seconds, so that times are displayed exactly
+            let now = Local::now().naive_local();
+            let now = now.with_nanosecond(0).unwrap_or(now);
+            Value::DateTime(datetime::serial(now))
+        }
+        _ => Value::Error(Error::Value),
+    }
+}
+
+/// Calls a function extracting a part of a date and time.
+fn part<F>(arguments: &[Value], function: F) -> Value
+where
+    F: FnOnce(NaiveDateTime) -> u32,
+{
+    let inner = || match arguments {
+        [value] => {
+            let datetime = from_serial(&serial_of(value)?).ok_or(Error::Number)?;
+            Ok(BigDecimal::from(function(datetime)))
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `year(date)`: the year of the date
+pub fn year(arguments: &[Value]) -> Value {
+    part(arguments, |datetime| datetime.year() as u32)
+}
+
+/// `month(date)`: the month of the date, from 1 (January) to 12 (December)
+pub fn month(arguments: &[Value]) -> Value {
+    part(arguments, |datetime| datetime.month())
+}
+
+/// `day(date)`: the day of the month of the date
+pub fn day(arguments: &[Value]) -> Value {
+    part(arguments, |datetime| datetime.day())
+}
+
+/// `hour(time)`: the hour of the time, from 0 to 23
+pub fn hour(arguments: &[Value]) -> Value {
+    part(arguments, |datetime| datetime.hour())
+}
+
+/// `minute(time)`: the minute of the time, from 0 to 59
+pub fn minute(arguments: &[Value]) -> Value {
+    part(arguments, |datetime| datetime.minute())
+}
+
+/// `second(time)`: the second of the time, from 0 to 59
+pub fn second(arguments: &[Value]) -> Value {
+    part(arguments, |datetime| datetime.second())
+}
+
+/// `weekday(date, [type])`: the day of the week of the date. With type `1` (the default),
+/// days are numbered from 1 (Sunday) to 7 (Saturday); with type `2`, from 1 (Monday) to 7 (Sunday);
+/// and with type `3`, from 0 (Monday) to 6 (Sunday).
+pub fn weekday(arguments: &[Value]) -> Value {
+    let inner = || {
+        let (date, kind) = match arguments {
+            [date] => (date_of(date)?, 1),
+            [date, kind] => (date_of(date)?, integer(kind.as_number()?)?),
+            _ => return Err(Error::Value),
+        };
+        let weekday = date.weekday();
+        let number = match kind {
+            1 => weekday.number_from_sunday(),
+            2 => weekday.number_from_monday(),
+            3 => weekday.num_days_from_monday(),
+            _ => return Err(Error::Number),
+        };
+        Ok(BigDecimal::from(number))
+    };
+    result(inner())
+}
+
+/// `edate(date, months)`: the date the given number of months before or after the date;
+/// if the month is shorter, the result is its last day
+pub fn edate(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [date, months] => Ok(add_months(date_of(date)?, integer(months.as_number()?)?)),
+        _ => Err(Error::Value),
+    };
+    inner().map_or_else(Value::Error, date_value)
+}
+
+/// `eomonth(date, months)`: the last day of the month the given number of months before or after the date
+pub fn eomonth(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [date, months] => {
+            let date = date_of(date)?
+                .with_day(1)
+                .expect("every month has a first day");
+            let first = add_months(date, integer(months.as_number()?)?);
+            Ok(first
+                .and_then(|first| first.checked_add_months(Months::new(1)))
+                .and_then(|next| next.pred_opt()))
+        }
+        _ => Err(Error::Value),
+    };
+    inner().map_or_else(Value::Error, date_value)
+}
+
+/// The number of whole months from one date to a later one
+fn whole_months(start: NaiveDate, end: NaiveDate) -> i64 {
+    let months = i64::from(end.year() - start.year()) * 12 + i64::from(end.month())
+        - i64::from(start.month());
+    // the last month isn't complete if the end date is before the start's day of the month
+    if end.day() < start.day() {
+        months - 1
+    } else {
+        months
+    }
+}
+
+/// `datedif(start, end, unit)`: the difference between two dates, where the end date must not be before
+/// the start date. The unit is one of:
+///
+/// - `"Y"`: whole years
+/// - `"M"`: whole months
+/// - `"D"`: days
+/// - `"YM"`: whole months, ignoring the years
+/// - `"YD"`: days, ignoring the years
+/// - `"MD"`: days, ignoring the months and years
+pub fn datedif(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [start, end, unit] => {
+            let (start, end) = (date_of(start)?, date_of(end)?);
+            if start > end {
+                return Err(Error::Number);
+            }
+            let months = whole_months(start, end);
+            let days_since = |months: i64| {
+                let date = add_months(start, months).ok_or(Error::Number)?;
+                Ok((end - date).num_days())
+            };
+
+            let difference = match unit.to_text()?.to_ascii_uppercase().as_str() {
+                "Y" => months / 12,
+                "M" => months,
+                "D" => (end - start).num_days(),
+                "YM" => months % 12,
+                "YD" => days_since(months / 12 * 12)?,
+                "MD" => days_since(months)?,
+                _ => return Err(Error::Number),
+            };
+            Ok(BigDecimal::from(difference))
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `networkdays(start, end, [holidays])`: the number of working days from the start date to the end date,
+/// including both. Working days are Monday to Friday, except for the holidays, which may be a range of dates.
+/// If the end date is before the start date, the result is negative.
+pub fn networkdays(arguments: &[Value]) -> Value {
+    let inner = || {
+        let (start, end, holidays) = match arguments {
+            [start, end] => (date_of(start)?, date_of(end)?, holidays(None)?),
+            [start, end, dates] => (date_of(start)?, date_of(end)?, holidays(Some(dates))?),
+            _ => return Err(Error::Value),
+        };
+        let (first, last, sign) = if start <= end {
+            (start, end, 1)
+        } else {
+            (end, start, -1)
+        };
+
+        let days = first
+            .iter_days()
+            .take_while(|date| *date <= last)
+            .filter(|date| !is_weekend(*date) && !holidays.contains(date))
+            .count() as i64;
+        Ok(BigDecimal::from(sign * days))
+    };
+    result(inner())
+}
+
+/// `workday(start, days, [holidays])`: the date the given number of working days after the start date,
+/// or before it if the number is negative. Working days are as for `networkdays`.
+pub fn workday(arguments: &[Value]) -> Value {
+    let inner = || {
+        let (start, days, holidays) = match arguments {
+            [start, days] => (date_of(start)?, days, holidays(None)?),
+            [start, days, dates] => (date_of(start)?, days, holidays(Some(dates))?),
+            _ => return Err(Error::Value),
+        };
+        let days = integer(days.as_number()?)?;
+
+        let mut date = start;
+        for _ in 0..days.unsigned_abs() {
+            loop {
+                date = if days > 0 {
+                    date.succ_opt()
+                } else {
+                    date.pred_opt()
+                }
+                .ok_or(Error::Number)?;
+                if !is_weekend(date) && !holidays.contains(&date) {
+                    break;
+                }
+            }
+        }
+        Ok(Some(date))
+    };
+    inner().map_or_else(Value::Error, date_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::functions::testing::{error, number};
+    use crate::sheet::Sheet;
+
+    fn check(cases: &[(&str, Value)]) {
+        let mut sheet = Sheet::with_standard_library();
+        // holidays
+        sheet
+            .set_cell("A1".parse().unwrap(), "2024-12-25".to_string())
+            .unwrap();
+        sheet
+            .set_cell("A2".parse().unwrap(), "2024-12-26".to_string())
+            .unwrap();
+
+        let address = "B1".parse().unwrap();
+        for (formula, expected) in cases {
+            sheet.set_cell(address, formula.to_string()).unwrap();
+            assert_eq!(
+                &Value::from(sheet.value(&address)),
+                expected,
+                "formula {}",
+                formula
+            );
+        }
+    }
+
+    /// the date with the given ISO 8601 representation
+    fn date(value: &str) -> Value {
+        let (_, serial) = all_consuming(parse_datetime)(value).unwrap();
+        Value::DateTime(serial)
+    }
+
+    #[test]
+    fn test_date_time() {
+        check(&[
+            ("=date(2024, 2, 29)", date("2024-02-29")),
+            ("=date(2024, 14, 1)", date("2025-02-01")),
+            ("=date(2024, 3, 0)", date("2024-02-29")),
+            ("=date(2024, 1, -1)", date("2023-12-30")),
+            ("=date(2024, 0, 1)", date("2023-12-01")),
+            ("=date(1800, 1, 1)", error(Error::Number)),
+            ("=date(10000, 1, 1)", error(Error::Number)),
+            ("=date(2024, 1)", error(Error::Value)),
+            ("=time(13, 30, 0)", date("13:30")),
+            ("=time(0, 90, 30)", date("01:30:30")),
+            ("=time(25, 0, 0)", date("01:00")),
+            ("=time(-1, 0, 0)", error(Error::Number)),
+            (
+                "=date(2024, 2, 29) + time(12, 0, 0)",
+                date("2024-02-29T12:00"),
+            ),
+            ("=date(2024, 3, 1) - date(2024, 2, 1)", number("29")),
+        ]);
+    }
+
+    #[test]
+    fn test_today_now() {
+        check(&[
+            ("=today() = int(now())", Value::Boolean(true)),
+            ("=today() > date(2024, 1, 1)", Value::Boolean(true)),
+            ("=now() - today() < 1", Value::Boolean(true)),
+            ("=today(1)", error(Error::Value)),
+        ]);
+    }
+
+    #[test]
+    fn test_parts() {
+        check(&[
+            ("=year(date(2024, 2, 29))", number("2024")),
+            ("=month(date(2024, 2, 29))", number("2")),
+            ("=day(date(2024, 2, 29))", number("29")),
+            ("=day(A1)", number("25")),
+            ("=year(\"2024-02-29\")", number("2024")),
+            ("=month(45351)", number("2")),
+            ("=hour(time(13, 30, 15))", number("13")),
+            ("=minute(time(13, 30, 15))", number("30")),
+            ("=second(time(13, 30, 15))", number("15")),
+            ("=hour(\"2024-02-29 18:45\")", number("18")),
+            ("=year(-1)", error(Error::Number)),
+            ("=year(\"foo\")", error(Error::Value)),
+            ("=weekday(date(2024, 2, 29))", number("5")),
+            ("=weekday(date(2024, 3, 3))", number("1")),
+            ("=weekday(date(2024, 3, 3), 2)", number("7")),
+            ("=weekday(date(2024, 3, 3), 3)", number("6")),
+            ("=weekday(date(2024, 3, 4), 3)", number("0")),
+            ("=weekday(date(2024, 3, 4), 4)", error(Error::Number)),
+        ]);
+    }
+
+    #[test]
+    fn test_edate_eomonth() {
+        check(&[
+            ("=edate(date(2024, 1, 31), 1)", date("2024-02-29")),
+            ("=edate(date(2024, 1, 31), -2)", date("2023-11-30")),
+            ("=edate(date(2024, 1, 15), 12)", date("2025-01-15")),
+            ("=edate(date(2024, 1, 15), 0)", date("2024-01-15")),
+            ("=eomonth(date(2024, 1, 15), 1)", date("2024-02-29")),
+            ("=eomonth(date(2024, 1, 15), 0)", date("2024-01-31")),
+            ("=eomonth(date(2024, 1, 15), -1)", date("2023-12-31")),
+            ("=eomonth(date(9999, 12, 1), 1)", error(Error::Number)),
+        ]);
+    }
+
+    #[test]
+    fn test_datedif() {
+        check(&[
+            (
+                "=datedif(date(2020, 5, 15), date(2024, 2, 29), \"Y\")",
+                number("3"),
+            ),
+            (
+                "=datedif(date(2020, 5, 15), date(2024, 2, 29), \"M\")",
+                number("45"),
+            ),
+            (
+                "=datedif(date(2020, 5, 15), date(2024, 2, 29), \"D\")",
+                number("1385"),
+            ),
+            (
+                "=datedif(date(2020, 5, 15), date(2024, 2, 29), \"YM\")",
+                number("9"),
+            ),
+            (
+                "=datedif(date(2020, 5, 15), date(2024, 2, 29), \"YD\")",
+                number("290"),
+            ),
+            (
+                "=datedif(date(2020, 5, 15), date(2024, 2, 29), \"MD\")",
+                number("14"),
+            ),
+            (
+                "=datedif(date(2024, 1, 31), date(2024, 2, 29), \"M\")",
+                number("0"),
+            ),
+            (
+                "=datedif(date(2024, 1, 31), date(2024, 3, 1), \"M\")",
+                number("1"),
+            ),
+            (
+                "=datedif(date(2024, 1, 31), date(2024, 3, 1), \"md\")",
+                number("1"),
+            ),
+            (
+                "=datedif(date(2024, 2, 29), date(2025, 2, 28), \"Y\")",
+                number("0"),
+            ),
+            (
+                "=datedif(date(2024, 2, 29), date(2024, 1, 1), \"D\")",
+                error(Error::Number),
+            ),
+            (
+                "=datedif(date(2024, 1, 1), date(2024, 2, 1), \"W\")",
+                error(Error::Number),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_workdays() {
+        check(&[
+            // Monday to Friday
+            (
+                "=networkdays(date(2024, 3, 4), date(2024, 3, 8))",
+                number("5"),
+            ),
+            (
+                "=networkdays(date(2024, 3, 4), date(2024, 3, 17))",
+                number("10"),
+            ),
+            (
+                "=networkdays(date(2024, 3, 8), date(2024, 3, 4))",
+                number("-5"),
+            ),
+            (
+                "=networkdays(date(2024, 3, 9), date(2024, 3, 10))",
+                number("0"),
+            ),
+            (
+                "=networkdays(date(2024, 12, 23), date(2024, 12, 27))",
+                number("5"),
+            ),
+            (
+                "=networkdays(date(2024, 12, 23), date(2024, 12, 27), A1:A2)",
+                number("3"),
+            ),
+            (
+                "=networkdays(date(2024, 12, 23), date(2024, 12, 27), A1)",
+                number("4"),
+            ),
+            ("=workday(date(2024, 3, 8), 1)", date("2024-03-11")),
+            ("=workday(date(2024, 3, 4), 10)", date("2024-03-18")),
+            ("=workday(date(2024, 3, 11), -1)", date("2024-03-08")),
+            ("=workday(date(2024, 3, 9), 0)", date("2024-03-09")),
+            ("=workday(date(2024, 12, 24), 1, A1:A2)", date("2024-12-27")),
+            (
+                "=workday(date(2024, 12, 24), 1, #N/A)",
+                error(Error::NotAvailable),
+            ),
+        ]);
+    }
+}
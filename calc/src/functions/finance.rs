@@ -0,0 +1,898 @@
+//! Financial functions such as `pmt`, `npv` or `irr`.
+//!
+//! As usual in spreadsheets, money paid out is negative and money received is positive, and payments are
+//! due at the end of each period, unless the optional `type` argument is non-zero for payments at the beginning.
+//!
+//! Results that only need exact operations, such as those with a rate of `0` or `sln`, are exact.
+//! Compound interest is calculated at a higher precision and then rounded to [`PRECISION`] significant digits,
+//! like other inexact results. `rate`, `irr` and `xirr` solve for the rate iteratively, until successive
+//! approximations differ by less than [`tolerance`]; if that takes more than [`MAX_ITERATIONS`] steps,
+//! or an approximation leaves the valid range, the result is `#NUM!`.
+
+use bigdecimal::{BigDecimal, One, Signed, Zero};
+
+use crate::value::{Error, Value};
+
+use super::math::{inexact, integer, ln_of, power_of, round_to, working, Rounding, PRECISION};
+use super::{numbers, result};
+
+/// The largest number of steps of the iterative solvers
+pub const MAX_ITERATIONS: usize = 100;
+
+/// Iterative solvers stop once successive approximations differ by less than this (10⁻¹⁷)
+pub fn tolerance() -> BigDecimal {
+    BigDecimal::new(1.into(), PRECISION as i64 + 2)
+}
+
+/// The default guess of `rate`, `irr` and `xirr`
+fn default_guess() -> BigDecimal {
+    BigDecimal::new(1.into(), 1)
+}
+
+/// Rounds a result calculated at working precision, unless the rate is `0` so that it's exact
+fn rounded(number: BigDecimal, rate: &BigDecimal) -> BigDecimal {
+    if rate.is_zero() {
+        number
+    } else {
+        inexact(number)
+    }
+}
+
+/// The number of an optional argument, or the default if it's missing
+fn optional(argument: Option<&Value>, default: BigDecimal) -> Result<BigDecimal, Error> {
+    argument.map_or(Ok(default), |argument| argument.as_number().cloned())
+}
+
+/// Whether the optional `type` argument asks for payments at the beginning of each period
+fn beginning(argument: Option<&Value>) -> Result<bool, Error> {
+    Ok(!optional(argument, BigDecimal::zero())?.is_zero())
+}
+
+/// `(1 - rate)^periods`, the part of a book value that is left after declining by the rate for some periods
+fn decline(rate: &BigDecimal, periods: i64) -> Result<BigDecimal, Error> {
+    if periods == 0 {
+        return Ok(BigDecimal::one());
+    }
+    power_of(&(BigDecimal::one() - rate), &BigDecimal::from(periods))
+}
+
+/// The growth `(1 + rate)^periods` of a present value and the factor by which payments grow to a future value,
+/// such that `pv * growth + pmt * factor + fv = 0`
+fn annuity(
+    rate: &BigDecimal,
+    periods: &BigDecimal,
+    beginning: bool,
+) -> Result<(BigDecimal, BigDecimal), Error> {
+    if rate.is_zero() {
+        return Ok((BigDecimal::one(), periods.clone()));
+    }
+    let growth = power_of(&(BigDecimal::one() + rate), periods)?;
+    let mut factor = working((&growth - BigDecimal::one()) / rate);
+    if beginning {
+        factor = working(factor * (BigDecimal::one() + rate));
+    }
+    Ok((growth, factor))
+}
+
+/// The future value, with the sign convention of `fv`
+fn future_value(
+    rate: &BigDecimal,
+    periods: &BigDecimal,
+    payment: &BigDecimal,
+    present: &BigDecimal,
+    beginning: bool,
+) -> Result<BigDecimal, Error> {
+    let (growth, factor) = annuity(rate, periods, beginning)?;
+    Ok(-(present * growth + payment * factor))
+}
+
+/// The payment per period, with the sign convention of `pmt`
+fn payment(
+    rate: &BigDecimal,
+    periods: &BigDecimal,
+    present: &BigDecimal,
+    future: &BigDecimal,
+    beginning: bool,
+) -> Result<BigDecimal, Error> {
+    let (growth, factor) = annuity(rate, periods, beginning)?;
+    if factor.is_zero() {
+        return Err(Error::Number);
+    }
+    Ok(-(present * growth + future) / factor)
+}
+
+/// The interest part of the payment in the given period, with the sign convention of `ipmt`
+fn interest(
+    rate: &BigDecimal,
+    period: &BigDecimal,
+    periods: &BigDecimal,
+    present: &BigDecimal,
+    future: &BigDecimal,
+    beginning: bool,
+) -> Result<BigDecimal, Error> {
+    if period < &BigDecimal::one() || period > periods {
+        return Err(Error::Number);
+    }
+    let payment = payment(rate, periods, present, future, beginning)?;
+    // the interest is that of the balance after the previous period; payments at the beginning of a period
+    // don't accrue interest in it
+    let balance = if period.is_one() {
+        if beginning {
+            BigDecimal::zero()
+        } else {
+            -present
+        }
+    } else if beginning {
+        let two = BigDecimal::from(2);
+        future_value(rate, &(period - two), &payment, present, true)? - payment
+    } else {
+        future_value(
+            rate,
+            &(period - BigDecimal::one()),
+            &payment,
+            present,
+            false,
+        )?
+    };
+    Ok(balance * rate)
+}
+
+/// Finds a root of the function with the secant method, starting from the guess
+fn solve<F>(guess: BigDecimal, function: F) -> Result<BigDecimal, Error>
+where
+    F: Fn(&BigDecimal) -> Result<BigDecimal, Error>,
+{
+    let tolerance = tolerance();
+    let mut previous = guess;
+    let mut previous_value = function(&previous)?;
+    let mut current = &previous + BigDecimal::new(1.into(), 4);
+    for _ in 0..MAX_ITERATIONS {
+        let value = function(&current)?;
+        if value.is_zero() {
+            return Ok(inexact(current));
+        }
+        let slope = working((&value - &previous_value) / (&current - &previous));
+        if slope.is_zero() {
+            return Err(Error::Number);
+        }
+        let next = working(&current - value.clone() / slope);
+        if (&next - &current).abs() < tolerance {
+            return Ok(inexact(next));
+        }
+        previous = current;
+        previous_value = value;
+        current = next;
+    }
+    Err(Error::Number)
+}
+
+/// Solves for the rate of cash flows, which must include positive and negative ones
+fn solve_rate<F>(flows: &[&BigDecimal], guess: BigDecimal, net_present_value: F) -> Value
+where
+    F: Fn(&BigDecimal) -> Result<BigDecimal, Error>,
+{
+    let positive = flows.iter().any(|flow| flow.is_positive());
+    let negative = flows.iter().any(|flow| flow.is_negative());
+    if !positive || !negative {
+        return Value::Error(Error::Number);
+    }
+    // rates of -1 or less are meaningless, and errors of the function mean that the solver diverged
+    result(solve(guess, |rate| {
+        if rate <= &-BigDecimal::one() {
+            return Err(Error::Number);
+        }
+        net_present_value(rate).map_err(|_| Error::Number)
+    }))
+}
+
+/// `Σ flows[i] / (1 + rate)^i`, where the first flow isn't discounted
+fn discounted(rate: &BigDecimal, flows: &[&BigDecimal]) -> Result<BigDecimal, Error> {
+    let base = BigDecimal::one() + rate;
+    if base.is_zero() {
+        return Err(Error::DivisionByZero);
+    }
+    let factor = working(BigDecimal::one() / base);
+    // Horner's method: flows[0] + factor * (flows[1] + factor * (flows[2] + ...))
+    Ok(flows.iter().rev().fold(BigDecimal::zero(), |sum, flow| {
+        working(sum * &factor + *flow)
+    }))
+}
+
+/// `Σ flows[i] / (1 + rate)^((dates[i] - dates[0]) / 365)`
+fn discounted_by_date(
+    rate: &BigDecimal,
+    flows: &[&BigDecimal],
+    dates: &[&BigDecimal],
+) -> Result<BigDecimal, Error> {
+    let base = BigDecimal::one() + rate;
+    let first = dates[0].with_scale(0);
+    let year = BigDecimal::from(365);
+    flows
+        .iter()
+        .zip(dates)
+        .try_fold(BigDecimal::zero(), |sum, (flow, date)| {
+            let years = (date.with_scale(0) - &first) / &year;
+            Ok(sum + working(*flow / power_of(&base, &years)?))
+        })
+}
+
+/// The cash flows and dates of `xnpv` and `xirr`, which must have the same, non-zero length
+fn flows_and_dates<'a>(
+    flows: &'a Value,
+    dates: &'a Value,
+) -> Result<(Vec<&'a BigDecimal>, Vec<&'a BigDecimal>), Error> {
+    let flows = numbers(std::slice::from_ref(flows))?;
+    let dates = numbers(std::slice::from_ref(dates))?;
+    if flows.is_empty() || flows.len() != dates.len() {
+        return Err(Error::Number);
+    }
+    if dates.iter().any(|date| date < &dates[0]) {
+        return Err(Error::Number);
+    }
+    Ok((flows, dates))
+}
+
+/// `pv(rate, nper, pmt, [fv], [type])`: the present value of `nper` periodic payments `pmt`
+/// and a final value `fv` (default `0`)
+pub fn pv(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [rate, periods, payment, rest @ ..] if rest.len() <= 2 => {
+            let rate = rate.as_number()?;
+            let future = optional(rest.first(), BigDecimal::zero())?;
+            let (growth, factor) = annuity(rate, periods.as_number()?, beginning(rest.get(1))?)?;
+            if growth.is_zero() {
+                return Err(Error::Number);
+            }
+            let present = -(future + payment.as_number()? * factor) / growth;
+            Ok(rounded(present, rate))
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `fv(rate, nper, pmt, [pv], [type])`: the future value of a present value `pv` (default `0`)
+/// and `nper` periodic payments `pmt`
+pub fn fv(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [rate, periods, payment, rest @ ..] if rest.len() <= 2 => {
+            let rate = rate.as_number()?;
+            let present = optional(rest.first(), BigDecimal::zero())?;
+            let future = future_value(
+                rate,
+                periods.as_number()?,
+                payment.as_number()?,
+                &present,
+                beginning(rest.get(1))?,
+            )?;
+            Ok(rounded(future, rate))
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `pmt(rate, nper, pv, [fv], [type])`: the periodic payment that pays off a loan `pv` in `nper` periods,
+/// leaving the final value `fv` (default `0`)
+pub fn pmt(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [rate, periods, present, rest @ ..] if rest.len() <= 2 => {
+            let rate = rate.as_number()?;
+            let future = optional(rest.first(), BigDecimal::zero())?;
+            let payment = payment(
+                rate,
+                periods.as_number()?,
+                present.as_number()?,
+                &future,
+                beginning(rest.get(1))?,
+            )?;
+            Ok(rounded(payment, rate))
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `ipmt(rate, per, nper, pv, [fv], [type])`: the interest part of the payment in period `per`,
+/// which is between 1 and `nper`
+pub fn ipmt(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [rate, period, periods, present, rest @ ..] if rest.len() <= 2 => {
+            let rate = rate.as_number()?;
+            let interest = interest(
+                rate,
+                period.as_number()?,
+                periods.as_number()?,
+                present.as_number()?,
+                &optional(rest.first(), BigDecimal::zero())?,
+                beginning(rest.get(1))?,
+            )?;
+            Ok(rounded(interest, rate))
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `ppmt(rate, per, nper, pv, [fv], [type])`: the principal part of the payment in period `per`,
+/// which is the payment minus the interest
+pub fn ppmt(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [rate, period, periods, present, rest @ ..] if rest.len() <= 2 => {
+            let rate = rate.as_number()?;
+            let (periods, present) = (periods.as_number()?, present.as_number()?);
+            let future = optional(rest.first(), BigDecimal::zero())?;
+            let beginning = beginning(rest.get(1))?;
+            let interest = interest(
+                rate,
+                period.as_number()?,
+                periods,
+                present,
+                &future,
+                beginning,
+            )?;
+            let payment = payment(rate, periods, present, &future, beginning)?;
+            Ok(rounded(payment - interest, rate))
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `nper(rate, pmt, pv, [fv], [type])`: the number of periods until periodic payments `pmt`
+/// pay off a loan `pv`, leaving the final value `fv` (default `0`)
+pub fn nper(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [rate, payment, present, rest @ ..] if rest.len() <= 2 => {
+            let (rate, payment, present) = (
+                rate.as_number()?,
+                payment.as_number()?,
+                present.as_number()?,
+            );
+            let future = optional(rest.first(), BigDecimal::zero())?;
+            if rate.is_zero() {
+                if payment.is_zero() {
+                    return Err(Error::Number);
+                }
+                return Ok(-(present + future) / payment);
+            }
+
+            // solving pv * (1 + rate)^n + pmt' * ((1 + rate)^n - 1) / rate + fv = 0 for n,
+            // where pmt' includes the interest of payments at the beginning
+            let payment = if beginning(rest.get(1))? {
+                payment * (BigDecimal::one() + rate)
+            } else {
+                payment.clone()
+            };
+            let numerator = &payment - &future * rate;
+            let denominator = &payment + present * rate;
+            let base = BigDecimal::one() + rate;
+            if denominator.is_zero() || !base.is_positive() {
+                return Err(Error::Number);
+            }
+            let ratio = working(numerator / denominator);
+            if !ratio.is_positive() {
+                return Err(Error::Number);
+            }
+            Ok(inexact(ln_of(&ratio) / ln_of(&base)))
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `rate(nper, pmt, pv, [fv], [type], [guess])`: the interest rate per period at which periodic payments `pmt`
+/// pay off a loan `pv` in `nper` periods, leaving the final value `fv` (default `0`).
+/// The rate is solved for iteratively, starting from the guess (default `0.1`).
+pub fn rate(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [periods, payment, present, rest @ ..] if rest.len() <= 3 => {
+            let (periods, payment, present) = (
+                periods.as_number()?,
+                payment.as_number()?,
+                present.as_number()?,
+            );
+            let future = optional(rest.first(), BigDecimal::zero())?;
+            let beginning = beginning(rest.get(1))?;
+            let guess = optional(rest.get(2), default_guess())?;
+            Ok(solve_rate(&[present, payment, &future], guess, |rate| {
+                let (growth, factor) = annuity(rate, periods, beginning)?;
+                Ok(working(present * growth + payment * factor + &future))
+            }))
+        }
+        _ => Err(Error::Value),
+    };
+    inner().unwrap_or_else(Value::Error)
+}
+
+/// `npv(rate, value, ...)`: the net present value of cash flows at the end of consecutive periods,
+/// discounted at the rate per period; in ranges, values other than numbers are ignored
+pub fn npv(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [rate, flows @ ..] if !flows.is_empty() => {
+            let rate = rate.as_number()?;
+            let flows = numbers(flows)?;
+            // the first flow is discounted by one period
+            let zero = BigDecimal::zero();
+            let flows: Vec<_> = std::iter::once(&zero).chain(flows).collect();
+            Ok(rounded(discounted(rate, &flows)?, rate))
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `irr(values, [guess])`: the internal rate of return of cash flows at regular intervals,
+/// which is the rate at which their net present value is `0`. The first value isn't discounted.
+/// The rate is solved for iteratively, starting from the guess (default `0.1`).
+pub fn irr(arguments: &[Value]) -> Value {
+    let inner = || {
+        let (flows, guess) = match arguments {
+            [flows] => (flows, default_guess()),
+            [flows, guess] => (flows, guess.as_number()?.clone()),
+            _ => return Err(Error::Value),
+        };
+        let flows = numbers(std::slice::from_ref(flows))?;
+        Ok(solve_rate(&flows, guess, |rate| discounted(rate, &flows)))
+    };
+    inner().unwrap_or_else(Value::Error)
+}
+
+/// `xnpv(rate, values, dates)`: the net present value of cash flows at the given dates,
+/// discounted at the yearly rate to the first date. Years are counted as 365 days.
+pub fn xnpv(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [rate, flows, dates] => {
+            let rate = rate.as_number()?;
+            if rate <= &-BigDecimal::one() {
+                return Err(Error::Number);
+            }
+            let (flows, dates) = flows_and_dates(flows, dates)?;
+            Ok(rounded(discounted_by_date(rate, &flows, &dates)?, rate))
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `xirr(values, dates, [guess])`: the yearly internal rate of return of cash flows at the given dates,
+/// which is the rate at which their `xnpv` is `0`.
+/// The rate is solved for iteratively, starting from the guess (default `0.1`).
+pub fn xirr(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [flows, dates, rest @ ..] if rest.len() <= 1 => {
+            let (flows, dates) = flows_and_dates(flows, dates)?;
+            let guess = optional(rest.first(), default_guess())?;
+            Ok(solve_rate(&flows, guess, |rate| {
+                discounted_by_date(rate, &flows, &dates)
+            }))
+        }
+        _ => Err(Error::Value),
+    };
+    inner().unwrap_or_else(Value::Error)
+}
+
+/// `sln(cost, salvage, life)`: the straight-line depreciation per period
+pub fn sln(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [cost, salvage, life] => {
+            let life = life.as_number()?;
+            if life.is_zero() {
+                return Err(Error::DivisionByZero);
+            }
+            Ok((cost.as_number()? - salvage.as_number()?) / life)
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `db(cost, salvage, life, period, [month])`: the fixed-declining balance depreciation in the given period,
+/// where the first year has `month` months (default `12`). The rate is `1 - (salvage / cost)^(1 / life)`,
+/// rounded to three decimal places; if the first year is shorter, there is a period `life + 1` for the rest.
+/// The result is rounded to [`PRECISION`] significant digits.
+pub fn db(arguments: &[Value]) -> Value {
+    let inner = || {
+        let (cost, salvage, life, period, months) = match arguments {
+            [cost, salvage, life, period, rest @ ..] if rest.len() <= 1 => (
+                cost.as_number()?,
+                salvage.as_number()?,
+                integer(life.as_number()?)?,
+                integer(period.as_number()?)?,
+                integer(&optional(rest.first(), BigDecimal::from(12))?)?,
+            ),
+            _ => return Err(Error::Value),
+        };
+        let last = if months < 12 { life + 1 } else { life };
+        if cost.is_negative()
+            || salvage.is_negative()
+            || life <= 0
+            || !(1..=12).contains(&months)
+            || !(1..=last).contains(&period)
+        {
+            return Err(Error::Number);
+        }
+        if cost.is_zero() {
+            return Ok(BigDecimal::zero());
+        }
+
+        let exponent = BigDecimal::one() / BigDecimal::from(life);
+        let remaining = power_of(&working(salvage / cost), &exponent)?;
+        let rate = round_to(&(BigDecimal::one() - remaining), 3, Rounding::HalfUp);
+        let twelfths = |months: i64| BigDecimal::from(months) / BigDecimal::from(12);
+
+        // the first period only has the given months; after it, the book value declines by the rate each period
+        let first = cost * &rate * twelfths(months);
+        if period == 1 {
+            return Ok(inexact(first));
+        }
+        let decline = decline(&rate, period - 2)?;
+        let mut depreciation = (cost - first) * decline * &rate;
+        if period == life + 1 {
+            depreciation *= twelfths(12 - months);
+        }
+        Ok(inexact(depreciation))
+    };
+    result(inner())
+}
+
+/// `ddb(cost, salvage, life, period, [factor])`: the declining balance depreciation in the given period,
+/// at `factor` (default `2`) times the straight-line rate; the book value doesn't fall below the salvage.
+/// The period is truncated to an integer.
+pub fn ddb(arguments: &[Value]) -> Value {
+    let inner = || {
+        let (cost, salvage, life, period, factor) = match arguments {
+            [cost, salvage, life, period, rest @ ..] if rest.len() <= 1 => (
+                cost.as_number()?,
+                salvage.as_number()?,
+                life.as_number()?,
+                integer(period.as_number()?)?,
+                optional(rest.first(), BigDecimal::from(2))?,
+            ),
+            _ => return Err(Error::Value),
+        };
+        if cost.is_negative()
+            || salvage.is_negative()
+            || !life.is_positive()
+            || !factor.is_positive()
+            || period < 1
+            || BigDecimal::from(period) > *life
+        {
+            return Err(Error::Number);
+        }
+
+        // the book value declines by the rate each period, until it reaches the salvage
+        let rate = working(factor / life).min(BigDecimal::one());
+        let decline = decline(&rate, period - 1)?;
+        let book = working(cost * decline).max(salvage.min(cost).clone());
+        let remaining = (&book - salvage).max(BigDecimal::zero());
+        let depreciation = working(&book * &rate).min(remaining);
+        Ok(inexact(depreciation))
+    };
+    result(inner())
+}
+
+/// `effect(nominal_rate, npery)`: the effective yearly rate of a nominal rate compounded `npery` times a year
+pub fn effect(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [rate, periods] => {
+            let (rate, periods) = (rate.as_number()?, integer(periods.as_number()?)?);
+            if !rate.is_positive() || periods < 1 {
+                return Err(Error::Number);
+            }
+            let periods = BigDecimal::from(periods);
+            let base = BigDecimal::one() + working(rate / &periods);
+            Ok(inexact(power_of(&base, &periods)? - BigDecimal::one()))
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `nominal(effect_rate, npery)`: the nominal yearly rate compounded `npery` times a year
+/// that results in the effective rate
+pub fn nominal(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [rate, periods] => {
+            let (rate, periods) = (rate.as_number()?, integer(periods.as_number()?)?);
+            if !rate.is_positive() || periods < 1 {
+                return Err(Error::Number);
+            }
+            let periods = BigDecimal::from(periods);
+            let exponent = working(BigDecimal::one() / &periods);
+            let base = BigDecimal::one() + rate;
+            Ok(inexact(
+                (power_of(&base, &exponent)? - BigDecimal::one()) * periods,
+            ))
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::functions::testing::{column, error, number, numbers};
+
+    /// a number given as a quotient, such as a monthly rate
+    fn quotient(numerator: &str, denominator: i32) -> Value {
+        Value::Number(numerator.parse::<BigDecimal>().unwrap() / BigDecimal::from(denominator))
+    }
+
+    #[test]
+    fn test_pmt_pv_fv() {
+        let monthly = quotient("0.08", 12);
+        assert_eq!(
+            pmt(&[monthly.clone(), number("10"), number("10000")]),
+            number("-1037.03208935915")
+        );
+        assert_eq!(
+            pmt(&[
+                monthly,
+                number("10"),
+                number("10000"),
+                number("0"),
+                number("1")
+            ]),
+            number("-1030.16432717797")
+        );
+        assert_eq!(
+            pmt(&[quotient("0.06", 12), number("360"), number("200000")]),
+            number("-1199.1010503055")
+        );
+        assert_eq!(
+            pmt(&[number("0"), number("8"), number("1000"), number("200")]),
+            number("-150")
+        );
+        assert_eq!(
+            pmt(&[number("0.1"), number("0"), number("1000")]),
+            error(Error::Number)
+        );
+        assert_eq!(pmt(&[number("0.1"), number("10")]), error(Error::Value));
+        // the growth over this many periods is too large, and isn't calculated by squaring 60 times
+        assert_eq!(
+            fv(&[number("0.1"), number("1e18"), number("-1")]),
+            error(Error::Number)
+        );
+
+        assert_eq!(
+            pv(&[quotient("0.08", 12), number("240"), number("500")]),
+            number("-59777.145851188")
+        );
+        assert_eq!(
+            pv(&[number("0"), number("10"), number("-100"), number("500")]),
+            number("500")
+        );
+        assert_eq!(
+            fv(&[
+                quotient("0.06", 12),
+                number("10"),
+                number("-200"),
+                number("-500"),
+                number("1")
+            ]),
+            number("2581.40337406018")
+        );
+        assert_eq!(
+            fv(&[number("0"), number("12"), number("-100")]),
+            number("1200")
+        );
+        assert_eq!(
+            fv(&[number("0.1"), number("2"), Value::String("x".into())]),
+            error(Error::Value)
+        );
+    }
+
+    #[test]
+    fn test_ipmt_ppmt() {
+        assert_eq!(
+            ipmt(&[
+                quotient("0.1", 12),
+                number("1"),
+                number("36"),
+                number("8000")
+            ]),
+            number("-66.6666666666667")
+        );
+        assert_eq!(
+            ipmt(&[number("0.1"), number("3"), number("3"), number("8000")]),
+            number("-292.447129909366")
+        );
+        assert_eq!(
+            ipmt(&[
+                number("0.1"),
+                number("2"),
+                number("3"),
+                number("8000"),
+                number("0"),
+                number("1")
+            ]),
+            number("-507.552870090634")
+        );
+        assert_eq!(
+            ipmt(&[
+                number("0.1"),
+                number("1"),
+                number("3"),
+                number("8000"),
+                number("0"),
+                number("1")
+            ]),
+            number("0")
+        );
+        assert_eq!(
+            ipmt(&[number("0.1"), number("4"), number("3"), number("8000")]),
+            error(Error::Number)
+        );
+        assert_eq!(
+            ppmt(&[
+                quotient("0.1", 12),
+                number("1"),
+                number("24"),
+                number("2000")
+            ]),
+            number("-75.6231860083663")
+        );
+        assert_eq!(
+            ppmt(&[number("0"), number("2"), number("4"), number("1000")]),
+            number("-250")
+        );
+    }
+
+    #[test]
+    fn test_nper_rate() {
+        assert_eq!(
+            nper(&[
+                quotient("0.12", 12),
+                number("-100"),
+                number("-1000"),
+                number("10000"),
+                number("1")
+            ]),
+            number("59.6738656742946")
+        );
+        assert_eq!(
+            nper(&[number("0"), number("-100"), number("1000")]),
+            number("10")
+        );
+        assert_eq!(
+            nper(&[number("0.1"), number("-50"), number("1000")]),
+            error(Error::Number)
+        );
+
+        assert_eq!(
+            rate(&[number("48"), number("-200"), number("8000")]),
+            number("0.00770147248820204")
+        );
+        assert_eq!(
+            rate(&[number("10"), number("100"), number("1000")]),
+            error(Error::Number)
+        );
+    }
+
+    #[test]
+    fn test_npv_irr() {
+        assert_eq!(
+            npv(&[
+                number("0.1"),
+                number("-10000"),
+                numbers(&["3000", "4200", "6800"])
+            ]),
+            number("1188.44341233522")
+        );
+        assert_eq!(npv(&[number("0"), numbers(&["1", "2", "3"])]), number("6"));
+        assert_eq!(
+            npv(&[number("-1"), number("1")]),
+            error(Error::DivisionByZero)
+        );
+        assert_eq!(npv(&[number("0.1")]), error(Error::Value));
+
+        let flows = numbers(&["-70000", "12000", "15000", "18000", "21000", "26000"]);
+        assert_eq!(irr(&[flows]), number("0.0866309480365316"));
+        assert_eq!(
+            irr(&[numbers(&["-70000", "12000", "15000"]), number("-0.1")]),
+            number("-0.443506941334741")
+        );
+        assert_eq!(irr(&[numbers(&["-100", "110"])]), number("0.1"));
+        assert_eq!(irr(&[numbers(&["100", "110"])]), error(Error::Number));
+    }
+
+    #[test]
+    fn test_xnpv_xirr() {
+        let flows = numbers(&["-10000", "2750", "4250", "3250", "2750"]);
+        let dates = column(
+            &[39448, 39508, 39751, 39859, 39904].map(|serial| Value::DateTime(serial.into())),
+        );
+        assert_eq!(
+            xnpv(&[number("0.09"), flows.clone(), dates.clone()]),
+            number("2086.64760203154")
+        );
+        assert_eq!(
+            xirr(&[flows.clone(), dates.clone()]),
+            number("0.373362533518832")
+        );
+        assert_eq!(
+            xnpv(&[number("0.09"), flows, numbers(&["39448"])]),
+            error(Error::Number)
+        );
+        assert_eq!(
+            xirr(&[numbers(&["-100", "-10"]), numbers(&["39448", "39813"])]),
+            error(Error::Number)
+        );
+    }
+
+    #[test]
+    fn test_depreciation() {
+        assert_eq!(
+            sln(&[number("30000"), number("7500"), number("10")]),
+            number("2250")
+        );
+        assert_eq!(
+            sln(&[number("30000"), number("7500"), number("0")]),
+            error(Error::DivisionByZero)
+        );
+
+        let db = |period: &str| {
+            db(&[
+                number("1000000"),
+                number("100000"),
+                number("6"),
+                number(period),
+                number("7"),
+            ])
+        };
+        assert_eq!(db("1"), number("186083.333333333"));
+        assert_eq!(db("2"), number("259639.416666667"));
+        assert_eq!(db("7"), number("15845.0984738481"));
+        assert_eq!(db("8"), error(Error::Number));
+
+        let ddb = |life: &str, period: &str| {
+            ddb(&[number("2400"), number("300"), number(life), number(period)])
+        };
+        assert_eq!(ddb("3650", "1"), number("1.31506849315068"));
+        assert_eq!(ddb("120", "1"), number("40"));
+        assert_eq!(ddb("10", "1"), number("480"));
+        assert_eq!(ddb("10", "10"), number("22.1225472"));
+        assert_eq!(ddb("10", "11"), error(Error::Number));
+        // the book value reaches the salvage in the second period
+        let salvaged = |period: &str| {
+            super::ddb(&[number("2400"), number("1000"), number("5"), number(period)])
+        };
+        assert_eq!(salvaged("2"), number("440"));
+        assert_eq!(salvaged("3"), number("0"));
+        // a rate of 100% writes off everything in the first period
+        let all =
+            |period: &str| super::ddb(&[number("100"), number("0"), number("2"), number(period)]);
+        assert_eq!(all("1"), number("100"));
+        assert_eq!(all("2"), number("0"));
+
+        // very long lives don't take a step per period
+        let long = [
+            number("1000"),
+            number("100"),
+            number("1e15"),
+            number("1e15"),
+        ];
+        assert_eq!(super::db(&long), number("0"));
+        assert_eq!(super::ddb(&long), number("2.70670566473225e-13"));
+    }
+
+    #[test]
+    fn test_effect_nominal() {
+        assert_eq!(
+            effect(&[number("0.0525"), number("4")]),
+            number("0.0535426673707581")
+        );
+        assert_eq!(
+            nominal(&[number("0.053543"), number("4")]),
+            number("0.0525003198683559")
+        );
+        assert_eq!(effect(&[number("0.05"), number("0")]), error(Error::Number));
+        assert_eq!(
+            nominal(&[number("-0.05"), number("4")]),
+            error(Error::Number)
+        );
+    }
+}
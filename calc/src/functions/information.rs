@@ -0,0 +1,148 @@
+//! Functions for inspecting values, especially errors.
+
+use crate::formula::function::Argument;
+use crate::formula::Context;
+use crate::value::{Error, Value};
+
+/// the single argument of a function, or `None` for any other number of arguments
+fn single(arguments: &[Value]) -> Option<&Value> {
+    match arguments {
+        [argument] => Some(argument),
+        _ => None,
+    }
+}
+
+/// `iserror(value)`: whether the value is any error
+pub fn iserror(arguments: &[Value]) -> Value {
+    match single(arguments) {
+        Some(value) => Value::Boolean(matches!(value, Value::Error(_))),
+        None => Value::Error(Error::Value),
+    }
+}
+
+/// `iserr(value)`: whether the value is an error other than `#N/A`
+pub fn iserr(arguments: &[Value]) -> Value {
+    match single(arguments) {
+        Some(value) => {
+            Value::Boolean(matches!(value, Value::Error(error) if *error != Error::NotAvailable))
+        }
+        None => Value::Error(Error::Value),
+    }
+}
+
+/// `isna(value)`: whether the value is the `#N/A` error
+pub fn isna(arguments: &[Value]) -> Value {
+    match single(arguments) {
+        Some(value) => Value::Boolean(*value == Value::Error(Error::NotAvailable)),
+        None => Value::Error(Error::Value),
+    }
+}
+
+/// `iferror(value, fallback)`: the fallback if the value is an error, otherwise the value.
+/// The fallback is only evaluated if it is needed.
+pub fn iferror(arguments: &[Argument], _context: &dyn Context) -> Value {
+    match arguments {
+        [value, fallback] => match value.evaluate() {
+            Value::Error(_) => fallback.evaluate(),
+            value => value,
+        },
+        _ => Value::Error(Error::Value),
+    }
+}
+
+/// `ifna(value, fallback)`: the fallback if the value is `#N/A`, otherwise the value.
+/// The fallback is only evaluated if it is needed.
+pub fn ifna(arguments: &[Argument], _context: &dyn Context) -> Value {
+    match arguments {
+        [value, fallback] => match value.evaluate() {
+            Value::Error(Error::NotAvailable) => fallback.evaluate(),
+            value => value,
+        },
+        _ => Value::Error(Error::Value),
+    }
+}
+
+/// `errortype(value)`: the number identifying the kind of error,
+/// using the same numbers as other spreadsheet applications, or `#N/A` if the value is not an error.
+///
+/// | error     | number |
+/// |-----------|--------|
+/// | `#DIV/0!` | 2      |
+/// | `#VALUE!` | 3      |
+/// | `#REF!`   | 4      |
+/// | `#NAME?`  | 5      |
+/// | `#NUM!`   | 6      |
+/// | `#N/A`    | 7      |
+/// | `#CYCLE`  | 8      |
+pub fn errortype(arguments: &[Value]) -> Value {
+    let number = match single(arguments) {
+        Some(Value::Error(error)) => match error {
+            Error::DivisionByZero => 2,
+            Error::Value => 3,
+            Error::Reference => 4,
+            Error::Name => 5,
+            Error::Number => 6,
+            Error::NotAvailable => 7,
+            Error::Cycle => 8,
+        },
+        Some(_) => return Value::Error(Error::NotAvailable),
+        None => return Value::Error(Error::Value),
+    };
+    Value::Number(number.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::sheet::Sheet;
+
+    fn evaluate(formula: &str) -> Value {
+        let mut sheet = Sheet::new();
+        sheet.set_function("iserror", iserror);
+        sheet.set_function("iserr", iserr);
+        sheet.set_function("isna", isna);
+        sheet.set_lazy_function("iferror", iferror);
+        sheet.set_lazy_function("ifna", ifna);
+        sheet.set_function("errortype", errortype);
+
+        let address = "A1".parse().unwrap();
+        sheet.set_cell(address, formula.to_string()).unwrap();
+        sheet.value(&address).into()
+    }
+
+    fn number(value: i32) -> Value {
+        Value::Number(value.into())
+    }
+
+    #[test]
+    fn test_is_error() {
+        assert_eq!(evaluate("=iserror(1/0)"), Value::Boolean(true));
+        assert_eq!(evaluate("=iserror(#N/A)"), Value::Boolean(true));
+        assert_eq!(evaluate("=iserror(1)"), Value::Boolean(false));
+        assert_eq!(evaluate("=iserr(#N/A)"), Value::Boolean(false));
+        assert_eq!(evaluate("=iserr(#REF!)"), Value::Boolean(true));
+        assert_eq!(evaluate("=isna(#N/A)"), Value::Boolean(true));
+        assert_eq!(evaluate("=isna(#VALUE!)"), Value::Boolean(false));
+        assert_eq!(evaluate("=iserror()"), Value::Error(Error::Value));
+        // unknown functions are errors as well
+        assert_eq!(evaluate("=iserror(foo())"), Value::Boolean(true));
+    }
+
+    #[test]
+    fn test_if_error() {
+        assert_eq!(evaluate("=iferror(1/0, 2)"), number(2));
+        assert_eq!(evaluate("=iferror(1, 2)"), number(1));
+        assert_eq!(evaluate("=ifna(#N/A, 2)"), number(2));
+        assert_eq!(evaluate("=ifna(#NUM!, 2)"), Value::Error(Error::Number));
+        assert_eq!(evaluate("=iferror(1)"), Value::Error(Error::Value));
+    }
+
+    #[test]
+    fn test_error_type() {
+        assert_eq!(evaluate("=errortype(1/0)"), number(2));
+        assert_eq!(evaluate("=errortype(\"foo\" + 1)"), number(3));
+        assert_eq!(evaluate("=errortype(#n/a)"), number(7));
+        assert_eq!(evaluate("=errortype(1)"), Value::Error(Error::NotAvailable));
+    }
+}
@@ -0,0 +1,288 @@
+//! Logical functions: `if`, `and`, `or`, `not`, `xor`, `ifs` and `switch`.
+//!
+//! These are lazy functions, so that they can skip arguments whose values are not needed;
+//! e.g. the branch of `if` that is not taken is never evaluated.
+//! Like all other functions, they are part of the standard library, or can be registered on a sheet
+//! using [`Sheet::set_lazy_function`](crate::sheet::Sheet::set_lazy_function).
+
+use std::cmp::Ordering;
+
+use crate::formula::function::Argument;
+use crate::formula::Context;
+use crate::value::{Error, Value};
+
+/// The conditions an argument of `and`, `or` or `xor` contributes.
+/// In ranges, values other than booleans and numbers are ignored;
+/// given directly, only empty values are ignored and strings are an error.
+fn conditions(value: Value) -> Result<Vec<bool>, Error> {
+    match value {
+        Value::Array(array) => array
+            .values()
+            .iter()
+            .filter(|value| {
+                matches!(
+                    value,
+                    Value::Boolean(_) | Value::Number(_) | Value::Error(_)
+                )
+            })
+            .map(Value::as_boolean)
+            .collect(),
+        Value::Empty => Ok(Vec::new()),
+        value => Ok(vec![value.as_boolean()?]),
+    }
+}
+
+/// `if(condition, then, [otherwise])`: `then` if the condition is true, otherwise `otherwise` (or `FALSE`)
+pub fn if_(arguments: &[Argument], _context: &dyn Context) -> Value {
+    let (condition, then, otherwise) = match arguments {
+        [condition, then] => (condition, then, None),
+        [condition, then, otherwise] => (condition, then, Some(otherwise)),
+        _ => return Value::Error(Error::Value),
+    };
+
+    match condition.evaluate().as_boolean() {
+        Ok(true) => then.evaluate(),
+        Ok(false) => otherwise.map_or(Value::Boolean(false), |otherwise| otherwise.evaluate()),
+        Err(error) => Value::Error(error),
+    }
+}
+
+/// Evaluates the arguments until one contains a condition equal to `stop`, and returns `stop` in that case.
+/// Otherwise, returns `!stop`, or `#VALUE!` if there were no conditions at all.
+fn short_circuit(arguments: &[Argument], stop: bool) -> Value {
+    let mut any = false;
+    for argument in arguments {
+        match conditions(argument.evaluate()) {
+            Ok(conditions) if conditions.contains(&stop) => return Value::Boolean(stop),
+            Ok(conditions) => any |= !conditions.is_empty(),
+            Err(error) => return Value::Error(error),
+        }
+    }
+
+    if any {
+        Value::Boolean(!stop)
+    } else {
+        Value::Error(Error::Value)
+    }
+}
+
+/// `and(condition, ...)`: whether all conditions are true; stops at the first false one
+pub fn and(arguments: &[Argument], _context: &dyn Context) -> Value {
+    short_circuit(arguments, false)
+}
+
+/// `or(condition, ...)`: whether any condition is true; stops at the first true one
+pub fn or(arguments: &[Argument], _context: &dyn Context) -> Value {
+    short_circuit(arguments, true)
+}
+
+/// `not(condition)`: whether the condition is false
+pub fn not(arguments: &[Argument], _context: &dyn Context) -> Value {
+    match arguments {
+        [condition] => condition
+            .evaluate()
+            .as_boolean()
+            .map_or_else(Value::Error, |condition| Value::Boolean(!condition)),
+        _ => Value::Error(Error::Value),
+    }
+}
+
+/// `xor(condition, ...)`: whether an odd number of conditions are true
+pub fn xor(arguments: &[Argument], _context: &dyn Context) -> Value {
+    let mut any = false;
+    let mut result = false;
+    for argument in arguments {
+        match conditions(argument.evaluate()) {
+            Ok(conditions) => {
+                any |= !conditions.is_empty();
+                result ^= conditions.iter().filter(|condition| **condition).count() % 2 == 1;
+            }
+            Err(error) => return Value::Error(error),
+        }
+    }
+
+    if any {
+        Value::Boolean(result)
+    } else {
+        Value::Error(Error::Value)
+    }
+}
+
+/// `ifs(condition, value, ...)`: the value after the first true condition, or `#N/A` if none is true
+pub fn ifs(arguments: &[Argument], _context: &dyn Context) -> Value {
+    if arguments.is_empty() || !arguments.len().is_multiple_of(2) {
+        return Value::Error(Error::Value);
+    }
+
+    for pair in arguments.chunks(2) {
+        match pair[0].evaluate().as_boolean() {
+            Ok(true) => return pair[1].evaluate(),
+            Ok(false) => {}
+            Err(error) => return Value::Error(error),
+        }
+    }
+    Value::Error(Error::NotAvailable)
+}
+
+/// `switch(value, case, result, ..., [default])`: the result after the first case equal to the value,
+/// otherwise the default, or `#N/A` if there is none
+pub fn switch(arguments: &[Argument], _context: &dyn Context) -> Value {
+    let (value, cases) = match arguments {
+        [value, cases @ ..] if cases.len() >= 2 => (value.evaluate(), cases),
+        _ => return Value::Error(Error::Value),
+    };
+    if let Value::Error(error) = value {
+        return Value::Error(error);
+    }
+
+    let mut pairs = cases.chunks_exact(2);
+    for pair in &mut pairs {
+        match value.compare(&pair[0].evaluate()) {
+            Ok(Ordering::Equal) => return pair[1].evaluate(),
+            Ok(_) => {}
+            Err(error) => return Value::Error(error),
+        }
+    }
+    match pairs.remainder() {
+        [default] => default.evaluate(),
+        _ => Value::Error(Error::NotAvailable),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::sheet::Sheet;
+
+    fn evaluate(formula: &str) -> Value {
+        let mut sheet = Sheet::with_standard_library();
+        sheet
+            .set_cell("B1".parse().unwrap(), "\"foo\"".to_string())
+            .unwrap();
+        sheet
+            .set_cell("B2".parse().unwrap(), "TRUE".to_string())
+            .unwrap();
+        sheet
+            .set_cell("B3".parse().unwrap(), "0".to_string())
+            .unwrap();
+
+        let address = "A1".parse().unwrap();
+        sheet.set_cell(address, formula.to_string()).unwrap();
+        sheet.value(&address).into()
+    }
+
+    fn number(value: i32) -> Value {
+        Value::Number(value.into())
+    }
+
+    #[test]
+    fn test_if() {
+        assert_eq!(evaluate("=if(1 < 2, 1, 2)"), number(1));
+        assert_eq!(evaluate("=if(FALSE, 1, 2)"), number(2));
+        assert_eq!(evaluate("=if(0, 1)"), Value::Boolean(false));
+        assert_eq!(evaluate("=if(B1, 1, 2)"), Value::Error(Error::Value));
+        assert_eq!(evaluate("=if(1)"), Value::Error(Error::Value));
+
+        // the branch that isn't taken is not evaluated
+        assert_eq!(evaluate("=if(TRUE, 1, 1/0)"), number(1));
+        assert_eq!(evaluate("=if(FALSE, 1/0, 2)"), number(2));
+        assert_eq!(
+            evaluate("=if(#N/A, 1, 2)"),
+            Value::Error(Error::NotAvailable)
+        );
+    }
+
+    #[test]
+    fn test_lazy() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        let mut sheet = Sheet::with_standard_library();
+        sheet.set_function("effect", |_| {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            Value::Boolean(true)
+        });
+        for formula in [
+            "=if(TRUE, 1, effect())",
+            "=and(FALSE, effect())",
+            "=or(TRUE, effect())",
+            "=ifs(TRUE, 1, effect(), 2)",
+            "=switch(1, 1, 2, effect())",
+        ] {
+            sheet
+                .set_cell("A1".parse().unwrap(), formula.to_string())
+                .unwrap();
+        }
+        assert_eq!(CALLS.load(Ordering::SeqCst), 0);
+
+        sheet
+            .set_cell("A1".parse().unwrap(), "=if(FALSE, 1, effect())".to_string())
+            .unwrap();
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_and_or() {
+        assert_eq!(evaluate("=and(TRUE, 1)"), Value::Boolean(true));
+        assert_eq!(evaluate("=and(TRUE, 0)"), Value::Boolean(false));
+        assert_eq!(evaluate("=or(FALSE, 0)"), Value::Boolean(false));
+        assert_eq!(evaluate("=or(FALSE, 2)"), Value::Boolean(true));
+
+        // strings in ranges are ignored, but not given directly
+        assert_eq!(evaluate("=and(B1:B2)"), Value::Boolean(true));
+        assert_eq!(evaluate("=or(B1:B3)"), Value::Boolean(true));
+        assert_eq!(evaluate("=and(B1:B3)"), Value::Boolean(false));
+        assert_eq!(evaluate("=and(B1)"), Value::Error(Error::Value));
+        assert_eq!(evaluate("=and(C1)"), Value::Error(Error::Value));
+        assert_eq!(evaluate("=or()"), Value::Error(Error::Value));
+
+        // evaluation stops once the result is known
+        assert_eq!(evaluate("=and(FALSE, 1/0)"), Value::Boolean(false));
+        assert_eq!(evaluate("=or(TRUE, 1/0)"), Value::Boolean(true));
+        assert_eq!(
+            evaluate("=and(TRUE, 1/0)"),
+            Value::Error(Error::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn test_not_xor() {
+        assert_eq!(evaluate("=not(FALSE)"), Value::Boolean(true));
+        assert_eq!(evaluate("=not(1 = 1)"), Value::Boolean(false));
+        assert_eq!(evaluate("=not(TRUE, TRUE)"), Value::Error(Error::Value));
+
+        assert_eq!(evaluate("=xor(TRUE, FALSE)"), Value::Boolean(true));
+        assert_eq!(evaluate("=xor(TRUE, TRUE)"), Value::Boolean(false));
+        assert_eq!(evaluate("=xor(B1:B3, 1)"), Value::Boolean(false));
+        assert_eq!(evaluate("=xor(#REF!)"), Value::Error(Error::Reference));
+    }
+
+    #[test]
+    fn test_ifs() {
+        assert_eq!(evaluate("=ifs(FALSE, 1, TRUE, 2)"), number(2));
+        assert_eq!(evaluate("=ifs(TRUE, 1, 1/0, 2)"), number(1));
+        assert_eq!(
+            evaluate("=ifs(FALSE, 1)"),
+            Value::Error(Error::NotAvailable)
+        );
+        assert_eq!(evaluate("=ifs(TRUE)"), Value::Error(Error::Value));
+    }
+
+    #[test]
+    fn test_switch() {
+        assert_eq!(
+            evaluate("=switch(2, 1, \"a\", 2, \"b\")"),
+            Value::String("b".into())
+        );
+        assert_eq!(evaluate("=switch(B1, \"FOO\", 1, 2)"), number(1));
+        assert_eq!(evaluate("=switch(3, 1, \"a\", 2, \"b\", 0)"), number(0));
+        assert_eq!(
+            evaluate("=switch(3, 1, \"a\")"),
+            Value::Error(Error::NotAvailable)
+        );
+        assert_eq!(evaluate("=switch(1, 1, 2, 1/0)"), number(2));
+        assert_eq!(evaluate("=switch(1, 1)"), Value::Error(Error::Value));
+    }
+}
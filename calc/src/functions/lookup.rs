@@ -0,0 +1,686 @@
+//! Lookup and reference functions such as `vlookup`, `match` or `index`.
+//!
+//! Values are looked up as the `=` operator compares them, i.e. text case-insensitively,
+//! but only among values of the same type: `1` doesn't match `"1"`, and empty cells never match.
+//! Exact lookups of text also support the wildcards `?` and `*` (see [`search`](super::text::search)).
+//! Positions, like row and column numbers, start at 1.
+
+use std::cmp::Ordering;
+
+use bigdecimal::{BigDecimal, Zero};
+
+use crate::address::{CellAddress, CellReference};
+use crate::formula::expression::Expression;
+use crate::formula::function::Argument;
+use crate::formula::Context;
+use crate::parser::format_sheet_name;
+use crate::value::{Array, Error, Value};
+
+use super::math::integer;
+use super::result;
+use super::text::Pattern;
+
+/// How a lookup value is matched against the values searched
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    /// only equal values
+    Exact,
+    /// equal values, or for text, values matching it as a pattern with wildcards
+    Wildcard,
+    /// an equal value, or otherwise the largest smaller one
+    ExactOrSmaller,
+    /// an equal value, or otherwise the smallest larger one
+    ExactOrLarger,
+}
+
+/// A position argument, such as a row or column number, converted to an index starting at 0.
+/// `0` is only allowed if `zero` is set, in which case the result is `None`.
+fn index_of(value: &Value, zero: bool) -> Result<Option<usize>, Error> {
+    let position = integer(value.as_number()?).map_err(|_| Error::Value)?;
+    match usize::try_from(position) {
+        Ok(0) if zero => Ok(None),
+        Ok(position) if position > 0 => Ok(Some(position - 1)),
+        _ => Err(Error::Value),
+    }
+}
+
+/// A single value as a 1x1 array, so that it can be used like a range
+fn array(value: &Value) -> Result<Array, Error> {
+    match value {
+        Value::Array(array) => Ok(array.clone()),
+        Value::Error(error) => Err(*error),
+        value => Ok(Array::new(1, 1, vec![value.clone()])),
+    }
+}
+
+/// The values of a range with a single row or column; other ranges result in `#N/A`
+fn vector(array: &Array) -> Result<&[Value], Error> {
+    if array.rows() == 1 || array.cols() == 1 {
+        Ok(array.values())
+    } else {
+        Err(Error::NotAvailable)
+    }
+}
+
+/// A part of an array, which is a single value if it contains only one
+fn part(rows: u32, cols: u32, values: Vec<Value>) -> Value {
+    match <[Value; 1]>::try_from(values) {
+        Ok([value]) => value,
+        Err(values) => Value::Array(Array::new(rows, cols, values)),
+    }
+}
+
+/// The row of an array with the given index
+fn row_of(array: &Array, row: u32) -> Value {
+    let values = (0..array.cols()).map(|col| Value::from(array.get(row, col)));
+    part(1, array.cols(), values.collect())
+}
+
+/// The column of an array with the given index
+fn column_of(array: &Array, col: u32) -> Value {
+    let values = (0..array.rows()).map(|row| Value::from(array.get(row, col)));
+    part(array.rows(), 1, values.collect())
+}
+
+/// Whether values can be compared for a lookup, i.e. are numbers (including dates), text or booleans
+/// of the same type
+fn comparable(left: &Value, right: &Value) -> bool {
+    matches!(
+        (left, right),
+        (
+            Value::Number(_) | Value::DateTime(_),
+            Value::Number(_) | Value::DateTime(_)
+        ) | (Value::String(_), Value::String(_))
+            | (Value::Boolean(_), Value::Boolean(_))
+    )
+}
+
+/// Whether a value matches the lookup value in the given mode, ignoring `ExactOrSmaller` or `ExactOrLarger`
+fn equals(lookup: &Value, value: &Value, mode: Mode) -> bool {
+    match (lookup, value, mode) {
+        (Value::String(pattern), Value::String(text), Mode::Wildcard) => {
+            let text: Vec<char> = text.chars().collect();
+            Pattern::new(pattern).matches(&text)
+        }
+        _ => comparable(lookup, value) && lookup.compare(value) == Ok(Ordering::Equal),
+    }
+}
+
+/// Searches the values for the lookup value, from the first to the last value or the other way round.
+/// For `ExactOrSmaller` or `ExactOrLarger`, the values don't need to be sorted.
+fn position(lookup: &Value, values: &[Value], mode: Mode, reverse: bool) -> Option<usize> {
+    let indices: Box<dyn Iterator<Item = usize>> = if reverse {
+        Box::new((0..values.len()).rev())
+    } else {
+        Box::new(0..values.len())
+    };
+
+    let mut best: Option<usize> = None;
+    for index in indices {
+        let value = &values[index];
+        if equals(lookup, value, mode) {
+            return Some(index);
+        }
+        if !comparable(lookup, value) {
+            continue;
+        }
+
+        // the ordering that a value needs relative to the lookup value, and to the best value so far
+        let wanted = match mode {
+            Mode::Exact | Mode::Wildcard => continue,
+            Mode::ExactOrSmaller => Ordering::Less,
+            Mode::ExactOrLarger => Ordering::Greater,
+        };
+        let better = match best {
+            None => true,
+            Some(best) => values[best].compare(value) == Ok(wanted),
+        };
+        if value.compare(lookup) == Ok(wanted) && better {
+            best = Some(index);
+        }
+    }
+    best
+}
+
+/// Searches values sorted in ascending (or descending) order for the last value that is smaller (or larger)
+/// than or equal to the lookup value, stopping at the first value that is larger (or smaller).
+/// Values of other types than the lookup value are skipped.
+fn sorted_position(lookup: &Value, values: &[Value], descending: bool) -> Option<usize> {
+    let stop = if descending {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    };
+
+    let mut found = None;
+    for (index, value) in values.iter().enumerate() {
+        if !comparable(lookup, value) {
+            continue;
+        }
+        if value.compare(lookup) == Ok(stop) {
+            break;
+        }
+        found = Some(index);
+    }
+    found
+}
+
+/// `index(range, row, [column])`: the value at the given row and column of the range.
+/// For a range with a single row, the second argument is the column. If the row or column is `0`,
+/// the result is the whole column or row, which can be passed to other functions.
+pub fn index(arguments: &[Value]) -> Value {
+    let inner = || {
+        let (array, row, col) = match arguments {
+            [range, position] => {
+                let array = array(range)?;
+                let position = index_of(position, true)?;
+                if array.rows() == 1 {
+                    (array, Some(0), position)
+                } else {
+                    (array, position, Some(0))
+                }
+            }
+            [range, row, col] => (array(range)?, index_of(row, true)?, index_of(col, true)?),
+            _ => return Err(Error::Value),
+        };
+
+        let row = row.map(|row| u32::try_from(row).map_err(|_| Error::Reference));
+        let col = col.map(|col| u32::try_from(col).map_err(|_| Error::Reference));
+        match (row.transpose()?, col.transpose()?) {
+            (Some(row), _) if row >= array.rows() => Err(Error::Reference),
+            (_, Some(col)) if col >= array.cols() => Err(Error::Reference),
+            (Some(row), Some(col)) => Ok(Value::from(array.get(row, col))),
+            (Some(row), None) => Ok(row_of(&array, row)),
+            (None, Some(col)) => Ok(column_of(&array, col)),
+            (None, None) => Ok(Value::Array(array)),
+        }
+    };
+    inner().unwrap_or_else(Value::Error)
+}
+
+/// `match(value, range, [type])`: the position of the value in a range with a single row or column.
+/// With type `0`, the first equal value is found; with type `1` (the default), the range must be sorted
+/// in ascending order and the largest value smaller than or equal to the value is found;
+/// with type `-1`, the range must be sorted in descending order and the smallest value larger than
+/// or equal to the value is found. If there is none, the result is `#N/A`.
+pub fn r#match(arguments: &[Value]) -> Value {
+    let inner = || {
+        let (lookup, range, kind) = match arguments {
+            [lookup, range] => (lookup, range, BigDecimal::from(1)),
+            [lookup, range, kind] => (lookup, range, kind.as_number()?.clone()),
+            _ => return Err(Error::Value),
+        };
+        if let Value::Error(error) = lookup {
+            return Err(*error);
+        }
+        let array = array(range)?;
+        let values = vector(&array)?;
+
+        let found = match kind.cmp(&BigDecimal::zero()) {
+            Ordering::Equal => position(lookup, values, Mode::Wildcard, false),
+            Ordering::Greater => sorted_position(lookup, values, false),
+            Ordering::Less => sorted_position(lookup, values, true),
+        };
+        found
+            .map(|index| BigDecimal::from(index as u64 + 1))
+            .ok_or(Error::NotAvailable)
+    };
+    result(inner())
+}
+
+/// Shared implementation of `vlookup` and `hlookup`, which differ in whether the table is transposed.
+fn table_lookup(arguments: &[Value], vertical: bool) -> Value {
+    let inner = || {
+        let (lookup, table, index, approximate) = match arguments {
+            [lookup, table, index] => (lookup, table, index, true),
+            [lookup, table, index, approximate] => {
+                (lookup, table, index, approximate.as_boolean()?)
+            }
+            _ => return Err(Error::Value),
+        };
+        if let Value::Error(error) = lookup {
+            return Err(*error);
+        }
+        let table = array(table)?;
+        let index = index_of(index, false)?.expect("zero is not allowed");
+
+        // the first column (or row) is searched, the result is taken from the `index`th one
+        let (keys, results) = if vertical {
+            (column_of(&table, 0), table.cols())
+        } else {
+            (row_of(&table, 0), table.rows())
+        };
+        if index >= results as usize {
+            return Err(Error::Reference);
+        }
+        let keys = array(&keys)?;
+        let found = if approximate {
+            sorted_position(lookup, keys.values(), false)
+        } else {
+            position(lookup, keys.values(), Mode::Wildcard, false)
+        };
+
+        let found = found.ok_or(Error::NotAvailable)? as u32;
+        let result = if vertical {
+            table.get(found, index as u32)
+        } else {
+            table.get(index as u32, found)
+        };
+        Ok(Value::from(result))
+    };
+    inner().unwrap_or_else(Value::Error)
+}
+
+/// `vlookup(value, table, column, [approximate])`: searches the first column of the table for the value,
+/// and results in the value in the given column of the row where it was found. If `approximate` is true
+/// (the default), the first column must be sorted in ascending order and the largest value smaller than
+/// or equal to the value is found; otherwise only an equal value is found. If there is none,
+/// the result is `#N/A`.
+pub fn vlookup(arguments: &[Value]) -> Value {
+    table_lookup(arguments, true)
+}
+
+/// `hlookup(value, table, row, [approximate])`: like `vlookup`, but searches the first row of the table,
+/// and results in the value in the given row of the column where it was found.
+pub fn hlookup(arguments: &[Value]) -> Value {
+    table_lookup(arguments, false)
+}
+
+/// `xlookup(value, lookup_range, result_range, [if_not_found], [match_mode], [search_mode])`:
+/// searches a range with a single row or column for the value, and results in the corresponding value
+/// of the result range: the value at the same position, or the whole row or column of a larger range.
+///
+/// - `if_not_found` is the result if nothing was found; by default, it is `#N/A`
+/// - `match_mode` is `0` for exact matches (the default), `-1` for an exact match or the next smaller value,
+///   `1` for an exact match or the next larger value, and `2` for matching text with wildcards
+/// - `search_mode` is `1` to search from the first value (the default) and `-1` to search from the last one.
+///   `2` and `-2`, which are for ranges sorted in ascending or descending order, give the same results
+///   as searching from the first value.
+pub fn xlookup(arguments: &[Value]) -> Value {
+    let inner = || {
+        let (lookup, lookup_range, result_range, rest) = match arguments {
+            [lookup, lookup_range, result_range, rest @ ..] if rest.len() <= 3 => {
+                (lookup, lookup_range, result_range, rest)
+            }
+            _ => return Err(Error::Value),
+        };
+        if let Value::Error(error) = lookup {
+            return Err(*error);
+        }
+        let option = |index: usize| -> Result<Option<i64>, Error> {
+            match rest.get(index) {
+                None | Some(Value::Empty) => Ok(None),
+                Some(value) => integer(value.as_number()?).map(Some),
+            }
+        };
+        let mode = match option(1)?.unwrap_or(0) {
+            0 => Mode::Exact,
+            -1 => Mode::ExactOrSmaller,
+            1 => Mode::ExactOrLarger,
+            2 => Mode::Wildcard,
+            _ => return Err(Error::Value),
+        };
+        let reverse = match option(2)?.unwrap_or(1) {
+            1 | 2 | -2 => false,
+            -1 => true,
+            _ => return Err(Error::Value),
+        };
+
+        let lookup_array = array(lookup_range)?;
+        let result_array = array(result_range)?;
+        let values = vector(&lookup_array).map_err(|_| Error::Value)?;
+        let found = position(lookup, values, mode, reverse);
+
+        let Some(found) = found else {
+            return match rest.first() {
+                Some(if_not_found) => Ok(if_not_found.clone()),
+                None => Err(Error::NotAvailable),
+            };
+        };
+        let found = found as u32;
+        if lookup_array.cols() == 1 && result_array.rows() == lookup_array.rows() {
+            Ok(row_of(&result_array, found))
+        } else if lookup_array.rows() == 1 && result_array.cols() == lookup_array.cols() {
+            Ok(column_of(&result_array, found))
+        } else {
+            Err(Error::Value)
+        }
+    };
+    inner().unwrap_or_else(Value::Error)
+}
+
+/// `choose(index, value, ...)`: the value at the given position among the values.
+/// Only the chosen value is evaluated.
+pub fn choose(arguments: &[Argument], _context: &dyn Context) -> Value {
+    let inner = || {
+        let (index, choices) = arguments.split_first().ok_or(Error::Value)?;
+        let index = index_of(&index.evaluate(), false)?.expect("zero is not allowed");
+        let choice = choices.get(index).ok_or(Error::Value)?;
+        Ok(choice.evaluate())
+    };
+    inner().unwrap_or_else(Value::Error)
+}
+
+/// `rows(range)`: the number of rows of the range
+pub fn rows(arguments: &[Value]) -> Value {
+    match arguments {
+        [range] => array(range).map_or_else(Value::Error, |array| {
+            Value::Number(BigDecimal::from(array.rows()))
+        }),
+        _ => Value::Error(Error::Value),
+    }
+}
+
+/// `columns(range)`: the number of columns of the range
+pub fn columns(arguments: &[Value]) -> Value {
+    match arguments {
+        [range] => array(range).map_or_else(Value::Error, |array| {
+            Value::Number(BigDecimal::from(array.cols()))
+        }),
+        _ => Value::Error(Error::Value),
+    }
+}
+
+/// The cell a reference argument refers to, or the top left cell of a range.
+/// Without an argument, this is the cell containing the formula.
+fn referenced_cell(arguments: &[Argument], context: &dyn Context) -> Result<CellAddress, Error> {
+    match arguments {
+        [] => context.cell().ok_or(Error::Value),
+        [argument] => match argument.expression() {
+            Expression::Reference { reference, .. } => Ok(reference.address()),
+            Expression::Range { reference, .. } => Ok(reference.start().address()),
+            // e.g. a reference that became `#REF!`
+            _ => match argument.evaluate() {
+                Value::Error(error) => Err(error),
+                _ => Err(Error::Value),
+            },
+        },
+        _ => Err(Error::Value),
+    }
+}
+
+/// `row([reference])`: the row number of the referenced cell, or the top row of a range;
+/// without an argument, that of the cell containing the formula
+pub fn row(arguments: &[Argument], context: &dyn Context) -> Value {
+    referenced_cell(arguments, context).map_or_else(Value::Error, |address| {
+        Value::Number(BigDecimal::from(address.row().get()))
+    })
+}
+
+/// `column([reference])`: the column number of the referenced cell, or the left column of a range;
+/// without an argument, that of the cell containing the formula
+pub fn column(arguments: &[Argument], context: &dyn Context) -> Value {
+    referenced_cell(arguments, context).map_or_else(Value::Error, |address| {
+        Value::Number(BigDecimal::from(address.col().get()))
+    })
+}
+
+/// `address(row, column, [absolute], [a1], [sheet])`: a reference to the cell as text, such as `$B$3`.
+/// `absolute` is `1` for an absolute reference (the default), `2` for an absolute row, `3` for an absolute
+/// column and `4` for a relative reference. If `a1` is false, the reference is written in R1C1 notation,
+/// e.g. `R3C2` or `R[3]C[2]`. If a sheet name is given, it precedes the reference, as in `Sheet2!$B$3`.
+pub fn address(arguments: &[Value]) -> Value {
+    let inner = || {
+        let (row, col, rest) = match arguments {
+            [row, col, rest @ ..] if rest.len() <= 3 => (row, col, rest),
+            _ => return Err(Error::Value),
+        };
+        let row = index_of(row, false)?.expect("zero is not allowed") + 1;
+        let col = index_of(col, false)?.expect("zero is not allowed") + 1;
+        let (row_absolute, col_absolute) = match rest.first() {
+            None => (true, true),
+            Some(absolute) => match integer(absolute.as_number()?)? {
+                1 => (true, true),
+                2 => (true, false),
+                3 => (false, true),
+                4 => (false, false),
+                _ => return Err(Error::Value),
+            },
+        };
+        let a1 = match rest.get(1) {
+            None => true,
+            Some(a1) => a1.as_boolean()?,
+        };
+
+        let mut address = match rest.get(2) {
+            Some(sheet) => format!("{}!", format_sheet_name(&sheet.to_text()?)),
+            None => String::new(),
+        };
+        if a1 {
+            let row = u32::try_from(row).map_err(|_| Error::Value)?;
+            let col = u32::try_from(col).map_err(|_| Error::Value)?;
+            let cell = CellAddress::new(
+                row.try_into().map_err(|_| Error::Value)?,
+                col.try_into().map_err(|_| Error::Value)?,
+            );
+            let reference = CellReference::new(cell, row_absolute, col_absolute);
+            address.push_str(&reference.to_string());
+        } else {
+            let part = |absolute: bool, number: usize| {
+                if absolute {
+                    number.to_string()
+                } else {
+                    format!("[{}]", number)
+                }
+            };
+            address.push_str(&format!(
+                "R{}C{}",
+                part(row_absolute, row),
+                part(col_absolute, col)
+            ));
+        }
+        Ok(address)
+    };
+    inner().map_or_else(Value::Error, Value::String)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::functions::testing::{error, number, string};
+    use crate::sheet::Sheet;
+
+    /// A sheet with a table of fruits in A1:C4, sorted by their names:
+    ///
+    /// | apple  | 1 | red    |
+    /// | banana | 2 | yellow |
+    /// | cherry | 3 | red    |
+    /// | kiwi   | 5 | green  |
+    fn sheet() -> Sheet {
+        let mut sheet = Sheet::with_standard_library();
+        let table = [
+            ["\"apple\"", "1", "\"red\""],
+            ["\"banana\"", "2", "\"yellow\""],
+            ["\"cherry\"", "3", "\"red\""],
+            ["\"kiwi\"", "5", "\"green\""],
+        ];
+        for (row, cells) in table.iter().enumerate() {
+            for (col, input) in cells.iter().enumerate() {
+                let address = CellAddress::new(
+                    (row as u32 + 1).try_into().unwrap(),
+                    (col as u32 + 1).try_into().unwrap(),
+                );
+                sheet.set_cell(address, input.to_string()).unwrap();
+            }
+        }
+        sheet
+    }
+
+    fn check(cases: &[(&str, Value)]) {
+        let mut sheet = sheet();
+        let address = "E5".parse().unwrap();
+        for (formula, expected) in cases {
+            sheet.set_cell(address, formula.to_string()).unwrap();
+            assert_eq!(
+                &Value::from(sheet.value(&address)),
+                expected,
+                "formula {}",
+                formula
+            );
+        }
+    }
+
+    #[test]
+    fn test_index() {
+        check(&[
+            ("=index(A1:C4, 2, 3)", string("yellow")),
+            ("=index(A1:A4, 3)", string("cherry")),
+            ("=index(A1:C1, 2)", number("1")),
+            ("=index(A1:C4, 4, 2)", number("5")),
+            ("=sum(index(A1:C4, 0, 2))", number("11")),
+            ("=counta(index(A1:C4, 2, 0))", number("3")),
+            ("=index(A1:C4, 5, 1)", error(Error::Reference)),
+            ("=index(A1:C4, 1, 4)", error(Error::Reference)),
+            ("=index(A1:C4, -1, 1)", error(Error::Value)),
+            ("=index(7, 1, 1)", number("7")),
+        ]);
+    }
+
+    #[test]
+    fn test_match() {
+        check(&[
+            ("=match(\"cherry\", A1:A4, 0)", number("3")),
+            ("=match(\"CHERRY\", A1:A4, 0)", number("3")),
+            ("=match(\"b*\", A1:A4, 0)", number("2")),
+            ("=match(\"?iwi\", A1:A4, 0)", number("4")),
+            ("=match(\"grape\", A1:A4, 0)", error(Error::NotAvailable)),
+            ("=match(3, B1:B4, 0)", number("3")),
+            ("=match(\"3\", B1:B4, 0)", error(Error::NotAvailable)),
+            ("=match(4, B1:B4)", number("3")),
+            ("=match(5, B1:B4, 1)", number("4")),
+            ("=match(100, B1:B4)", number("4")),
+            ("=match(0, B1:B4)", error(Error::NotAvailable)),
+            ("=match(\"c\", A1:A4)", number("2")),
+            ("=match(2, B1:B4, -1)", error(Error::NotAvailable)),
+            ("=match(1, A1:C4, 0)", error(Error::NotAvailable)),
+            ("=match(#REF!, B1:B4, 0)", error(Error::Reference)),
+        ]);
+    }
+
+    #[test]
+    fn test_vlookup_hlookup() {
+        check(&[
+            ("=vlookup(\"banana\", A1:C4, 3, FALSE)", string("yellow")),
+            ("=vlookup(\"K*\", A1:C4, 2, FALSE)", number("5")),
+            (
+                "=vlookup(\"grape\", A1:C4, 2, FALSE)",
+                error(Error::NotAvailable),
+            ),
+            // approximately, grape would be between cherry and kiwi
+            ("=vlookup(\"grape\", A1:C4, 2)", number("3")),
+            (
+                "=vlookup(\"aardvark\", A1:C4, 2)",
+                error(Error::NotAvailable),
+            ),
+            ("=vlookup(4, B1:C4, 2, TRUE)", string("red")),
+            (
+                "=vlookup(\"apple\", A1:C4, 4, FALSE)",
+                error(Error::Reference),
+            ),
+            ("=vlookup(\"apple\", A1:C4, 0, FALSE)", error(Error::Value)),
+            ("=hlookup(\"apple\", A1:C4, 3, FALSE)", string("cherry")),
+            ("=hlookup(1, B1:C4, 4, FALSE)", number("5")),
+            ("=hlookup(\"red\", A1:C4, 2, FALSE)", string("yellow")),
+            (
+                "=hlookup(\"green\", A1:C4, 2, FALSE)",
+                error(Error::NotAvailable),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_xlookup() {
+        check(&[
+            ("=xlookup(\"cherry\", A1:A4, C1:C4)", string("red")),
+            (
+                "=xlookup(\"grape\", A1:A4, C1:C4)",
+                error(Error::NotAvailable),
+            ),
+            (
+                "=xlookup(\"grape\", A1:A4, C1:C4, \"none\")",
+                string("none"),
+            ),
+            ("=xlookup(\"red\", C1:C4, A1:A4)", string("apple")),
+            (
+                "=xlookup(\"red\", C1:C4, A1:A4, #N/A, 0, -1)",
+                string("cherry"),
+            ),
+            ("=xlookup(4, B1:B4, A1:A4, #N/A, -1)", string("cherry")),
+            ("=xlookup(4, B1:B4, A1:A4, #N/A, 1)", string("kiwi")),
+            (
+                "=xlookup(6, B1:B4, A1:A4, #N/A, 1)",
+                error(Error::NotAvailable),
+            ),
+            ("=xlookup(\"*w*\", A1:A4, B1:B4, #N/A, 2)", number("5")),
+            (
+                "=xlookup(\"*w*\", A1:A4, B1:B4)",
+                error(Error::NotAvailable),
+            ),
+            ("=xlookup(2, B1:B4, A1:A4, #N/A, 0, 2)", string("banana")),
+            ("=counta(xlookup(\"kiwi\", A1:A4, A1:C4))", number("3")),
+            ("=xlookup(\"apple\", A1:C1, A2:C2)", string("banana")),
+            ("=xlookup(\"apple\", A1:A4, A1:A3)", error(Error::Value)),
+            (
+                "=xlookup(\"apple\", A1:A4, B1:B4, #N/A, 3)",
+                error(Error::Value),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_choose() {
+        check(&[
+            ("=choose(2, \"a\", \"b\", \"c\")", string("b")),
+            ("=choose(3.9, \"a\", \"b\", \"c\")", string("c")),
+            // only the chosen argument is evaluated
+            ("=choose(1, \"a\", 1/0)", string("a")),
+            ("=choose(4, \"a\", \"b\", \"c\")", error(Error::Value)),
+            ("=choose(0, \"a\")", error(Error::Value)),
+            ("=sum(choose(2, A1, B1:B4))", number("11")),
+        ]);
+    }
+
+    #[test]
+    fn test_rows_columns() {
+        check(&[
+            ("=rows(A1:C4)", number("4")),
+            ("=columns(A1:C4)", number("3")),
+            ("=rows(A1)", number("1")),
+            ("=columns(5)", number("1")),
+            ("=rows(#N/A)", error(Error::NotAvailable)),
+        ]);
+    }
+
+    #[test]
+    fn test_row_column() {
+        check(&[
+            ("=row()", number("5")),
+            ("=column()", number("5")),
+            ("=row(B3)", number("3")),
+            ("=column(B3)", number("2")),
+            ("=row(Sheet2!C7:D9)", number("7")),
+            ("=column(C7:D9)", number("3")),
+            ("=row(#REF!)", error(Error::Reference)),
+            ("=row(1)", error(Error::Value)),
+        ]);
+    }
+
+    #[test]
+    fn test_address() {
+        check(&[
+            ("=address(3, 2)", string("$B$3")),
+            ("=address(3, 2, 2)", string("B$3")),
+            ("=address(3, 2, 3)", string("$B3")),
+            ("=address(3, 28, 4)", string("AB3")),
+            ("=address(3, 2, 1, FALSE)", string("R3C2")),
+            ("=address(3, 2, 4, FALSE)", string("R[3]C[2]")),
+            (
+                "=address(3, 2, 1, TRUE, \"My Sheet\")",
+                string("'My Sheet'!$B$3"),
+            ),
+            ("=address(0, 2)", error(Error::Value)),
+            ("=address(1, 2, 5)", error(Error::Value)),
+        ]);
+    }
+}
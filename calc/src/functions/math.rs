@@ -0,0 +1,973 @@
+//! Mathematical functions such as `sum`, `round` or `sin`.
+//!
+//! Functions whose results can be represented exactly, such as `sum`, `round` or `mod`, calculate them exactly.
+//! Other results, such as `sqrt(2)` or `sin(1)`, are calculated with a few more digits than necessary,
+//! and then rounded to [`PRECISION`] significant digits.
+//! Arguments outside of a function's domain, such as `sqrt(-1)`, result in a `#NUM!` error.
+
+use std::cmp::Ordering;
+
+use bigdecimal::{BigDecimal, One, Signed, ToPrimitive, Zero};
+use num_bigint::{BigInt, Sign};
+
+use crate::formula::operator;
+use crate::value::{Error, Value};
+
+use super::{numbers, result};
+
+/// The number of significant digits that results are rounded to if they can't be calculated exactly
+pub const PRECISION: u64 = 15;
+
+/// The number of significant digits of intermediate results, so that rounding errors don't affect the result
+const WORKING_PRECISION: u64 = PRECISION + 10;
+
+/// Integer exponents up to this (2³⁰) are calculated by squaring; as each squaring doubles the rounding error,
+/// larger ones are calculated using logarithms.
+const MAX_SQUARED_EXPONENT: u64 = 1 << 30;
+
+/// The largest number of decimal places that numbers can be rounded to
+const MAX_DIGITS: i64 = 1000;
+
+/// π to more digits than [`WORKING_PRECISION`]
+const PI: &str = "3.14159265358979323846264338327950288419716939937510";
+
+/// Arguments of `exp` larger than this result in `#NUM!`, as the result would be unreasonably large
+pub(crate) const MAX_EXP: i64 = 709;
+
+/// Arguments of trigonometric functions larger than this (2²⁷) result in `#NUM!`,
+/// as too few digits of the result would be accurate
+const MAX_TRIGONOMETRIC: i64 = 134_217_728;
+
+fn pi_value() -> BigDecimal {
+    PI.parse().expect("π is a valid number")
+}
+
+/// Calls a function of a single number.
+fn unary<F>(arguments: &[Value], function: F) -> Value
+where
+    F: FnOnce(&BigDecimal) -> Result<BigDecimal, Error>,
+{
+    match arguments {
+        [number] => result(number.as_number().and_then(function)),
+        _ => Value::Error(Error::Value),
+    }
+}
+
+/// Calls a function of a number and an optional second number, which defaults to `default`.
+fn binary<F>(arguments: &[Value], default: Option<BigDecimal>, function: F) -> Value
+where
+    F: FnOnce(&BigDecimal, &BigDecimal) -> Result<BigDecimal, Error>,
+{
+    let inner = || match (arguments, default) {
+        ([first, second], _) => function(first.as_number()?, second.as_number()?),
+        ([first], Some(default)) => function(first.as_number()?, &default),
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// Rounds a result that can't be calculated exactly to [`PRECISION`] significant digits.
+pub(crate) fn inexact(number: BigDecimal) -> BigDecimal {
+    // `with_prec` only rounds positive numbers, and truncates negative ones
+    let rounded = number.abs().with_prec(PRECISION).normalized();
+    if number.is_negative() {
+        -rounded
+    } else {
+        rounded
+    }
+}
+
+/// Truncates a number of intermediate calculations to [`WORKING_PRECISION`] significant digits.
+pub(crate) fn working(number: BigDecimal) -> BigDecimal {
+    if number.digits() > WORKING_PRECISION {
+        number.with_prec(WORKING_PRECISION)
+    } else {
+        number
+    }
+}
+
+/// The smallest term of a series that still affects the result at [`WORKING_PRECISION`],
+/// for results that are roughly between 0.1 and 10
+fn epsilon() -> BigDecimal {
+    BigDecimal::new(BigInt::one(), WORKING_PRECISION as i64 + 2)
+}
+
+/// The integer part of a number as an `i64`, as used for numbers of digits
+pub(crate) fn integer(number: &BigDecimal) -> Result<i64, Error> {
+    number.with_scale(0).to_i64().ok_or(Error::Number)
+}
+
+/// The integer part of a number as a `BigInt`
+fn big_integer(number: &BigDecimal) -> BigInt {
+    number.with_scale(0).as_bigint_and_exponent().0
+}
+
+/// `⌊a / b⌋`, calculated exactly
+fn div_floor(a: &BigDecimal, b: &BigDecimal) -> BigInt {
+    let scale = a
+        .as_bigint_and_exponent()
+        .1
+        .max(b.as_bigint_and_exponent().1);
+    let a = a.with_scale(scale).as_bigint_and_exponent().0;
+    let b = b.with_scale(scale).as_bigint_and_exponent().0;
+
+    let quotient = &a / &b;
+    if (&quotient * &b != a) && (a.is_negative() != b.is_negative()) {
+        quotient - 1
+    } else {
+        quotient
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Rounding {
+    /// to the nearest number, and away from zero if the number is exactly in between
+    HalfUp,
+    /// away from zero
+    Up,
+    /// towards zero
+    Down,
+}
+
+/// Rounds a number to the given number of decimal places; negative numbers round to tens, hundreds etc.
+pub(crate) fn round_to(number: &BigDecimal, digits: i64, rounding: Rounding) -> BigDecimal {
+    let digits = digits.clamp(-MAX_DIGITS, MAX_DIGITS);
+    // `with_scale` truncates towards zero
+    let truncated = number.with_scale(digits);
+    let remainder = (number - &truncated).abs();
+    let unit = BigDecimal::new(BigInt::one(), digits);
+
+    let away = match rounding {
+        Rounding::HalfUp => remainder.double() >= unit,
+        Rounding::Up => !remainder.is_zero(),
+        Rounding::Down => false,
+    };
+    let rounded = match (away, number.is_negative()) {
+        (false, _) => truncated,
+        (true, false) => truncated + unit,
+        (true, true) => truncated - unit,
+    };
+    rounded.normalized()
+}
+
+fn round_with(arguments: &[Value], rounding: Rounding) -> Value {
+    binary(arguments, Some(BigDecimal::zero()), |number, digits| {
+        Ok(round_to(number, integer(digits)?, rounding))
+    })
+}
+
+/// `sum(number, ...)`: the sum of all numbers; in ranges, values other than numbers are ignored
+pub fn sum(arguments: &[Value]) -> Value {
+    result(numbers(arguments).map(|numbers| {
+        numbers
+            .into_iter()
+            .fold(BigDecimal::zero(), |sum, number| sum + number)
+    }))
+}
+
+/// `product(number, ...)`: the product of all numbers, or `0` if there are none;
+/// in ranges, values other than numbers are ignored
+pub fn product(arguments: &[Value]) -> Value {
+    result(numbers(arguments).map(|numbers| {
+        if numbers.is_empty() {
+            BigDecimal::zero()
+        } else {
+            numbers
+                .into_iter()
+                .fold(BigDecimal::one(), |product, number| product * number)
+        }
+    }))
+}
+
+/// `abs(number)`: the absolute value
+pub fn abs(arguments: &[Value]) -> Value {
+    unary(arguments, |number| Ok(number.abs()))
+}
+
+/// `sign(number)`: `-1`, `0` or `1`, depending on the sign of the number
+pub fn sign(arguments: &[Value]) -> Value {
+    unary(arguments, |number| Ok(number.signum()))
+}
+
+/// `round(number, [digits])`: the number rounded to the given number of decimal places (default `0`);
+/// halfway cases are rounded away from zero
+pub fn round(arguments: &[Value]) -> Value {
+    round_with(arguments, Rounding::HalfUp)
+}
+
+/// `roundup(number, [digits])`: the number rounded away from zero to the given number of decimal places
+pub fn roundup(arguments: &[Value]) -> Value {
+    round_with(arguments, Rounding::Up)
+}
+
+/// `rounddown(number, [digits])`: the number rounded towards zero to the given number of decimal places
+pub fn rounddown(arguments: &[Value]) -> Value {
+    round_with(arguments, Rounding::Down)
+}
+
+/// `trunc(number, [digits])`: the number truncated to the given number of decimal places,
+/// which is the same as `rounddown`
+pub fn trunc(arguments: &[Value]) -> Value {
+    round_with(arguments, Rounding::Down)
+}
+
+/// `int(number)`: the number rounded down to the next integer, e.g. `-2` for `-1.5`
+pub fn int(arguments: &[Value]) -> Value {
+    unary(arguments, |number| {
+        Ok(div_floor(number, &BigDecimal::one()).into())
+    })
+}
+
+/// `ceiling(number, [significance])`: the number rounded up to a multiple of the significance (default `1`).
+/// A positive number can't be rounded to a negative significance.
+pub fn ceiling(arguments: &[Value]) -> Value {
+    binary(
+        arguments,
+        Some(BigDecimal::one()),
+        |number, significance| {
+            if significance.is_zero() {
+                return Ok(BigDecimal::zero());
+            }
+            if number.is_positive() && significance.is_negative() {
+                return Err(Error::Number);
+            }
+            let multiple = -div_floor(&-number, significance);
+            Ok(BigDecimal::from(multiple) * significance)
+        },
+    )
+}
+
+/// `floor(number, [significance])`: the number rounded down to a multiple of the significance (default `1`).
+/// A positive number can't be rounded to a negative significance.
+pub fn floor(arguments: &[Value]) -> Value {
+    binary(
+        arguments,
+        Some(BigDecimal::one()),
+        |number, significance| {
+            if significance.is_zero() {
+                return Err(Error::DivisionByZero);
+            }
+            if number.is_positive() && significance.is_negative() {
+                return Err(Error::Number);
+            }
+            Ok(BigDecimal::from(div_floor(number, significance)) * significance)
+        },
+    )
+}
+
+/// `mod(number, divisor)`: the remainder of dividing the number by the divisor,
+/// which has the same sign as the divisor
+pub fn r#mod(arguments: &[Value]) -> Value {
+    binary(arguments, None, |number, divisor| {
+        if divisor.is_zero() {
+            return Err(Error::DivisionByZero);
+        }
+        Ok(number - BigDecimal::from(div_floor(number, divisor)) * divisor)
+    })
+}
+
+/// `power(base, exponent)`: the same as `base ^ exponent`
+pub fn power(arguments: &[Value]) -> Value {
+    binary(arguments, None, operator::power)
+}
+
+/// The integers of the arguments of `gcd` and `lcm`, which must not be negative
+fn integers(arguments: &[Value]) -> Result<Vec<BigInt>, Error> {
+    numbers(arguments)?
+        .iter()
+        .map(|number| match number.is_negative() {
+            true => Err(Error::Number),
+            false => Ok(big_integer(number)),
+        })
+        .collect()
+}
+
+fn gcd_of(mut a: BigInt, mut b: BigInt) -> BigInt {
+    while !b.is_zero() {
+        let remainder = &a % &b;
+        a = b;
+        b = remainder;
+    }
+    a
+}
+
+/// `gcd(number, ...)`: the greatest common divisor of the integer parts of the numbers
+pub fn gcd(arguments: &[Value]) -> Value {
+    let inner = || {
+        let integers = integers(arguments)?;
+        if integers.is_empty() {
+            return Err(Error::Value);
+        }
+        Ok(integers.into_iter().fold(BigInt::zero(), gcd_of).into())
+    };
+    result(inner())
+}
+
+/// `lcm(number, ...)`: the least common multiple of the integer parts of the numbers
+pub fn lcm(arguments: &[Value]) -> Value {
+    let inner = || {
+        let integers = integers(arguments)?;
+        if integers.is_empty() {
+            return Err(Error::Value);
+        }
+        let lcm = integers.into_iter().fold(BigInt::one(), |a, b| {
+            if a.is_zero() || b.is_zero() {
+                return BigInt::zero();
+            }
+            let gcd = gcd_of(a.clone(), b.clone());
+            a / gcd * b
+        });
+        Ok(lcm.into())
+    };
+    result(inner())
+}
+
+/// `e^x`, calculated at [`WORKING_PRECISION`]; `|x|` must not be larger than [`MAX_EXP`]
+pub(crate) fn exp_of(x: &BigDecimal) -> BigDecimal {
+    // e^x = (e^(x / 2^k))^(2^k), where |x / 2^k| < 1 so that the series converges quickly
+    let mut reduced = x.clone();
+    let mut squarings = 0;
+    while reduced.abs() >= BigDecimal::one() {
+        reduced = reduced.half();
+        squarings += 1;
+    }
+
+    // e^x = 1 + x + x^2/2! + x^3/3! + ...
+    let epsilon = epsilon();
+    let mut term = BigDecimal::one();
+    let mut sum = BigDecimal::one();
+    for n in 1u32.. {
+        term = working(term * &reduced / BigDecimal::from(n));
+        if term.abs() < epsilon {
+            break;
+        }
+        sum += &term;
+    }
+
+    for _ in 0..squarings {
+        sum = working(sum.square());
+    }
+    sum
+}
+
+/// `ln(x)` for positive `x`, calculated at [`WORKING_PRECISION`]
+pub(crate) fn ln_of(x: &BigDecimal) -> BigDecimal {
+    /// ln(x) = 2 * atanh((x - 1) / (x + 1)) = 2 * (y + y^3/3 + y^5/5 + ...), for x between 0.5 and 2
+    fn series(x: &BigDecimal) -> BigDecimal {
+        let y = working((x - BigDecimal::one()) / (x + BigDecimal::one()));
+        let y_squared = working(y.square());
+        let epsilon = epsilon();
+
+        let mut power = y;
+        let mut sum = BigDecimal::zero();
+        for n in (1u32..).step_by(2) {
+            let term = working(&power / BigDecimal::from(n));
+            if term.abs() < epsilon {
+                break;
+            }
+            sum += term;
+            power = working(power * &y_squared);
+        }
+        sum.double()
+    }
+
+    // ln(x) = ln(m) + k * ln(10), where x = m * 10^k and m is between 0.1 and 1;
+    // this is only done for x far from 1, where it doesn't cancel out most of the result's digits
+    let ten = BigDecimal::from(10);
+    let mut reduced = x.clone();
+    let mut exponent = 0i64;
+    if x > &ten || x * &ten < BigDecimal::one() {
+        let (mantissa, scale) = x.as_bigint_and_exponent();
+        let digits = x.digits() as i64;
+        reduced = working(BigDecimal::new(mantissa, digits));
+        exponent = digits - scale;
+    }
+
+    // ln(m) = ln(m / 2^j) + j * ln(2), where m / 2^j is between 0.5 and 2
+    let two = BigDecimal::from(2);
+    let half = BigDecimal::one().half();
+    let mut halvings = 0i64;
+    while reduced > two {
+        reduced = working(reduced.half());
+        halvings += 1;
+    }
+    while reduced < half {
+        reduced = working(reduced.double());
+        halvings -= 1;
+    }
+
+    let ln_2 = series(&two);
+    let mut result = series(&reduced);
+    if halvings != 0 {
+        result += &ln_2 * BigDecimal::from(halvings);
+    }
+    if exponent != 0 {
+        // ln(10) = 3 * ln(2) + ln(1.25)
+        let ln_10 = &ln_2 * BigDecimal::from(3) + series(&BigDecimal::new(125.into(), 2));
+        result += ln_10 * BigDecimal::from(exponent);
+    }
+    result
+}
+
+/// `base ^ exponent` at working precision; non-integer exponents require a positive base.
+/// Powers that are too large result in `#NUM!`, those that are too small in `0`, and `0^0` is undefined.
+pub(crate) fn power_of(base: &BigDecimal, exponent: &BigDecimal) -> Result<BigDecimal, Error> {
+    let integer = exponent
+        .is_integer()
+        .then(|| exponent.abs().to_u64())
+        .flatten();
+
+    if base.is_zero() {
+        return match exponent.sign() {
+            Sign::Minus => Err(Error::DivisionByZero),
+            // 0^0 is undefined
+            Sign::NoSign => Err(Error::Number),
+            Sign::Plus => Ok(BigDecimal::zero()),
+        };
+    }
+    // only integer powers of negative numbers are real numbers
+    let negative = match integer {
+        _ if base.is_positive() => false,
+        Some(exponent) => !exponent.is_multiple_of(2),
+        None => return Err(Error::Number),
+    };
+
+    let logarithm = working(ln_of(&base.abs()) * exponent);
+    if logarithm > BigDecimal::from(MAX_EXP) {
+        return Err(Error::Number);
+    }
+    if logarithm < BigDecimal::from(-MAX_EXP) {
+        return Ok(BigDecimal::zero());
+    }
+
+    if let Some(mut remaining) = integer.filter(|&exponent| exponent <= MAX_SQUARED_EXPONENT) {
+        // exponentiation by squaring
+        let mut factor = base.clone();
+        let mut result = BigDecimal::one();
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                result = working(result * &factor);
+            }
+            factor = working(factor.square());
+            remaining >>= 1;
+        }
+        return Ok(if exponent.is_negative() {
+            working(BigDecimal::one() / result)
+        } else {
+            result
+        });
+    }
+
+    let magnitude = exp_of(&logarithm);
+    Ok(if negative { -magnitude } else { magnitude })
+}
+
+/// `sin(x)`, calculated at [`WORKING_PRECISION`]
+fn sin_of(x: &BigDecimal) -> Result<BigDecimal, Error> {
+    if x.abs() >= BigDecimal::from(MAX_TRIGONOMETRIC) {
+        return Err(Error::Number);
+    }
+
+    // reduce x to between -π and π
+    let pi = pi_value();
+    let two_pi = pi.double();
+    let turns = div_floor(&(x + &pi), &two_pi);
+    let reduced = x - BigDecimal::from(turns) * two_pi;
+
+    // sin(x) = x - x^3/3! + x^5/5! - ...
+    let epsilon = epsilon();
+    let x_squared = working(reduced.square());
+    let mut term = reduced;
+    let mut sum = BigDecimal::zero();
+    for n in (1u32..).step_by(2) {
+        if term.abs() < epsilon {
+            break;
+        }
+        sum += &term;
+        term = working(-term * &x_squared / BigDecimal::from((n + 1) * (n + 2)));
+    }
+    Ok(sum)
+}
+
+fn cos_of(x: &BigDecimal) -> Result<BigDecimal, Error> {
+    sin_of(&(x + pi_value().half()))
+}
+
+/// `atan(x)`, calculated at [`WORKING_PRECISION`]
+fn atan_of(x: &BigDecimal) -> BigDecimal {
+    // atan(x) = 2 * atan(x / (1 + sqrt(1 + x^2))), until x is small enough for the series to converge quickly
+    let limit = BigDecimal::new(BigInt::one(), 1);
+    let mut reduced = x.clone();
+    let mut doublings = 0;
+    while reduced.abs() > limit {
+        let root = (BigDecimal::one() + reduced.square())
+            .sqrt()
+            .expect("1 + x^2 is positive");
+        reduced = working(reduced / (BigDecimal::one() + working(root)));
+        doublings += 1;
+    }
+
+    // atan(x) = x - x^3/3 + x^5/5 - ...
+    let epsilon = epsilon();
+    let x_squared = working(reduced.square());
+    let mut power = reduced;
+    let mut sum = BigDecimal::zero();
+    for n in (1u32..).step_by(2) {
+        let term = working(&power / BigDecimal::from(n));
+        if term.abs() < epsilon {
+            break;
+        }
+        sum += term;
+        power = working(-power * &x_squared);
+    }
+
+    for _ in 0..doublings {
+        sum = sum.double();
+    }
+    sum
+}
+
+/// `asin(x)` for `x` between -1 and 1, calculated at [`WORKING_PRECISION`]
+fn asin_of(x: &BigDecimal) -> Result<BigDecimal, Error> {
+    match x.abs().cmp(&BigDecimal::one()) {
+        Ordering::Greater => Err(Error::Number),
+        Ordering::Equal => Ok(pi_value().half() * x.signum()),
+        // asin(x) = atan(x / sqrt(1 - x^2))
+        Ordering::Less => {
+            let root = (BigDecimal::one() - x.square())
+                .sqrt()
+                .expect("1 - x^2 is positive");
+            Ok(atan_of(&working(x / root)))
+        }
+    }
+}
+
+/// `sqrt(number)`: the square root of a number that is not negative
+pub fn sqrt(arguments: &[Value]) -> Value {
+    unary(arguments, |number| {
+        number.sqrt().map(inexact).ok_or(Error::Number)
+    })
+}
+
+/// `exp(number)`: e raised to the power of the number
+pub fn exp(arguments: &[Value]) -> Value {
+    unary(arguments, |number| {
+        if number > &BigDecimal::from(MAX_EXP) {
+            return Err(Error::Number);
+        }
+        // the result would be too small to be represented
+        if number < &BigDecimal::from(-MAX_EXP) {
+            return Ok(BigDecimal::zero());
+        }
+        Ok(inexact(exp_of(number)))
+    })
+}
+
+/// `ln(number)`: the natural logarithm of a positive number
+pub fn ln(arguments: &[Value]) -> Value {
+    unary(arguments, |number| {
+        if !number.is_positive() {
+            return Err(Error::Number);
+        }
+        Ok(inexact(ln_of(number)))
+    })
+}
+
+/// `log(number, [base])`: the logarithm of a positive number to the given base (default `10`)
+pub fn log(arguments: &[Value]) -> Value {
+    binary(arguments, Some(BigDecimal::from(10)), |number, base| {
+        if !number.is_positive() || !base.is_positive() {
+            return Err(Error::Number);
+        }
+        if base.is_one() {
+            return Err(Error::DivisionByZero);
+        }
+        Ok(inexact(ln_of(number) / ln_of(base)))
+    })
+}
+
+/// `log10(number)`: the logarithm of a positive number to base 10
+pub fn log10(arguments: &[Value]) -> Value {
+    match arguments {
+        [number] => log(&[number.clone(), Value::Number(10.into())]),
+        _ => Value::Error(Error::Value),
+    }
+}
+
+/// `pi()`: the number π
+pub fn pi(arguments: &[Value]) -> Value {
+    match arguments {
+        [] => Value::Number(inexact(pi_value())),
+        _ => Value::Error(Error::Value),
+    }
+}
+
+/// `sin(angle)`: the sine of an angle in radians
+pub fn sin(arguments: &[Value]) -> Value {
+    unary(arguments, |number| sin_of(number).map(inexact))
+}
+
+/// `cos(angle)`: the cosine of an angle in radians
+pub fn cos(arguments: &[Value]) -> Value {
+    unary(arguments, |number| cos_of(number).map(inexact))
+}
+
+/// `tan(angle)`: the tangent of an angle in radians
+pub fn tan(arguments: &[Value]) -> Value {
+    unary(arguments, |number| {
+        let cos = cos_of(number)?;
+        if cos.is_zero() {
+            return Err(Error::DivisionByZero);
+        }
+        Ok(inexact(sin_of(number)? / cos))
+    })
+}
+
+/// `asin(number)`: the angle in radians whose sine is the number, which must be between -1 and 1
+pub fn asin(arguments: &[Value]) -> Value {
+    unary(arguments, |number| asin_of(number).map(inexact))
+}
+
+/// `acos(number)`: the angle in radians whose cosine is the number, which must be between -1 and 1
+pub fn acos(arguments: &[Value]) -> Value {
+    unary(arguments, |number| {
+        Ok(inexact(pi_value().half() - asin_of(number)?))
+    })
+}
+
+/// `atan(number)`: the angle in radians whose tangent is the number
+pub fn atan(arguments: &[Value]) -> Value {
+    unary(arguments, |number| Ok(inexact(atan_of(number))))
+}
+
+/// `atan2(x, y)`: the angle in radians between the x axis and the point (x, y), between -π and π
+pub fn atan2(arguments: &[Value]) -> Value {
+    binary(arguments, None, |x, y| {
+        let pi = pi_value();
+        let angle = match (x.sign(), y.is_negative()) {
+            (num_bigint::Sign::NoSign, _) if y.is_zero() => return Err(Error::DivisionByZero),
+            (num_bigint::Sign::NoSign, negative) => {
+                let angle = pi.half();
+                if negative {
+                    -angle
+                } else {
+                    angle
+                }
+            }
+            (num_bigint::Sign::Plus, _) => atan_of(&working(y / x)),
+            (num_bigint::Sign::Minus, false) => atan_of(&working(y / x)) + pi,
+            (num_bigint::Sign::Minus, true) => atan_of(&working(y / x)) - pi,
+        };
+        Ok(inexact(angle))
+    })
+}
+
+/// `degrees(angle)`: an angle in radians converted to degrees
+pub fn degrees(arguments: &[Value]) -> Value {
+    unary(arguments, |number| {
+        Ok(inexact(number * BigDecimal::from(180) / pi_value()))
+    })
+}
+
+/// `radians(angle)`: an angle in degrees converted to radians
+pub fn radians(arguments: &[Value]) -> Value {
+    unary(arguments, |number| {
+        Ok(inexact(number * pi_value() / BigDecimal::from(180)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::value::Array;
+
+    use crate::functions::testing::{error, number};
+
+    /// the numbers as the arguments of a function
+    fn values_of(values: &[&str]) -> Vec<Value> {
+        values.iter().map(|value| number(value)).collect()
+    }
+
+    /// asserts that the function returns the expected result for each list of arguments
+    fn check(function: fn(&[Value]) -> Value, cases: &[(&[&str], Value)]) {
+        for (arguments, expected) in cases {
+            assert_eq!(
+                &function(&values_of(arguments)),
+                expected,
+                "arguments {:?}",
+                arguments
+            );
+        }
+    }
+
+    #[test]
+    fn test_sum_product() {
+        let range = Value::Array(Array::new(
+            1,
+            3,
+            vec![number("2"), Value::String("foo".into()), Value::Empty],
+        ));
+
+        assert_eq!(sum(&[number("1"), number("2.5")]), number("3.5"));
+        assert_eq!(sum(&[range.clone(), number("1")]), number("3"));
+        assert_eq!(sum(&[]), number("0"));
+        assert_eq!(sum(&[Value::String("foo".into())]), error(Error::Value));
+        assert_eq!(
+            sum(&[number("1"), error(Error::NotAvailable)]),
+            error(Error::NotAvailable)
+        );
+
+        assert_eq!(product(&[number("2"), number("-1.5")]), number("-3"));
+        assert_eq!(product(&[range, number("4")]), number("8"));
+        assert_eq!(product(&[]), number("0"));
+    }
+
+    #[test]
+    fn test_abs_sign() {
+        check(abs, &[(&["-1.5"], number("1.5")), (&["2"], number("2"))]);
+        check(
+            sign,
+            &[
+                (&["-1.5"], number("-1")),
+                (&["0"], number("0")),
+                (&["3"], number("1")),
+                (&[], error(Error::Value)),
+            ],
+        );
+        assert_eq!(abs(&[Value::Empty]), number("0"));
+    }
+
+    #[test]
+    fn test_round() {
+        check(
+            round,
+            &[
+                (&["2.5"], number("3")),
+                (&["-2.5"], number("-3")),
+                (&["2.4999"], number("2")),
+                (&["1.2345", "2"], number("1.23")),
+                (&["1.235", "2"], number("1.24")),
+                (&["1.5", "5"], number("1.5")),
+                (&["1250", "-2"], number("1300")),
+                (&["1234.5", "-4"], number("0")),
+                (&["1.99", "1.9"], number("2")),
+            ],
+        );
+        check(
+            roundup,
+            &[
+                (&["1.21", "1"], number("1.3")),
+                (&["-1.21", "1"], number("-1.3")),
+                (&["1.2"], number("2")),
+                (&["1201", "-2"], number("1300")),
+            ],
+        );
+        check(
+            rounddown,
+            &[
+                (&["1.29", "1"], number("1.2")),
+                (&["-1.29", "1"], number("-1.2")),
+                (&["1299", "-2"], number("1200")),
+            ],
+        );
+        check(
+            trunc,
+            &[
+                (&["-1.9"], number("-1")),
+                (&["3.14159", "3"], number("3.141")),
+            ],
+        );
+        assert_eq!(round(&[number("1"), number("1e30")]), error(Error::Number));
+    }
+
+    #[test]
+    fn test_int_ceiling_floor() {
+        check(
+            int,
+            &[
+                (&["1.5"], number("1")),
+                (&["-1.5"], number("-2")),
+                (&["-2"], number("-2")),
+            ],
+        );
+        check(
+            ceiling,
+            &[
+                (&["2.1"], number("3")),
+                (&["2.5", "2"], number("4")),
+                (&["-2.5", "2"], number("-2")),
+                (&["-2.5", "-2"], number("-4")),
+                (&["1.234", "0.05"], number("1.25")),
+                (&["2.5", "0"], number("0")),
+                (&["2.5", "-2"], error(Error::Number)),
+            ],
+        );
+        check(
+            floor,
+            &[
+                (&["2.9"], number("2")),
+                (&["2.5", "2"], number("2")),
+                (&["-2.5", "2"], number("-4")),
+                (&["-2.5", "-2"], number("-2")),
+                (&["1.234", "0.05"], number("1.2")),
+                (&["2.5", "0"], error(Error::DivisionByZero)),
+                (&["2.5", "-2"], error(Error::Number)),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_mod_power() {
+        check(
+            r#mod,
+            &[
+                (&["7", "3"], number("1")),
+                (&["-7", "3"], number("2")),
+                (&["7", "-3"], number("-2")),
+                (&["7.5", "2"], number("1.5")),
+                (&["1e30", "7"], number("1")),
+                (&["1", "0"], error(Error::DivisionByZero)),
+                (&["1"], error(Error::Value)),
+            ],
+        );
+        check(
+            power,
+            &[
+                (&["2", "10"], number("1024")),
+                (&["4", "0.5"], number("2")),
+                (&["-4", "0.5"], error(Error::Number)),
+                (&["0", "0"], error(Error::Number)),
+                (&["10", "400"], error(Error::Number)),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_gcd_lcm() {
+        check(
+            gcd,
+            &[
+                (&["12", "18"], number("6")),
+                (&["12.9", "18", "27"], number("3")),
+                (&["0", "5"], number("5")),
+                (&["-1", "5"], error(Error::Number)),
+                (&[], error(Error::Value)),
+            ],
+        );
+        check(
+            lcm,
+            &[
+                (&["4", "6"], number("12")),
+                (&["2", "3", "5"], number("30")),
+                (&["0", "5"], number("0")),
+                (&["-4", "6"], error(Error::Number)),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_sqrt_exp_ln() {
+        check(
+            sqrt,
+            &[
+                (&["4"], number("2")),
+                (&["2"], number("1.41421356237310")),
+                (&["0"], number("0")),
+                (&["-1"], error(Error::Number)),
+            ],
+        );
+        check(
+            exp,
+            &[
+                (&["0"], number("1")),
+                (&["1"], number("2.71828182845905")),
+                (&["-1"], number("0.367879441171442")),
+                (&["100"], number("2.68811714181614e43")),
+                (&["710"], error(Error::Number)),
+                (&["-710"], number("0")),
+                (&["-1e20"], number("0")),
+            ],
+        );
+        check(
+            ln,
+            &[
+                (&["1"], number("0")),
+                (&["2.718281828459045"], number("1")),
+                (&["10"], number("2.30258509299405")),
+                (&["0.001"], number("-6.90775527898214")),
+                (&["1e100000"], number("230258.509299405")),
+                (&["1e-100000"], number("-230258.509299405")),
+                (&["123456789"], number("18.631401766168")),
+                (&["1.00000000001"], number("9.99999999995e-12")),
+                (&["0"], error(Error::Number)),
+            ],
+        );
+        check(
+            log,
+            &[
+                (&["100"], number("2")),
+                (&["8", "2"], number("3")),
+                (&["2", "10"], number("0.301029995663981")),
+                (&["2", "1"], error(Error::DivisionByZero)),
+                (&["2", "-1"], error(Error::Number)),
+                (&["-2"], error(Error::Number)),
+            ],
+        );
+        check(log10, &[(&["1000"], number("3"))]);
+    }
+
+    #[test]
+    fn test_trigonometric() {
+        check(pi, &[(&[], number("3.14159265358979"))]);
+        check(
+            sin,
+            &[
+                (&["0"], number("0")),
+                (&["1"], number("0.841470984807897")),
+                (&["-10"], number("0.544021110889370")),
+                (&["1.5707963267948966"], number("1")),
+                (&["1e9"], error(Error::Number)),
+            ],
+        );
+        check(
+            cos,
+            &[(&["0"], number("1")), (&["1"], number("0.540302305868140"))],
+        );
+        check(tan, &[(&["1"], number("1.55740772465490"))]);
+        check(
+            asin,
+            &[
+                (&["0.5"], number("0.523598775598299")),
+                (&["-1"], number("-1.57079632679490")),
+                (&["1.1"], error(Error::Number)),
+            ],
+        );
+        check(
+            acos,
+            &[
+                (&["0.5"], number("1.04719755119660")),
+                (&["1"], number("0")),
+            ],
+        );
+        check(
+            atan,
+            &[
+                (&["1"], number("0.785398163397448")),
+                (&["-1e10"], number("-1.57079632669490")),
+            ],
+        );
+        check(
+            atan2,
+            &[
+                (&["1", "1"], number("0.785398163397448")),
+                (&["-1", "1"], number("2.35619449019234")),
+                (&["-1", "-1"], number("-2.35619449019234")),
+                (&["0", "-2"], number("-1.57079632679490")),
+                (&["0", "0"], error(Error::DivisionByZero)),
+            ],
+        );
+        check(degrees, &[(&["3.14159265358979323846"], number("180"))]);
+        check(radians, &[(&["90"], number("1.57079632679490"))]);
+    }
+}
@@ -0,0 +1,639 @@
+//! Statistical functions such as `average`, `stdev` or `percentile`.
+//!
+//! Like `sum`, most of these take any number of arguments, which may be single values or ranges:
+//! values given directly must be numbers, while text, booleans and empty cells in ranges are ignored.
+//! The exceptions are the `count` functions, which never fail, and functions comparing ranges element-wise,
+//! such as `correl` and `sumproduct`.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+
+use bigdecimal::{BigDecimal, One, Zero};
+
+use crate::value::{Error, Value};
+
+use super::math::{inexact, integer};
+use super::{numbers, result, values};
+
+fn count_of<T>(values: &[T]) -> BigDecimal {
+    BigDecimal::from(values.len() as u64)
+}
+
+fn total<B: Borrow<BigDecimal>>(numbers: impl IntoIterator<Item = B>) -> BigDecimal {
+    numbers
+        .into_iter()
+        .fold(BigDecimal::zero(), |sum, number| sum + number.borrow())
+}
+
+/// The square root of a variance, rounded as it's usually irrational
+fn square_root(variance: Value) -> Value {
+    variance.as_number().map_or_else(Value::Error, |variance| {
+        Value::Number(inexact(
+            variance.sqrt().expect("variances are not negative"),
+        ))
+    })
+}
+
+/// The numbers of a single argument, which may be a range, sorted in ascending order
+fn sorted(argument: &Value) -> Result<Vec<&BigDecimal>, Error> {
+    let mut numbers = numbers(std::slice::from_ref(argument))?;
+    numbers.sort();
+    Ok(numbers)
+}
+
+/// The variance of the numbers, dividing by `n - 1` for a sample or `n` for a whole population
+fn variance(numbers: &[&BigDecimal], sample: bool) -> Result<BigDecimal, Error> {
+    let n = count_of(numbers);
+    let divisor = if sample {
+        &n - BigDecimal::one()
+    } else {
+        n.clone()
+    };
+    if divisor <= BigDecimal::zero() {
+        return Err(Error::DivisionByZero);
+    }
+
+    // Σ(x - mean)² = (n * Σx² - (Σx)²) / n, which can be calculated exactly
+    let sum = total(numbers.iter().copied());
+    let sum_of_squares = total(numbers.iter().map(|number| number.square()));
+    Ok((n.clone() * sum_of_squares - sum.square()) / (n * divisor))
+}
+
+/// The value at the fraction `k` (between 0 and 1) of the sorted numbers, interpolating between neighbors
+fn percentile_of(sorted: &[&BigDecimal], k: &BigDecimal) -> Result<BigDecimal, Error> {
+    if sorted.is_empty() || k < &BigDecimal::zero() || k > &BigDecimal::one() {
+        return Err(Error::Number);
+    }
+
+    let position = k * (count_of(sorted) - BigDecimal::one());
+    let index = integer(&position)? as usize;
+    let fraction = position - BigDecimal::from(index as u64);
+    match sorted.get(index + 1) {
+        Some(next) if !fraction.is_zero() => Ok(sorted[index] + fraction * (*next - sorted[index])),
+        _ => Ok(sorted[index].clone()),
+    }
+}
+
+/// The position `k` of `large` and `small`, counting from 1; fractions are rounded up
+fn position(k: &Value, count: usize) -> Result<usize, Error> {
+    let k = k.as_number()?;
+    if *k <= BigDecimal::zero() || *k > BigDecimal::from(count as u64) {
+        return Err(Error::Number);
+    }
+    let truncated = integer(k)? as usize;
+    Ok(if BigDecimal::from(truncated as u64) < *k {
+        truncated + 1
+    } else {
+        truncated
+    })
+}
+
+/// `average(number, ...)`: the arithmetic mean of the numbers
+pub fn average(arguments: &[Value]) -> Value {
+    let inner = || {
+        let numbers = numbers(arguments)?;
+        if numbers.is_empty() {
+            return Err(Error::DivisionByZero);
+        }
+        Ok(total(numbers.iter().copied()) / count_of(&numbers))
+    };
+    result(inner())
+}
+
+/// `median(number, ...)`: the middle number, or the mean of the two middle numbers
+pub fn median(arguments: &[Value]) -> Value {
+    let inner = || {
+        let mut numbers = numbers(arguments)?;
+        numbers.sort();
+        let middle = numbers.len() / 2;
+        match numbers.len() {
+            0 => Err(Error::Number),
+            n if n % 2 == 1 => Ok(numbers[middle].clone()),
+            _ => Ok((numbers[middle - 1] + numbers[middle]).half()),
+        }
+    };
+    result(inner())
+}
+
+/// `mode(number, ...)`: the most frequent number; of several equally frequent ones, the one that comes first.
+/// If no number occurs more than once, the result is `#N/A`.
+pub fn mode(arguments: &[Value]) -> Value {
+    let inner = || {
+        let numbers = numbers(arguments)?;
+        let mut counts = HashMap::new();
+        for number in &numbers {
+            // normalize so that e.g. 1 and 1.0 are counted as the same number
+            *counts.entry(number.normalized()).or_insert(0) += 1;
+        }
+
+        let mut mode: Option<(&BigDecimal, usize)> = None;
+        for number in numbers {
+            let count = counts[&number.normalized()];
+            if count > 1 && mode.is_none_or(|(_, most)| count > most) {
+                mode = Some((number, count));
+            }
+        }
+        mode.map(|(number, _)| number.clone())
+            .ok_or(Error::NotAvailable)
+    };
+    result(inner())
+}
+
+/// `min(number, ...)`: the smallest number, or `0` if there are none
+pub fn min(arguments: &[Value]) -> Value {
+    result(numbers(arguments).map(|numbers| {
+        numbers
+            .into_iter()
+            .min()
+            .cloned()
+            .unwrap_or_else(BigDecimal::zero)
+    }))
+}
+
+/// `max(number, ...)`: the largest number, or `0` if there are none
+pub fn max(arguments: &[Value]) -> Value {
+    result(numbers(arguments).map(|numbers| {
+        numbers
+            .into_iter()
+            .max()
+            .cloned()
+            .unwrap_or_else(BigDecimal::zero)
+    }))
+}
+
+/// `count(value, ...)`: the number of values that are numbers or dates; other values, even errors, are not counted
+pub fn count(arguments: &[Value]) -> Value {
+    let count = arguments
+        .iter()
+        .flat_map(values)
+        .filter(|value| matches!(value, Value::Number(_) | Value::DateTime(_)))
+        .count();
+    Value::Number(BigDecimal::from(count as u64))
+}
+
+/// `counta(value, ...)`: the number of values that are not empty, including errors
+pub fn counta(arguments: &[Value]) -> Value {
+    let count = arguments
+        .iter()
+        .flat_map(values)
+        .filter(|value| !matches!(value, Value::Empty))
+        .count();
+    Value::Number(BigDecimal::from(count as u64))
+}
+
+/// `countblank(range, ...)`: the number of empty cells, including those containing empty text
+pub fn countblank(arguments: &[Value]) -> Value {
+    let count = arguments
+        .iter()
+        .flat_map(values)
+        .filter(|value| match value {
+            Value::Empty => true,
+            Value::String(string) => string.is_empty(),
+            _ => false,
+        })
+        .count();
+    Value::Number(BigDecimal::from(count as u64))
+}
+
+/// `var(number, ...)`: the variance of a sample, i.e. dividing by `n - 1`
+pub fn var(arguments: &[Value]) -> Value {
+    result(numbers(arguments).and_then(|numbers| variance(&numbers, true)))
+}
+
+/// `var.p(number, ...)`: the variance of a whole population, i.e. dividing by `n`
+pub fn var_p(arguments: &[Value]) -> Value {
+    result(numbers(arguments).and_then(|numbers| variance(&numbers, false)))
+}
+
+/// `stdev(number, ...)`: the standard deviation of a sample, i.e. the square root of `var`
+pub fn stdev(arguments: &[Value]) -> Value {
+    square_root(var(arguments))
+}
+
+/// `stdev.p(number, ...)`: the standard deviation of a whole population, i.e. the square root of `var.p`
+pub fn stdev_p(arguments: &[Value]) -> Value {
+    square_root(var_p(arguments))
+}
+
+/// `percentile(range, k)`: the value below which the fraction `k` (between 0 and 1) of the numbers lie,
+/// interpolating between the numbers if necessary
+pub fn percentile(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [range, k] => percentile_of(&sorted(range)?, k.as_number()?),
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `quartile(range, quart)`: the minimum (0), first quartile (1), median (2), third quartile (3)
+/// or maximum (4) of the numbers
+pub fn quartile(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [range, quart] => {
+            let quart = integer(quart.as_number()?)?;
+            if !(0..=4).contains(&quart) {
+                return Err(Error::Number);
+            }
+            let k = BigDecimal::from(quart) / BigDecimal::from(4);
+            percentile_of(&sorted(range)?, &k)
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `rank(number, range, [order])`: the position of the number among the numbers in the range,
+/// largest first if `order` is `0` (the default), otherwise smallest first.
+/// If the number is not in the range, the result is `#N/A`.
+pub fn rank(arguments: &[Value]) -> Value {
+    let inner = || {
+        let (number, range, ascending) = match arguments {
+            [number, range] => (number, range, false),
+            [number, range, order] => (number, range, !order.as_number()?.is_zero()),
+            _ => return Err(Error::Value),
+        };
+        let number = number.as_number()?;
+        let numbers = sorted(range)?;
+        if !numbers.contains(&number) {
+            return Err(Error::NotAvailable);
+        }
+
+        let before = numbers
+            .iter()
+            .filter(|other| {
+                if ascending {
+                    **other < number
+                } else {
+                    **other > number
+                }
+            })
+            .count();
+        Ok(BigDecimal::from(before as u64 + 1))
+    };
+    result(inner())
+}
+
+/// `large(range, k)`: the `k`-th largest number
+pub fn large(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [range, k] => {
+            let numbers = sorted(range)?;
+            let k = position(k, numbers.len())?;
+            Ok(numbers[numbers.len() - k].clone())
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `small(range, k)`: the `k`-th smallest number
+pub fn small(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [range, k] => {
+            let numbers = sorted(range)?;
+            let k = position(k, numbers.len())?;
+            Ok(numbers[k - 1].clone())
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `correl(range1, range2)`: the correlation coefficient of two ranges of the same size.
+/// Only pairs of values that are both numbers are used.
+pub fn correl(arguments: &[Value]) -> Value {
+    let inner = || {
+        let (xs, ys) = match arguments {
+            [xs, ys] => (values(xs), values(ys)),
+            _ => return Err(Error::Value),
+        };
+        if xs.len() != ys.len() {
+            return Err(Error::NotAvailable);
+        }
+
+        let mut pairs = Vec::new();
+        for (x, y) in xs.iter().zip(ys) {
+            match (x, y) {
+                (Value::Error(error), _) | (_, Value::Error(error)) => return Err(*error),
+                (Value::Number(x) | Value::DateTime(x), Value::Number(y) | Value::DateTime(y)) => {
+                    pairs.push((x, y))
+                }
+                _ => {}
+            }
+        }
+
+        // r = (n Σxy - Σx Σy) / sqrt((n Σx² - (Σx)²) (n Σy² - (Σy)²))
+        let n = count_of(&pairs);
+        let sum_x = total(pairs.iter().map(|(x, _)| *x));
+        let sum_y = total(pairs.iter().map(|(_, y)| *y));
+        let sum_xy = total(pairs.iter().map(|(x, y)| *x * *y));
+        let sum_xx = total(pairs.iter().map(|(x, _)| x.square()));
+        let sum_yy = total(pairs.iter().map(|(_, y)| y.square()));
+
+        let covariance = &n * sum_xy - &sum_x * &sum_y;
+        let variances = (&n * sum_xx - sum_x.square()) * (&n * sum_yy - sum_y.square());
+        if variances.is_zero() {
+            return Err(Error::DivisionByZero);
+        }
+        let root = variances.sqrt().expect("variances are not negative");
+        Ok(inexact(covariance / root))
+    };
+    result(inner())
+}
+
+/// `sumproduct(range, ...)`: the sum of the products of corresponding values of ranges of the same size.
+/// Values that are not numbers count as `0`.
+pub fn sumproduct(arguments: &[Value]) -> Value {
+    let inner = || {
+        let (first, rest) = arguments.split_first().ok_or(Error::Value)?;
+        let dimensions = |value: &Value| match value {
+            Value::Array(array) => (array.rows(), array.cols()),
+            _ => (1, 1),
+        };
+        if rest
+            .iter()
+            .any(|other| dimensions(other) != dimensions(first))
+        {
+            return Err(Error::Value);
+        }
+
+        let mut sum = BigDecimal::zero();
+        for i in 0..values(first).len() {
+            let mut product = BigDecimal::one();
+            for argument in arguments {
+                match &values(argument)[i] {
+                    Value::Number(number) | Value::DateTime(number) => product *= number,
+                    Value::Error(error) => return Err(*error),
+                    _ => product = BigDecimal::zero(),
+                }
+            }
+            sum += product;
+        }
+        Ok(sum)
+    };
+    result(inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::functions::testing::{column, error, number, numbers, string};
+
+    /// a range containing numbers and values that are ignored
+    fn mixed() -> Value {
+        column(&[
+            number("1"),
+            string("foo"),
+            Value::Empty,
+            number("3"),
+            Value::Boolean(true),
+            number("2"),
+        ])
+    }
+
+    #[test]
+    fn test_average() {
+        assert_eq!(average(&[numbers(&["1", "2", "3", "4"])]), number("2.5"));
+        assert_eq!(average(&[mixed()]), number("2"));
+        assert_eq!(average(&[mixed(), number("6")]), number("3"));
+        assert_eq!(average(&[number("1"), Value::Empty]), number("1"));
+        assert_eq!(
+            average(&[column(&[string("foo")])]),
+            error(Error::DivisionByZero)
+        );
+        assert_eq!(average(&[number("1"), string("foo")]), error(Error::Value));
+        assert_eq!(
+            average(&[number("1"), Value::Boolean(true)]),
+            error(Error::Value)
+        );
+        assert_eq!(
+            average(&[column(&[number("1"), error(Error::NotAvailable)])]),
+            error(Error::NotAvailable)
+        );
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&[numbers(&["3", "1", "2"])]), number("2"));
+        assert_eq!(median(&[numbers(&["4", "1", "3", "2"])]), number("2.5"));
+        assert_eq!(median(&[mixed(), number("10")]), number("2.5"));
+        assert_eq!(median(&[]), error(Error::Number));
+        assert_eq!(median(&[string("1")]), error(Error::Value));
+    }
+
+    #[test]
+    fn test_mode() {
+        assert_eq!(mode(&[numbers(&["1", "2", "2", "3", "3"])]), number("2"));
+        assert_eq!(mode(&[numbers(&["3", "1", "3", "1"])]), number("3"));
+        assert_eq!(mode(&[numbers(&["1", "1.0", "2"])]), number("1"));
+        assert_eq!(mode(&[mixed(), number("3")]), number("3"));
+        assert_eq!(mode(&[numbers(&["1", "2"])]), error(Error::NotAvailable));
+        assert_eq!(mode(&[]), error(Error::NotAvailable));
+        assert_eq!(mode(&[string("x")]), error(Error::Value));
+    }
+
+    #[test]
+    fn test_min_max() {
+        assert_eq!(min(&[mixed()]), number("1"));
+        assert_eq!(min(&[mixed(), number("-1")]), number("-1"));
+        assert_eq!(max(&[mixed()]), number("3"));
+        assert_eq!(max(&[mixed(), number("3.5")]), number("3.5"));
+        assert_eq!(min(&[column(&[string("foo")])]), number("0"));
+        assert_eq!(max(&[]), number("0"));
+        assert_eq!(max(&[string("foo")]), error(Error::Value));
+        assert_eq!(
+            min(&[number("1"), error(Error::Reference)]),
+            error(Error::Reference)
+        );
+    }
+
+    #[test]
+    fn test_count() {
+        let range = &[column(&[
+            number("1"),
+            string("foo"),
+            Value::Empty,
+            string(""),
+            Value::Boolean(false),
+            error(Error::Value),
+        ])];
+
+        assert_eq!(count(range), number("1"));
+        assert_eq!(
+            count(&[range[0].clone(), number("2"), string("3")]),
+            number("2")
+        );
+        assert_eq!(count(&[]), number("0"));
+
+        assert_eq!(counta(range), number("5"));
+        assert_eq!(counta(&[Value::Empty, string("x")]), number("1"));
+
+        assert_eq!(countblank(range), number("2"));
+        assert_eq!(countblank(&[Value::Empty]), number("1"));
+        assert_eq!(countblank(&[number("0")]), number("0"));
+    }
+
+    #[test]
+    fn test_variance() {
+        let range = &[numbers(&["2", "4", "4", "4", "5", "5", "7", "9"])];
+
+        assert_eq!(var_p(range), number("4"));
+        assert_eq!(stdev_p(range), number("2"));
+        assert_eq!(
+            var(range),
+            Value::Number(BigDecimal::from(32) / BigDecimal::from(7))
+        );
+        assert_eq!(stdev(range), number("2.13808993529940"));
+
+        assert_eq!(var(&[mixed()]), number("1"));
+        assert_eq!(var(&[number("1"), number("3")]), number("2"));
+        assert_eq!(var(&[number("1")]), error(Error::DivisionByZero));
+        assert_eq!(var_p(&[number("1")]), number("0"));
+        assert_eq!(var_p(&[]), error(Error::DivisionByZero));
+        assert_eq!(stdev(&[]), error(Error::DivisionByZero));
+        assert_eq!(stdev(&[string("1"), number("2")]), error(Error::Value));
+    }
+
+    #[test]
+    fn test_percentile_quartile() {
+        let range = numbers(&["1", "3", "2", "4"]);
+
+        assert_eq!(percentile(&[range.clone(), number("0")]), number("1"));
+        assert_eq!(percentile(&[range.clone(), number("1")]), number("4"));
+        assert_eq!(percentile(&[range.clone(), number("0.3")]), number("1.9"));
+        assert_eq!(percentile(&[range.clone(), number("0.5")]), number("2.5"));
+        assert_eq!(percentile(&[mixed(), number("0.5")]), number("2"));
+        assert_eq!(percentile(&[number("5"), number("0.5")]), number("5"));
+        assert_eq!(
+            percentile(&[range.clone(), number("1.1")]),
+            error(Error::Number)
+        );
+        assert_eq!(
+            percentile(&[range.clone(), number("-0.1")]),
+            error(Error::Number)
+        );
+        assert_eq!(
+            percentile(&[column(&[]), number("0.5")]),
+            error(Error::Number)
+        );
+        assert_eq!(percentile(&[number("0.5")]), error(Error::Value));
+
+        assert_eq!(quartile(&[range.clone(), number("0")]), number("1"));
+        assert_eq!(quartile(&[range.clone(), number("1")]), number("1.75"));
+        assert_eq!(quartile(&[range.clone(), number("2.9")]), number("2.5"));
+        assert_eq!(quartile(&[range.clone(), number("3")]), number("3.25"));
+        assert_eq!(quartile(&[range.clone(), number("4")]), number("4"));
+        assert_eq!(
+            quartile(&[range.clone(), number("5")]),
+            error(Error::Number)
+        );
+        assert_eq!(quartile(&[range, string("1")]), error(Error::Value));
+    }
+
+    #[test]
+    fn test_rank() {
+        let range = numbers(&["3", "1", "4", "1", "5"]);
+
+        assert_eq!(rank(&[number("5"), range.clone()]), number("1"));
+        assert_eq!(rank(&[number("1"), range.clone()]), number("4"));
+        assert_eq!(
+            rank(&[number("1"), range.clone(), number("1")]),
+            number("1")
+        );
+        assert_eq!(
+            rank(&[number("4"), range.clone(), number("1")]),
+            number("4")
+        );
+        assert_eq!(rank(&[number("3"), mixed()]), number("1"));
+        assert_eq!(
+            rank(&[number("2"), range.clone()]),
+            error(Error::NotAvailable)
+        );
+        assert_eq!(rank(&[string("1"), range]), error(Error::Value));
+    }
+
+    #[test]
+    fn test_large_small() {
+        let range = numbers(&["3", "1", "4", "1", "5"]);
+
+        assert_eq!(large(&[range.clone(), number("1")]), number("5"));
+        assert_eq!(large(&[range.clone(), number("2")]), number("4"));
+        assert_eq!(large(&[range.clone(), number("1.5")]), number("4"));
+        assert_eq!(large(&[range.clone(), number("5")]), number("1"));
+        assert_eq!(small(&[range.clone(), number("1")]), number("1"));
+        assert_eq!(small(&[range.clone(), number("3")]), number("3"));
+        assert_eq!(small(&[mixed(), number("3")]), number("3"));
+        assert_eq!(large(&[range.clone(), number("0")]), error(Error::Number));
+        assert_eq!(small(&[range.clone(), number("6")]), error(Error::Number));
+        assert_eq!(small(&[range, string("1")]), error(Error::Value));
+    }
+
+    #[test]
+    fn test_correl() {
+        let xs = numbers(&["1", "2", "3", "4"]);
+
+        assert_eq!(
+            correl(&[xs.clone(), numbers(&["2", "4", "6", "8"])]),
+            number("1")
+        );
+        assert_eq!(
+            correl(&[xs.clone(), numbers(&["8", "6", "4", "2"])]),
+            number("-1")
+        );
+        assert_eq!(
+            correl(&[xs.clone(), numbers(&["1", "3", "2", "5"])]),
+            number("0.8315218406203")
+        );
+        // pairs that aren't both numbers are ignored
+        assert_eq!(
+            correl(&[
+                xs.clone(),
+                column(&[number("2"), string("x"), number("6"), number("8")])
+            ]),
+            number("1")
+        );
+        assert_eq!(
+            correl(&[xs.clone(), numbers(&["1", "1", "1", "1"])]),
+            error(Error::DivisionByZero)
+        );
+        assert_eq!(
+            correl(&[xs.clone(), numbers(&["1", "2"])]),
+            error(Error::NotAvailable)
+        );
+        assert_eq!(
+            correl(&[
+                xs,
+                column(&[number("1"), number("2"), number("3"), error(Error::Value)])
+            ]),
+            error(Error::Value)
+        );
+    }
+
+    #[test]
+    fn test_sumproduct() {
+        let xs = numbers(&["1", "2", "3"]);
+        let ys = numbers(&["4", "5", "6"]);
+
+        assert_eq!(sumproduct(&[xs.clone(), ys.clone()]), number("32"));
+        assert_eq!(sumproduct(&[numbers(&["1", "2", "3"])]), number("6"));
+        assert_eq!(
+            sumproduct(&[xs.clone(), ys.clone(), xs.clone()]),
+            number("78")
+        );
+        assert_eq!(sumproduct(&[mixed(), mixed()]), number("14"));
+        assert_eq!(sumproduct(&[number("2"), number("3")]), number("6"));
+        assert_eq!(
+            sumproduct(&[xs.clone(), numbers(&["1", "2"])]),
+            error(Error::Value)
+        );
+        assert_eq!(
+            sumproduct(&[
+                xs,
+                column(&[number("1"), error(Error::Number), number("3")])
+            ]),
+            error(Error::Number)
+        );
+        assert_eq!(sumproduct(&[]), error(Error::Value));
+    }
+}
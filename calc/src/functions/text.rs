@@ -0,0 +1,962 @@
+//! Text functions such as `len`, `left` or `substitute`.
+//!
+//! Arguments that are used as text may also be numbers or booleans, which are converted as the `&` operator
+//! converts them (see [`Value::to_text`]). Positions and lengths count characters (Unicode scalar values),
+//! not bytes, and positions start at 1. Invalid positions or lengths, such as `left("foo", -1)`,
+//! result in `#VALUE!`.
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use nom::combinator::all_consuming;
+
+use crate::parser::datetime::parse_datetime;
+use crate::parser::number::parse_number;
+use crate::value::{Array, Error, Value};
+
+use super::math::{integer, round_to, Rounding};
+use super::values;
+
+/// The maximum length of text created by functions such as `rept` or `&`; longer results are `#VALUE!`
+pub const MAX_LENGTH: usize = 32767;
+
+fn result(result: Result<String, Error>) -> Value {
+    result.map_or_else(Value::Error, Value::String)
+}
+
+/// Checks that a length in characters of text that is about to be created is at most [`MAX_LENGTH`]
+fn check_length(length: usize) -> Result<(), Error> {
+    if length > MAX_LENGTH {
+        return Err(Error::Value);
+    }
+    Ok(())
+}
+
+/// Text that was created by a function, or `#VALUE!` if it is longer than [`MAX_LENGTH`]
+pub(crate) fn limited(text: String) -> Result<String, Error> {
+    check_length(text.chars().count())?;
+    Ok(text)
+}
+
+/// A number of characters, which can't be negative
+fn length(value: &Value) -> Result<usize, Error> {
+    let length = integer(value.as_number()?).map_err(|_| Error::Value)?;
+    usize::try_from(length).map_err(|_| Error::Value)
+}
+
+/// A position in a text, which starts at 1; the result is the corresponding index, starting at 0
+fn position(value: &Value) -> Result<usize, Error> {
+    match length(value)? {
+        0 => Err(Error::Value),
+        position => Ok(position - 1),
+    }
+}
+
+/// Calls a function of a single text.
+fn unary<F>(arguments: &[Value], function: F) -> Value
+where
+    F: FnOnce(&str) -> String,
+{
+    match arguments {
+        [text] => result(text.to_text().map(|text| function(&text))),
+        _ => Value::Error(Error::Value),
+    }
+}
+
+/// The characters of a text starting at index `start`, at most `length` of them
+fn chars(text: &str, start: usize, length: usize) -> String {
+    text.chars().skip(start).take(length).collect()
+}
+
+/// `concat(value, ...)` or `concatenate(value, ...)`: all values, including those in ranges, joined as text
+pub fn concat(arguments: &[Value]) -> Value {
+    let inner = || {
+        let mut concatenated = String::new();
+        for value in arguments.iter().flat_map(values) {
+            concatenated.push_str(&value.to_text()?);
+            check_length(concatenated.chars().count())?;
+        }
+        Ok(concatenated)
+    };
+    result(inner())
+}
+
+/// `len(text)`: the number of characters of the text
+pub fn len(arguments: &[Value]) -> Value {
+    match arguments {
+        [text] => text.to_text().map_or_else(Value::Error, |text| {
+            Value::Number(BigDecimal::from(text.chars().count() as u64))
+        }),
+        _ => Value::Error(Error::Value),
+    }
+}
+
+/// `left(text, [count])`: the first `count` characters of the text, by default one
+pub fn left(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [text] => Ok(chars(&text.to_text()?, 0, 1)),
+        [text, count] => Ok(chars(&text.to_text()?, 0, length(count)?)),
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `right(text, [count])`: the last `count` characters of the text, by default one
+pub fn right(arguments: &[Value]) -> Value {
+    let inner = || {
+        let (text, count) = match arguments {
+            [text] => (text.to_text()?, 1),
+            [text, count] => (text.to_text()?, length(count)?),
+            _ => return Err(Error::Value),
+        };
+        let start = text.chars().count().saturating_sub(count);
+        Ok(chars(&text, start, count))
+    };
+    result(inner())
+}
+
+/// `mid(text, start, count)`: `count` characters of the text, starting at position `start`
+pub fn mid(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [text, start, count] => Ok(chars(&text.to_text()?, position(start)?, length(count)?)),
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `upper(text)`: the text in upper case
+pub fn upper(arguments: &[Value]) -> Value {
+    unary(arguments, str::to_uppercase)
+}
+
+/// `lower(text)`: the text in lower case
+pub fn lower(arguments: &[Value]) -> Value {
+    unary(arguments, str::to_lowercase)
+}
+
+/// `proper(text)`: the text with the first letter of each word in upper case, and all other letters in lower case.
+/// A word starts with any letter that doesn't follow another letter.
+pub fn proper(arguments: &[Value]) -> Value {
+    unary(arguments, |text| {
+        let mut proper = String::with_capacity(text.len());
+        let mut in_word = false;
+        for char in text.chars() {
+            if in_word {
+                proper.extend(char.to_lowercase());
+            } else {
+                proper.extend(char.to_uppercase());
+            }
+            in_word = char.is_alphabetic();
+        }
+        proper
+    })
+}
+
+/// `trim(text)`: the text without leading and trailing spaces, and with runs of spaces replaced by a single one
+pub fn trim(arguments: &[Value]) -> Value {
+    unary(arguments, |text| {
+        text.split(' ')
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    })
+}
+
+/// `substitute(text, old, new, [instance])`: the text with occurrences of `old` replaced by `new`;
+/// if `instance` is given, only that occurrence (counting from 1) is replaced
+pub fn substitute(arguments: &[Value]) -> Value {
+    let inner = || {
+        let (text, old, new, instance) = match arguments {
+            [text, old, new] => (text, old, new, None),
+            [text, old, new, instance] => (text, old, new, Some(position(instance)?)),
+            _ => return Err(Error::Value),
+        };
+        let (text, old, new) = (text.to_text()?, old.to_text()?, new.to_text()?);
+        if old.is_empty() {
+            return Ok(text.into_owned());
+        }
+
+        match instance {
+            None => {
+                let replaced = text.matches(old.as_ref()).count();
+                let (old_length, new_length) = (old.chars().count(), new.chars().count());
+                check_length(
+                    (text.chars().count() - replaced * old_length)
+                        .saturating_add(replaced.saturating_mul(new_length)),
+                )?;
+                Ok(text.replace(old.as_ref(), &new))
+            }
+            Some(instance) => match text.match_indices(old.as_ref()).nth(instance) {
+                Some((index, _)) => {
+                    limited([&text[..index], &new, &text[index + old.len()..]].concat())
+                }
+                None => Ok(text.into_owned()),
+            },
+        }
+    };
+    result(inner())
+}
+
+/// `replace(text, start, count, new)`: the text with `count` characters starting at position `start`
+/// replaced by `new`
+pub fn replace(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [text, start, count, new] => {
+            let text = text.to_text()?;
+            let (start, count) = (position(start)?, length(count)?);
+            let mut replaced = chars(&text, 0, start);
+            replaced.push_str(&new.to_text()?);
+            replaced.extend(text.chars().skip(start.saturating_add(count)));
+            limited(replaced)
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// Shared implementation of `find` and `search`: the position of the first match of the pattern
+/// at or after the optional start position.
+fn find_with(arguments: &[Value], pattern: fn(&str) -> Pattern) -> Value {
+    let inner = || {
+        let (needle, haystack, start) = match arguments {
+            [needle, haystack] => (needle, haystack, 0),
+            [needle, haystack, start] => (needle, haystack, position(start)?),
+            _ => return Err(Error::Value),
+        };
+        let pattern = pattern(&needle.to_text()?);
+        let haystack: Vec<char> = haystack.to_text()?.chars().collect();
+        if start > haystack.len() {
+            return Err(Error::Value);
+        }
+
+        pattern
+            .find(&haystack, start)
+            .map(|index| BigDecimal::from(index as u64 + 1))
+            .ok_or(Error::Value)
+    };
+    inner().map_or_else(Value::Error, Value::Number)
+}
+
+/// `find(needle, haystack, [start])`: the position of the first occurrence of `needle` in `haystack`,
+/// at or after position `start`. The search is case-sensitive; if nothing is found, the result is `#VALUE!`.
+pub fn find(arguments: &[Value]) -> Value {
+    find_with(arguments, Pattern::literal)
+}
+
+/// `search(pattern, text, [start])`: like `find`, but case-insensitive, and the pattern may contain
+/// wildcards: `?` matches any character and `*` any number of characters, while `~` escapes a wildcard
+pub fn search(arguments: &[Value]) -> Value {
+    find_with(arguments, Pattern::new)
+}
+
+/// `rept(text, count)`: the text repeated `count` times
+pub fn rept(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [text, count] => {
+            let (text, count) = (text.to_text()?, length(count)?);
+            check_length(text.chars().count().saturating_mul(count))?;
+            Ok(text.repeat(count))
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `text(value, format)`: the number formatted according to the format, such as `0.00`, `#,##0` or `0.0%`.
+/// Dates and times are formatted as their serial numbers; other values are converted to text as they are.
+///
+/// Supported in formats are:
+///
+/// - the digit placeholders `0` (always shown) and `#` (only shown if significant), and a decimal point
+/// - `,` between digit placeholders, which groups thousands
+/// - `%`, which shows the number as a percentage
+/// - `E+00` or `E-00`, which shows the number in scientific notation
+/// - literal text in double quotes, or single characters escaped by `\`; other characters are shown as they are
+/// - up to three sections separated by `;`, for positive numbers, negative numbers and zero
+///
+/// `General` formats numbers as they are.
+pub fn text(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [Value::Number(number) | Value::DateTime(number), format] => {
+            format_number(number, &format.to_text()?)
+        }
+        [value, format] => {
+            format.to_text()?;
+            Ok(value.to_text()?.into_owned())
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `value(text)`: the text converted to a number, which may be followed by `%`;
+/// dates and times such as `2024-02-29` are converted to their serial numbers
+pub fn value(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [Value::Number(number) | Value::DateTime(number)] => Ok(number.clone()),
+        [Value::Empty] => Ok(BigDecimal::zero()),
+        [Value::String(text)] => {
+            let text = text.trim();
+            if let Ok((_, serial)) = all_consuming(parse_datetime)(text) {
+                return Ok(serial);
+            }
+            let (text, percent) = match text.strip_suffix('%') {
+                Some(text) => (text.trim_end(), true),
+                None => (text, false),
+            };
+            let (_, number) = all_consuming(parse_number)(text).map_err(|_| Error::Value)?;
+            Ok(if percent {
+                number / BigDecimal::from(100)
+            } else {
+                number
+            })
+        }
+        [Value::Error(error)] => Err(*error),
+        _ => Err(Error::Value),
+    };
+    inner().map_or_else(Value::Error, Value::Number)
+}
+
+/// `exact(text1, text2)`: whether the two texts are exactly equal, i.e. case-sensitively
+pub fn exact(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [left, right] => Ok(left.to_text()? == right.to_text()?),
+        _ => Err(Error::Value),
+    };
+    inner().map_or_else(Value::Error, Value::Boolean)
+}
+
+/// `textjoin(delimiter, ignore_empty, value, ...)`: all values, including those in ranges, joined as text
+/// with the delimiter between them; if `ignore_empty` is true, empty values and texts are left out
+pub fn textjoin(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [delimiter, ignore_empty, values @ ..] if !values.is_empty() => {
+            let delimiter = delimiter.to_text()?;
+            let ignore_empty = ignore_empty.as_boolean()?;
+
+            let mut texts = Vec::new();
+            let mut length = 0usize;
+            for value in values.iter().flat_map(super::values) {
+                let text = value.to_text()?;
+                if !(ignore_empty && text.is_empty()) {
+                    if !texts.is_empty() {
+                        length = length.saturating_add(delimiter.chars().count());
+                    }
+                    length = length.saturating_add(text.chars().count());
+                    check_length(length)?;
+                    texts.push(text);
+                }
+            }
+            Ok(texts.join(&delimiter))
+        }
+        _ => Err(Error::Value),
+    };
+    result(inner())
+}
+
+/// `split(text, delimiter)`: the parts of the text between occurrences of the delimiter, as a row.
+/// As a cell can only hold a single value, the result is meant to be passed to other functions.
+pub fn split(arguments: &[Value]) -> Value {
+    let inner = || match arguments {
+        [text, delimiter] => {
+            let (text, delimiter) = (text.to_text()?, delimiter.to_text()?);
+            if delimiter.is_empty() {
+                return Err(Error::Value);
+            }
+            let parts: Vec<_> = text
+                .split(delimiter.as_ref())
+                .map(|part| Value::String(part.to_string()))
+                .collect();
+            Ok(Array::new(1, parts.len() as u32, parts))
+        }
+        _ => Err(Error::Value),
+    };
+    inner().map_or_else(Value::Error, Value::Array)
+}
+
+/// A search pattern, which may contain the wildcards `?` and `*`
+pub(crate) struct Pattern {
+    tokens: Vec<Token>,
+    case_sensitive: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Token {
+    Char(char),
+    /// `?`
+    AnyChar,
+    /// `*`
+    AnyChars,
+}
+
+impl Pattern {
+    /// A case-insensitive pattern with wildcards; `~` escapes the next character
+    pub(crate) fn new(pattern: &str) -> Self {
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(char) = chars.next() {
+            tokens.push(match char {
+                '?' => Token::AnyChar,
+                '*' => Token::AnyChars,
+                // a `~` at the end stands for itself
+                '~' => Token::Char(chars.next().unwrap_or('~')),
+                char => Token::Char(char),
+            });
+        }
+        Self {
+            tokens,
+            case_sensitive: false,
+        }
+    }
+
+    /// A case-sensitive pattern matching the text exactly, without wildcards
+    pub(crate) fn literal(pattern: &str) -> Self {
+        Self {
+            tokens: pattern.chars().map(Token::Char).collect(),
+            case_sensitive: true,
+        }
+    }
+
+    /// Whether the pattern matches the whole text
+    pub(crate) fn matches(&self, text: &[char]) -> bool {
+        // Greedy matching that, on a mismatch, lets the last `*` match one more character
+        // and continues after it. This takes at most quadratic time, as earlier `*`s never
+        // need to be revisited.
+        let tokens = &self.tokens;
+        let (mut token, mut position) = (0, 0);
+        // the index of the last `*` and the position in the text it matches up to
+        let mut backtrack = None;
+
+        while let Some(&char) = text.get(position) {
+            match tokens.get(token) {
+                Some(Token::AnyChars) => {
+                    backtrack = Some((token, position));
+                    token += 1;
+                }
+                Some(expected) if self.matches_char(expected, char) => {
+                    token += 1;
+                    position += 1;
+                }
+                _ => match backtrack {
+                    Some((star, end)) => {
+                        backtrack = Some((star, end + 1));
+                        token = star + 1;
+                        position = end + 1;
+                    }
+                    None => return false,
+                },
+            }
+        }
+
+        // the rest of the pattern must match the empty text
+        tokens[token..]
+            .iter()
+            .all(|token| matches!(token, Token::AnyChars))
+    }
+
+    /// The first index at or after `start` where the pattern matches the beginning of the rest of the text
+    pub(crate) fn find(&self, text: &[char], start: usize) -> Option<usize> {
+        // Between the `*`s, the pattern consists of segments matching a fixed number of characters. The match
+        // starts where the first segment matches first, if the other segments can be found after it in order;
+        // each is searched as early as possible, as a later match would leave less of the text for the rest.
+        let mut segments = self.tokens.split(|token| matches!(token, Token::AnyChars));
+        let first = segments.next().unwrap_or_default();
+        let found = self.find_segment(first, text, start)?;
+
+        let mut position = found + first.len();
+        for segment in segments {
+            position = self.find_segment(segment, text, position)? + segment.len();
+        }
+        Some(found)
+    }
+
+    /// The first index at or after `start` where a segment of the pattern without `*`s matches
+    fn find_segment(&self, segment: &[Token], text: &[char], start: usize) -> Option<usize> {
+        let last = text.len().checked_sub(segment.len())?;
+        (start..=last).find(|&index| {
+            segment
+                .iter()
+                .zip(&text[index..])
+                .all(|(token, &char)| self.matches_char(token, char))
+        })
+    }
+
+    fn matches_char(&self, token: &Token, char: char) -> bool {
+        match token {
+            Token::Char(expected) if self.case_sensitive => *expected == char,
+            Token::Char(expected) => {
+                *expected == char || expected.to_lowercase().eq(char.to_lowercase())
+            }
+            Token::AnyChar => true,
+            Token::AnyChars => unreachable!("`*` is handled while matching"),
+        }
+    }
+}
+
+/// A section of a number format, see [`text`]
+#[derive(Default)]
+struct Format {
+    /// literal text before the first digit placeholder
+    prefix: String,
+    /// literal text after the first digit placeholder
+    suffix: String,
+    /// the number of `0` placeholders before the decimal point
+    integer_digits: usize,
+    /// the number of `0` placeholders after the decimal point
+    min_decimals: usize,
+    /// the number of all placeholders after the decimal point
+    max_decimals: usize,
+    grouping: bool,
+    percent: bool,
+    /// for scientific notation, whether positive exponents have a `+` and the number of exponent digits
+    exponent: Option<(bool, usize)>,
+    /// whether the section contains any digit placeholders
+    number: bool,
+}
+
+impl Format {
+    fn parse(format: &str) -> Self {
+        let mut parsed = Self::default();
+        let mut decimals = false;
+        let mut chars = format.chars().peekable();
+        while let Some(char) = chars.next() {
+            let literal = match char {
+                '0' | '#' if parsed.exponent.is_none() => {
+                    parsed.number = true;
+                    match (decimals, char) {
+                        (false, '0') => parsed.integer_digits += 1,
+                        (false, _) => {}
+                        (true, '0') => {
+                            parsed.min_decimals = parsed.max_decimals + 1;
+                            parsed.max_decimals += 1;
+                        }
+                        (true, _) => parsed.max_decimals += 1,
+                    }
+                    continue;
+                }
+                '.' if !decimals && parsed.exponent.is_none() => {
+                    decimals = true;
+                    parsed.number = true;
+                    continue;
+                }
+                ',' if parsed.number && !decimals && matches!(chars.peek(), Some('0' | '#')) => {
+                    parsed.grouping = true;
+                    continue;
+                }
+                'E' | 'e' if parsed.number && matches!(chars.peek(), Some('+' | '-')) => {
+                    let plus = chars.next() == Some('+');
+                    let mut digits = 0;
+                    while chars.next_if(|char| matches!(char, '0' | '#')).is_some() {
+                        digits += 1;
+                    }
+                    parsed.exponent = Some((plus, digits.max(1)));
+                    continue;
+                }
+                '"' => chars.by_ref().take_while(|char| *char != '"').collect(),
+                '\\' => chars.next().map(String::from).unwrap_or_default(),
+                '%' => {
+                    parsed.percent = true;
+                    String::from('%')
+                }
+                char => String::from(char),
+            };
+            if parsed.number {
+                parsed.suffix.push_str(&literal);
+            } else {
+                parsed.prefix.push_str(&literal);
+            }
+        }
+        parsed
+    }
+
+    /// Formats the absolute value of a number, preceded by a minus sign if `signed` is set,
+    /// the number is negative and it doesn't round to zero.
+    fn format(&self, number: &BigDecimal, signed: bool) -> String {
+        let negative = signed && number.is_negative();
+        let mut number = number.abs();
+        if self.percent {
+            number *= BigDecimal::from(100);
+        }
+        if !self.number {
+            return format!("{}{}", self.prefix, self.suffix);
+        }
+
+        let mut exponent = 0;
+        if self.exponent.is_some() && !number.is_zero() {
+            // scale the number so that it has a single digit before the decimal point
+            let (digits, scale) = number.as_bigint_and_exponent();
+            exponent = digits.to_string().len() as i64 - 1 - scale;
+            number *= BigDecimal::new(1.into(), exponent);
+            if round_to(&number, self.max_decimals as i64, Rounding::HalfUp) >= BigDecimal::from(10)
+            {
+                number = number / BigDecimal::from(10);
+                exponent += 1;
+            }
+        }
+
+        let rounded = round_to(&number, self.max_decimals as i64, Rounding::HalfUp);
+        let mut formatted = String::new();
+        if negative && !rounded.is_zero() {
+            formatted.push('-');
+        }
+        formatted.push_str(&self.prefix);
+
+        let rounded = rounded.with_scale(self.max_decimals as i64).to_string();
+        let (integer, decimals) = rounded.split_once('.').unwrap_or((&rounded, ""));
+        let decimals = decimals.trim_end_matches('0');
+        let integer = integer.trim_start_matches('0');
+        let integer = format!("{:0>width$}", integer, width = self.integer_digits);
+        for (i, digit) in integer.chars().enumerate() {
+            if self.grouping && i > 0 && (integer.len() - i).is_multiple_of(3) {
+                formatted.push(',');
+            }
+            formatted.push(digit);
+        }
+        if self.max_decimals > 0 || !decimals.is_empty() {
+            formatted.push('.');
+        }
+        formatted.push_str(&format!("{:0<width$}", decimals, width = self.min_decimals));
+        if let Some((plus, digits)) = self.exponent {
+            let sign = match exponent {
+                exponent if exponent < 0 => "-",
+                _ if plus => "+",
+                _ => "",
+            };
+            formatted.push_str(&format!(
+                "E{}{:0>width$}",
+                sign,
+                exponent.abs(),
+                width = digits
+            ));
+        }
+        formatted.push_str(&self.suffix);
+        formatted
+    }
+}
+
+/// Formats a number according to a format as used by [`text`].
+fn format_number(number: &BigDecimal, format: &str) -> Result<String, Error> {
+    if format.eq_ignore_ascii_case("general") {
+        return Ok(Value::Number(number.clone()).to_text()?.into_owned());
+    }
+
+    // split into sections at `;`s outside of quotes
+    let mut sections = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (index, char) in format.char_indices() {
+        match char {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                sections.push(&format[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    sections.push(&format[start..]);
+    if sections.len() > 3 {
+        return Err(Error::Value);
+    }
+
+    // the section for negative numbers is used without a minus sign
+    let formatted = match sections.len() {
+        3 if number.is_zero() => Format::parse(sections[2]).format(number, false),
+        2 | 3 if number.is_negative() => Format::parse(sections[1]).format(number, false),
+        _ => Format::parse(sections[0]).format(number, true),
+    };
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::functions::testing::{number, string};
+    use crate::sheet::Sheet;
+
+    fn evaluate(formula: &str) -> Value {
+        let mut sheet = Sheet::with_standard_library();
+        let address = "A1".parse().unwrap();
+        sheet.set_cell(address, formula.to_string()).unwrap();
+        sheet.value(&address).into()
+    }
+
+    fn check(cases: &[(&str, Value)]) {
+        for (formula, expected) in cases {
+            assert_eq!(&evaluate(formula), expected, "formula {}", formula);
+        }
+    }
+
+    #[test]
+    fn test_concat() {
+        check(&[
+            (r#"="foo" & "bar""#, string("foobar")),
+            (r#"=1 & 2 + 3"#, string("15")),
+            (r#"=concat("a", 1.50, TRUE)"#, string("a1.5TRUE")),
+            (r#"=concatenate("a", "b")"#, string("ab")),
+            (r#"=concat()"#, string("")),
+            (r#"=concat("a", #N/A)"#, Value::Error(Error::NotAvailable)),
+        ]);
+    }
+
+    #[test]
+    fn test_len_left_right_mid() {
+        check(&[
+            (r#"=len("héllo")"#, number("5")),
+            (r#"=len("")"#, number("0")),
+            (r#"=len(123.40)"#, number("5")),
+            (r#"=left("héllo")"#, string("h")),
+            (r#"=left("héllo", 2)"#, string("hé")),
+            (r#"=left("héllo", 10)"#, string("héllo")),
+            (r#"=left("héllo", 0)"#, string("")),
+            (r#"=left("héllo", -1)"#, Value::Error(Error::Value)),
+            (r#"=right("héllo")"#, string("o")),
+            (r#"=right("日本語", 2)"#, string("本語")),
+            (r#"=right("héllo", 10)"#, string("héllo")),
+            (r#"=mid("日本語テキスト", 2, 3)"#, string("本語テ")),
+            (r#"=mid("héllo", 5, 3)"#, string("o")),
+            (r#"=mid("héllo", 6, 3)"#, string("")),
+            (r#"=mid("héllo", 0, 3)"#, Value::Error(Error::Value)),
+            (r#"=mid("héllo", 1)"#, Value::Error(Error::Value)),
+            (r#"=left("foo", "x")"#, Value::Error(Error::Value)),
+        ]);
+    }
+
+    #[test]
+    fn test_case() {
+        check(&[
+            (r#"=upper("straße")"#, string("STRASSE")),
+            (r#"=lower("ÄBC")"#, string("äbc")),
+            (
+                r#"=proper("hello wORLD, 2-way it's")"#,
+                string("Hello World, 2-Way It'S"),
+            ),
+            (r#"=upper(TRUE)"#, string("TRUE")),
+            (r#"=upper()"#, Value::Error(Error::Value)),
+        ]);
+    }
+
+    #[test]
+    fn test_trim() {
+        check(&[
+            (r#"=trim("  foo   bar ")"#, string("foo bar")),
+            (r#"=trim("")"#, string("")),
+        ]);
+    }
+
+    #[test]
+    fn test_substitute_replace() {
+        check(&[
+            (r#"=substitute("a-b-c", "-", "+")"#, string("a+b+c")),
+            (r#"=substitute("a-b-c", "-", "", 2)"#, string("a-bc")),
+            (r#"=substitute("a-b-c", "-", "+", 3)"#, string("a-b-c")),
+            (r#"=substitute("a-b-c", "", "+")"#, string("a-b-c")),
+            (r#"=substitute("äöü", "ö", "oe")"#, string("äoeü")),
+            (
+                r#"=substitute("a-b", "-", "+", 0)"#,
+                Value::Error(Error::Value),
+            ),
+            (r#"=replace("abcdef", 2, 3, "X")"#, string("aXef")),
+            (r#"=replace("äöü", 2, 1, "o")"#, string("äoü")),
+            (r#"=replace("abc", 4, 0, "d")"#, string("abcd")),
+            (r#"=replace("abc", 10, 1, "d")"#, string("abcd")),
+            (r#"=replace("abc", 0, 1, "d")"#, Value::Error(Error::Value)),
+        ]);
+    }
+
+    #[test]
+    fn test_find_search() {
+        check(&[
+            (r#"=find("b", "abcb")"#, number("2")),
+            (r#"=find("b", "abcb", 3)"#, number("4")),
+            (r#"=find("B", "abcb")"#, Value::Error(Error::Value)),
+            (r#"=find("ü", "äöü")"#, number("3")),
+            (r#"=find("", "abc", 2)"#, number("2")),
+            (r#"=find("cd", "abc")"#, Value::Error(Error::Value)),
+            (r#"=find("a", "abc", 5)"#, Value::Error(Error::Value)),
+            (r#"=search("B", "abcb")"#, number("2")),
+            (r#"=search("b?b", "abcb")"#, number("2")),
+            (r#"=search("c*", "abcb")"#, number("3")),
+            (r#"=search("a*b", "xaxxb")"#, number("2")),
+            (r#"=search("~?", "a?b")"#, number("2")),
+            (r#"=search("~*", "a*b")"#, number("2")),
+            (r#"=search("Ö", "äöü")"#, number("2")),
+            (r#"=search("x?", "abx")"#, Value::Error(Error::Value)),
+            // patterns with many `*`s don't take exponential time
+            (
+                r#"=search("*a*a*a*a*a*a*b", rept("a", 60))"#,
+                Value::Error(Error::Value),
+            ),
+            (
+                r#"=search("a*a*a*a*a*a*b", rept("a", 60) & "b")"#,
+                number("1"),
+            ),
+            // nor does searching a long text
+            (
+                r#"=search("a*b", rept("a", 32767))"#,
+                Value::Error(Error::Value),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_pattern() {
+        let matches = |pattern: &str, text: &str| {
+            let text: Vec<_> = text.chars().collect();
+            Pattern::new(pattern).matches(&text)
+        };
+        let find = |pattern: &str, text: &str, start| {
+            let text: Vec<_> = text.chars().collect();
+            Pattern::new(pattern).find(&text, start)
+        };
+
+        assert!(matches("a*c", "abbc"));
+        assert!(!matches("a*c", "abbcd"));
+        assert!(matches("*", ""));
+        assert!(matches("a?*", "ab"));
+        assert!(!matches("a?*", "a"));
+        assert!(matches("*b*b", "abcb"));
+        assert!(!matches("*b*b", "abc"));
+        assert!(!matches("", "abc"));
+        assert!(!matches(&"*a".repeat(30), &"a".repeat(29)));
+
+        assert_eq!(find("a*c", "abbcd", 0), Some(0));
+        assert_eq!(find("a?c", "aabc", 0), Some(1));
+        assert_eq!(find("b*d", "abcbd", 2), Some(3));
+        assert_eq!(find("*c", "abc", 1), Some(1));
+        assert_eq!(find("", "abc", 3), Some(3));
+        assert_eq!(find("c", "abc", 4), None);
+        assert_eq!(find("a*b", &"a".repeat(32767), 0), None);
+    }
+
+    #[test]
+    fn test_rept() {
+        check(&[
+            (r#"=rept("ab", 3)"#, string("ababab")),
+            (r#"=rept("ab", 0)"#, string("")),
+            (r#"=rept("ab", -1)"#, Value::Error(Error::Value)),
+            (r#"=rept("ab", 20000)"#, Value::Error(Error::Value)),
+        ]);
+    }
+
+    #[test]
+    fn test_max_length() {
+        let long = r#"rept("ab", 16383)"#;
+        check(&[
+            (&format!("=len({})", long), number("32766")),
+            (
+                &format!(r#"=substitute({}, "a", "xyz")"#, long),
+                Value::Error(Error::Value),
+            ),
+            (
+                &format!(r#"=len(substitute({}, "a", "x"))"#, long),
+                number("32766"),
+            ),
+            (
+                &format!(r#"=substitute({}, "a", "xyz", 1)"#, long),
+                Value::Error(Error::Value),
+            ),
+            (
+                &format!(r#"=replace({}, 1, 1, rept("x", 1000))"#, long),
+                Value::Error(Error::Value),
+            ),
+            (
+                &format!("=concat({}, {})", long, long),
+                Value::Error(Error::Value),
+            ),
+            (
+                &format!(r#"=len(textjoin("", TRUE, {}, "x"))"#, long),
+                number("32767"),
+            ),
+            (
+                &format!(r#"=textjoin("-", TRUE, {}, {})"#, long, long),
+                Value::Error(Error::Value),
+            ),
+            (&format!("={} & {}", long, long), Value::Error(Error::Value)),
+        ]);
+    }
+
+    #[test]
+    fn test_text() {
+        check(&[
+            (r#"=text(1234.567, "0.00")"#, string("1234.57")),
+            (r##"=text(1234.567, "#,##0.00")"##, string("1,234.57")),
+            (r##"=text(1234567, "#,##0")"##, string("1,234,567")),
+            (r##"=text(0.5, "0%")"##, string("50%")),
+            (r##"=text(0.1234, "0.0%")"##, string("12.3%")),
+            (r##"=text(5, "000")"##, string("005")),
+            (r##"=text(0.5, "#.##")"##, string(".5")),
+            (r#"=text(1.5, "0.0#")"#, string("1.5")),
+            (r#"=text(1.555, "0.0#")"#, string("1.56")),
+            (r#"=text(-1.5, "0")"#, string("-2")),
+            (r#"=text(-0.1, "0")"#, string("0")),
+            (r#"=text(12345, "0.00E+00")"#, string("1.23E+04")),
+            (r#"=text(0.00012, "0.0E+0")"#, string("1.2E-4")),
+            (r#"=text(9.99, "0.0E+00")"#, string("1.0E+01")),
+            (r#"=text(5, "$0.00")"#, string("$5.00")),
+            (r#"=text(-5, "$0.00")"#, string("-$5.00")),
+            (r#"=text(3, "0 \"items\"")"#, string("3 items")),
+            (r#"=text(-0.1, "0 \"in 2024\"")"#, string("0 in 2024")),
+            (r#"=text(-3, "0;(0);zero")"#, string("(3)")),
+            (r#"=text(0, "0;(0);zero")"#, string("zero")),
+            (r#"=text(1.50, "General")"#, string("1.5")),
+            (r#"=text("foo", "0.00")"#, string("foo")),
+            (r#"=text(1, "0;0;0;0")"#, Value::Error(Error::Value)),
+        ]);
+    }
+
+    #[test]
+    fn test_value() {
+        check(&[
+            (r#"=value("1.5")"#, number("1.5")),
+            (r#"=value(" -2e3 ")"#, number("-2000")),
+            (r#"=value("1e-2")"#, number("0.01")),
+            (r#"=value("50%")"#, number("0.5")),
+            (r#"=value(7)"#, number("7")),
+            (r#"=value("2024-02-29 12:00")"#, number("45351.5")),
+            (r#"=value("foo")"#, Value::Error(Error::Value)),
+            (r#"=value("")"#, Value::Error(Error::Value)),
+            (r#"=value(TRUE)"#, Value::Error(Error::Value)),
+        ]);
+    }
+
+    #[test]
+    fn test_exact() {
+        check(&[
+            (r#"=exact("Foo", "Foo")"#, Value::Boolean(true)),
+            (r#"=exact("Foo", "foo")"#, Value::Boolean(false)),
+            (r#"=exact(1, "1")"#, Value::Boolean(true)),
+        ]);
+    }
+
+    #[test]
+    fn test_textjoin_split() {
+        let mut sheet = Sheet::with_standard_library();
+        for (address, input) in [("A1", "\"a\""), ("A2", ""), ("A3", "3")] {
+            sheet
+                .set_cell(address.parse().unwrap(), input.to_string())
+                .unwrap();
+        }
+        let address = "B1".parse().unwrap();
+        let mut evaluate = |formula: &str| {
+            sheet.set_cell(address, formula.to_string()).unwrap();
+            Value::from(sheet.value(&address))
+        };
+
+        assert_eq!(evaluate(r#"=textjoin(", ", TRUE, A1:A3)"#), string("a, 3"));
+        assert_eq!(
+            evaluate(r#"=textjoin(", ", FALSE, A1:A3, "x")"#),
+            string("a, , 3, x")
+        );
+        assert_eq!(
+            evaluate(r#"=textjoin(", ", TRUE)"#),
+            Value::Error(Error::Value)
+        );
+        assert_eq!(evaluate(r#"=counta(split("a,b,,c", ","))"#), number("4"));
+        assert_eq!(
+            evaluate(r#"=textjoin("+", TRUE, split("a,b,,c", ","))"#),
+            string("a+b+c")
+        );
+        assert_eq!(evaluate(r#"=split("abc", ",")"#), string("abc"));
+        assert_eq!(evaluate(r#"=split("abc", "")"#), Value::Error(Error::Value));
+    }
+}
@@ -0,0 +1,144 @@
+//! The undo/redo history of cell edits.
+//!
+//! Each step of the history is one edit operation, such as setting a single cell or pasting a range,
+//! and records the previous and new input of every cell it touched.
+
+use std::collections::VecDeque;
+
+use crate::formula::Formula;
+
+/// The number of steps that are kept by default
+pub const DEFAULT_DEPTH: usize = 100;
+
+/// The change of a single cell's input and formula.
+/// `K` identifies the cell, e.g. by its address.
+#[derive(Clone, Debug)]
+pub(crate) struct Edit<K> {
+    pub(crate) cell: K,
+    pub(crate) before: (String, Formula),
+    pub(crate) after: (String, Formula),
+}
+
+#[derive(Clone, Debug)]
+pub struct History<K> {
+    /// the steps that can be undone, oldest first
+    undo: VecDeque<Vec<Edit<K>>>,
+    /// the steps that can be redone, most recently undone last
+    redo: Vec<Vec<Edit<K>>>,
+    depth: usize,
+}
+
+impl<K> Default for History<K> {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEPTH)
+    }
+}
+
+impl<K> History<K> {
+    /// A history that keeps at most `depth` steps
+    pub fn new(depth: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            depth,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Changes the number of steps that are kept, forgetting the oldest steps if necessary.
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.undo.len() > depth {
+            self.undo.pop_front();
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forgets all steps
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Records a step, which makes all undone steps impossible to redo.
+    /// Edits that didn't change a cell's input are left out; a step without edits is not recorded.
+    pub(crate) fn record(&mut self, mut step: Vec<Edit<K>>) {
+        step.retain(|edit| edit.before.0 != edit.after.0);
+        if step.is_empty() || self.depth == 0 {
+            return;
+        }
+
+        self.redo.clear();
+        self.undo.push_back(step);
+        if self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Takes the most recent step for undoing it; it can then be redone.
+    /// Its edits need to be reverted in reverse order.
+    pub(crate) fn undo(&mut self) -> Option<&[Edit<K>]> {
+        let step = self.undo.pop_back()?;
+        self.redo.push(step);
+        self.redo.last().map(Vec::as_slice)
+    }
+
+    /// Takes the most recently undone step for redoing it; it can then be undone again.
+    /// Its edits need to be applied in order.
+    pub(crate) fn redo(&mut self) -> Option<&[Edit<K>]> {
+        let step = self.redo.pop()?;
+        self.undo.push_back(step);
+        self.undo.back().map(Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(cell: u32, before: &str, after: &str) -> Vec<Edit<u32>> {
+        let edit = |input: &str| (input.to_string(), input.parse().unwrap());
+        vec![Edit {
+            cell,
+            before: edit(before),
+            after: edit(after),
+        }]
+    }
+
+    #[test]
+    fn test_history() {
+        let mut history = History::new(2);
+        assert!(!history.can_undo());
+
+        history.record(step(1, "", "1"));
+        history.record(step(1, "1", "1"));
+        history.record(step(2, "", "2"));
+        history.record(step(3, "", "3"));
+
+        // only the last two steps are kept, and the step without changes wasn't recorded
+        assert_eq!(history.undo().unwrap()[0].cell, 3);
+        assert_eq!(history.undo().unwrap()[0].cell, 2);
+        assert!(history.undo().is_none());
+
+        assert_eq!(history.redo().unwrap()[0].cell, 2);
+        assert!(history.can_redo());
+
+        // a new step can't be followed by redoing older ones
+        history.record(step(4, "", "4"));
+        assert!(!history.can_redo());
+        assert_eq!(history.undo().unwrap()[0].cell, 4);
+
+        history.set_depth(0);
+        assert!(!history.can_undo());
+    }
+}
@@ -2,7 +2,13 @@
 
 pub mod address;
 pub mod cell;
+pub mod csv;
+pub mod datetime;
+pub mod file;
 pub mod formula;
+pub mod functions;
+pub mod history;
 pub mod parser;
 pub mod sheet;
 pub mod value;
+pub mod workbook;
@@ -1,24 +1,32 @@
 //! Parsers for different elements that can be put into a cell
 
+pub mod datetime;
 mod error;
 mod formula;
 mod identifier;
-mod number;
+pub mod number;
 pub mod range;
+mod sheet;
+pub mod signature;
 mod string;
+mod syntax;
 
 use nom::branch::alt;
-use nom::character::complete::space0;
-use nom::combinator::{all_consuming, eof, map};
-use nom::error::ParseError;
-use nom::sequence::delimited;
+use nom::character::complete::{char, space0};
+use nom::combinator::{all_consuming, eof, map, opt};
+use nom::error::{ErrorKind, ParseError};
+use nom::sequence::{delimited, pair, preceded};
 use nom::{AsChar, Err, InputLength, InputTakeAtPosition};
 use nom::{IResult, Parser};
 
+use crate::formula::expression::Expression;
+use crate::formula::signature::Signature;
 use crate::formula::Formula;
 use crate::value::Value;
 
 pub use self::error::*;
+pub use self::sheet::format_sheet_name;
+use self::syntax::{expect, token, PResult, SyntaxError};
 
 fn parse_complete<I, O, E: ParseError<I>, F>(f: F, input: I) -> Result<O, Err<E>>
 where
@@ -31,13 +39,44 @@ where
 }
 
 pub fn parse_cell_complete(input: &str) -> Result<Formula, ParseFormulaError> {
-    parse_complete(parse_cell, input).map_err(|_| ParseFormulaError::Invalid)
+    let otherwise = if input.trim_start().starts_with('=') {
+        "an operator or the end of the formula"
+    } else {
+        "the end of the value"
+    };
+    parse_complete(parse_cell, input).map_err(|error| syntax_error(input, error, otherwise))
 }
 
-pub fn parse_cell(input: &str) -> IResult<&str, Formula> {
+/// Parses the definition of a name: an expression such as `$B$1`, `A1:A12` or `0.19`, optionally preceded by `=`.
+pub fn parse_name_definition_complete(input: &str) -> Result<Expression, ParseFormulaError> {
+    let definition = preceded(opt(pair(char('='), space0)), formula::parse_expression);
+    parse_complete(definition, input)
+        .map_err(|error| syntax_error(input, error, "an operator or the end of the definition"))
+}
+
+/// Converts an error of the formula parser on `input`. `otherwise` is reported as expected if the
+/// parser did not say what it expected, e.g. when input is left over after a complete formula.
+fn syntax_error(input: &str, error: Err<SyntaxError>, otherwise: &str) -> ParseFormulaError {
+    match error {
+        Err::Error(error) | Err::Failure(error) => error.into_parse_error(input, otherwise),
+        // only streaming parsers are incomplete, and the formula parsers aren't
+        Err::Incomplete(_) => SyntaxError::from_error_kind(&input[input.len()..], ErrorKind::Eof)
+            .into_parse_error(input, otherwise),
+    }
+}
+
+pub fn parse_signature_complete(input: &str) -> Result<Signature, ParseSignatureError> {
+    parse_complete(signature::parse_signature, input).map_err(|_| ParseSignatureError::Invalid)
+}
+
+pub fn parse_cell(input: &str) -> PResult<'_, Formula> {
+    let value = expect(
+        "a number, a date, a boolean, text in quotes or a formula starting with '='",
+        token(parse_value),
+    );
     alt((
         map(formula::parse_formula, Formula::Formula),
-        map(parse_value, Formula::Literal),
+        map(value, Formula::Literal),
     ))(input)
 }
 
@@ -45,9 +84,12 @@ pub fn parse_cell(input: &str) -> IResult<&str, Formula> {
 pub fn parse_value(input: &str) -> IResult<&str, Value> {
     alt((
         map(eof, |_| Value::Empty),
+        // dates need to be tried before numbers, which they start with
+        map(datetime::parse_datetime, Value::DateTime),
         map(number::parse_number, Value::Number),
         // TODO plain entered strings with quotes & escaping or verbatim?
         map(string::parse_string, Value::String),
+        map(formula::parse_boolean, Value::Boolean),
     ))(input)
 }
 
@@ -67,6 +109,76 @@ mod tests {
             parse_value(" \"foo\" ").unwrap(),
             Value::String("foo".into())
         );
-        assert!(matches!(parse_value("x"), Err(_)));
+        assert_eq!(parse_value("True").unwrap(), Value::Boolean(true));
+        assert_eq!(
+            parse_value("2026-10-18").unwrap(),
+            Value::DateTime(46313.into())
+        );
+        assert_eq!(parse_value("2026").unwrap(), Value::Number(2026.into()));
+        assert!(parse_value("x").is_err());
+    }
+
+    #[test]
+    fn test_parse_cell_errors() {
+        let error = |s| parse_cell_complete(s).unwrap_err().to_string();
+
+        assert_eq!(
+            parse_cell_complete("=sum(1, 2").unwrap_err(),
+            ParseFormulaError {
+                offset: 9,
+                column: 10,
+                token: None,
+                expected: "')' to close call to sum started at column 5".into(),
+            }
+        );
+        assert_eq!(
+            error("=sum(1 2)"),
+            "column 8: expected ')' to close call to sum started at column 5, found '2'"
+        );
+        assert_eq!(
+            error("=(1 + 2"),
+            "column 8: expected ')' to close the parenthesis started at column 2, found the end of the input"
+        );
+        assert_eq!(
+            error("= 1 + * 2"),
+            "column 7: expected an operand after '+', found '*'"
+        );
+        assert_eq!(
+            error("=\"foo"),
+            "column 6: expected '\"' to close the text started at column 2, found the end of the input"
+        );
+        assert_eq!(
+            error("=A1 B1"),
+            "column 5: expected an operator or the end of the formula, found 'B1'"
+        );
+        assert_eq!(
+            error("="),
+            "column 2: expected an operand, found the end of the input"
+        );
+        assert_eq!(
+            error("hello"),
+            "column 1: expected a number, a date, a boolean, text in quotes or a formula starting with '=', found 'hello'"
+        );
+        assert_eq!(
+            error("12 apples"),
+            "column 4: expected the end of the value, found 'apples'"
+        );
+
+        // nesting is limited, instead of running out of stack
+        let nested =
+            |depth: usize, open: &str| format!("={}1{}", open.repeat(depth), ")".repeat(depth));
+        assert!(parse_cell_complete(&nested(64, "(")).is_ok());
+        assert_eq!(
+            error(&nested(65, "(")),
+            "column 67: expected at most 64 levels of nesting, as the formula is too deeply nested, found '1'"
+        );
+        assert!(parse_cell_complete(&nested(5000, "(")).is_err());
+        assert!(parse_cell_complete(&nested(5000, "abs(")).is_err());
+        assert!(parse_cell_complete(&format!("={}1", "-".repeat(5000))).is_err());
+
+        // columns count characters, offsets bytes
+        let error = parse_cell_complete("=\"ä\" & ä)").unwrap_err();
+        assert_eq!((error.offset, error.column), (10, 9));
+        assert_eq!(error.token.as_deref(), Some(")"));
     }
 }
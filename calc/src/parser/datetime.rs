@@ -0,0 +1,98 @@
+//! Parsing of ISO 8601 dates and times such as `2024-02-29`, `13:30` or `2024-02-29T13:30:00`
+//! into serial numbers (see [`crate::datetime`]).
+
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveTime};
+use nom::branch::alt;
+use nom::bytes::complete::take_while_m_n;
+use nom::character::complete::{char, one_of};
+use nom::combinator::{map, map_opt, map_res, opt};
+use nom::sequence::{preceded, tuple};
+use nom::IResult;
+
+use crate::datetime::{date_serial, time_serial, MAX_SERIAL};
+
+fn digits(min: usize, max: usize) -> impl FnMut(&str) -> IResult<&str, u32> {
+    move |input| {
+        map_res(
+            take_while_m_n(min, max, |c: char| c.is_ascii_digit()),
+            str::parse,
+        )(input)
+    }
+}
+
+/// Parses a date such as `2024-02-29`, resulting in its serial number
+fn date(input: &str) -> IResult<&str, i64> {
+    let parts = tuple((
+        digits(4, 4),
+        preceded(char('-'), digits(1, 2)),
+        preceded(char('-'), digits(1, 2)),
+    ));
+    map_opt(parts, |(year, month, day)| {
+        let date = NaiveDate::from_ymd_opt(year as i32, month, day)?;
+        let serial = date_serial(date);
+        (0..=MAX_SERIAL).contains(&serial).then_some(serial)
+    })(input)
+}
+
+/// Parses a time such as `13:30` or `13:30:00`
+fn time(input: &str) -> IResult<&str, NaiveTime> {
+    let parts = tuple((
+        digits(1, 2),
+        preceded(char(':'), digits(2, 2)),
+        opt(preceded(char(':'), digits(2, 2))),
+    ));
+    map_opt(parts, |(hour, minute, second)| {
+        NaiveTime::from_hms_opt(hour, minute, second.unwrap_or(0))
+    })(input)
+}
+
+/// Parses a date, a time, or a date and time separated by `T` or a space,
+/// resulting in the serial number
+pub fn parse_datetime(input: &str) -> IResult<&str, BigDecimal> {
+    alt((
+        map(
+            tuple((date, opt(preceded(one_of("T "), time)))),
+            |(date, time)| {
+                let date = BigDecimal::from(date);
+                match time {
+                    Some(time) => date + time_serial(time),
+                    None => date,
+                }
+            },
+        ),
+        map(time, time_serial),
+    ))(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::parse_complete;
+
+    #[test]
+    fn test_parse_datetime() {
+        let parse_datetime = |s| parse_complete(parse_datetime, s);
+        let number = |s: &str| s.parse::<BigDecimal>().unwrap();
+
+        assert_eq!(parse_datetime("2024-02-29").unwrap(), number("45351"));
+        assert_eq!(parse_datetime("2024-2-9").unwrap(), number("45331"));
+        assert_eq!(
+            parse_datetime("2024-02-29T12:00").unwrap(),
+            number("45351.5")
+        );
+        assert_eq!(
+            parse_datetime("2024-02-29 18:00:00").unwrap(),
+            number("45351.75")
+        );
+        assert_eq!(parse_datetime("6:00").unwrap(), number("0.25"));
+        assert_eq!(parse_datetime("13:30:00").unwrap(), number("0.5625"));
+        assert!(parse_datetime("2023-02-29").is_err());
+        assert!(parse_datetime("2024-13-01").is_err());
+        assert!(parse_datetime("1800-01-01").is_err());
+        assert!(parse_datetime("24:00").is_err());
+        assert!(parse_datetime("12:5").is_err());
+        assert!(parse_datetime("2024").is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use std::num::{IntErrorKind, ParseIntError};
 
+use serde::Serialize;
 use thiserror::Error;
 
 /// An error while parsind a column address such as "C" or "AA".
@@ -54,10 +55,40 @@ pub enum ParseCellAddressError {
     Invalid,
 }
 
-/// An error while parsind a column address such as "C" or "AA".
+/// An error while parsing a cell range such as "A1:C10".
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseCellRangeError {
+    #[error("parsed string was not a valid cell range")]
+    Invalid,
+}
+
+/// An error while parsing a cell's formula or value such as "=sum(1, 2".
+/// It tells where parsing failed, what was found there and what was expected instead.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize)]
+#[error("column {column}: expected {expected}, found {}", found(.token))]
+pub struct ParseFormulaError {
+    /// the byte offset into the parsed string
+    pub offset: usize,
+    /// the 1-based column, counted in characters
+    pub column: usize,
+    /// the text found at that position, or `None` at the end of the input
+    pub token: Option<String>,
+    /// what would have been accepted, e.g. "')' to close call to sum started at column 5"
+    pub expected: String,
+}
+
+fn found(token: &Option<String>) -> String {
+    match token {
+        Some(token) => format!("'{}'", token),
+        None => "the end of the input".to_string(),
+    }
+}
+
+/// An error while parsing a function signature such as "round(number: number, [digits: number]) -> number".
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
-pub enum ParseFormulaError {
-    #[error("parsed string was not a valid formula")]
+pub enum ParseSignatureError {
+    #[error("parsed string was not a valid function signature")]
     Invalid,
 }
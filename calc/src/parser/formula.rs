@@ -1,71 +1,302 @@
 use nom::branch::alt;
-use nom::bytes::complete::tag;
-use nom::character::complete::space0;
-use nom::character::streaming::char;
-use nom::combinator::{map, opt};
+use nom::bytes::complete::{tag, tag_no_case};
+use nom::character::complete::{char, satisfy, space0};
+use nom::combinator::{cut, map, map_opt, not, opt, value};
+use nom::error::ErrorKind;
 use nom::multi::many0;
-use nom::sequence::{delimited, preceded, separated_pair, terminated, tuple};
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
 use nom::IResult;
 
 use crate::formula::expression::Expression;
-use crate::value::Value;
+use crate::formula::operator::{BinaryOperator, UnaryOperator};
+use crate::value::{Error, Value};
 
 use super::identifier::parse_identifier;
 use super::number::parse_number;
-use super::range::parse_range;
+use super::range::{cell_reference, range_reference};
+use super::sheet::parse_sheet_name;
 use super::string::parse_string;
+use super::syntax::{expect, nested, token, unclosed, PResult, SyntaxError};
 
-pub fn parse_formula(input: &str) -> IResult<&str, Expression> {
-    preceded(tuple((tag("="), space0)), parse_expression)(input)
+/// Parses a formula such as `=A1 + 1`. Once the `=` is parsed, any error is unrecoverable.
+pub fn parse_formula(input: &str) -> PResult<'_, Expression> {
+    preceded(tuple((tag("="), space0)), cut(parse_expression))(input)
 }
 
-pub fn parse_expression(input: &str) -> IResult<&str, Expression> {
+/// Parses an expression, consisting of operands combined by operators.
+/// From lowest to highest precedence, the operators are:
+///
+/// - `=`, `<>`, `<`, `<=`, `>` and `>=`
+/// - `&`
+/// - `+` and `-` (binary)
+/// - `*` and `/`
+/// - `^`
+/// - `%` (postfix)
+/// - `+` and `-` (prefix), i.e. `-2^2` is `4`
+///
+/// All binary operators are left associative.
+pub fn parse_expression(input: &str) -> PResult<'_, Expression> {
+    parse_comparison(input)
+}
+
+/// Parses a sequence of operands separated by left-associative binary operators.
+/// An operator must be followed by an operand, otherwise parsing fails.
+fn parse_binary<'a, O, F>(
+    mut operator: O,
+    mut operand: F,
+) -> impl FnMut(&'a str) -> PResult<'a, Expression>
+where
+    O: FnMut(&'a str) -> PResult<'a, BinaryOperator>,
+    F: FnMut(&'a str) -> PResult<'a, Expression>,
+{
+    move |input| {
+        let (mut input, mut expression) = operand(input)?;
+
+        // repeatedly try to parse another operator; stop at the first failure
+        while let Ok((rest, operator)) = delimited(space0, &mut operator, space0)(input) {
+            let (rest, right) = operand(rest).map_err(|error| {
+                let error = error.map(|error| {
+                    if error.at.len() == rest.len() {
+                        SyntaxError::new(rest, format!("an operand after '{}'", operator), None)
+                    } else {
+                        error
+                    }
+                });
+                match error {
+                    nom::Err::Error(error) => nom::Err::Failure(error),
+                    error => error,
+                }
+            })?;
+
+            input = rest;
+            expression = Expression::Binary {
+                operator,
+                left: Box::new(expression),
+                right: Box::new(right),
+            };
+        }
+
+        Ok((input, expression))
+    }
+}
+
+fn parse_comparison(input: &str) -> PResult<'_, Expression> {
+    // two-character operators need to be tried first
+    let operator = alt((
+        value(BinaryOperator::LessEqual, tag("<=")),
+        value(BinaryOperator::NotEqual, tag("<>")),
+        value(BinaryOperator::GreaterEqual, tag(">=")),
+        value(BinaryOperator::Less, char('<')),
+        value(BinaryOperator::Greater, char('>')),
+        value(BinaryOperator::Equal, char('=')),
+    ));
+    parse_binary(operator, parse_concatenation)(input)
+}
+
+fn parse_concatenation(input: &str) -> PResult<'_, Expression> {
+    let operator = value(BinaryOperator::Concatenate, char('&'));
+    parse_binary(operator, parse_additive)(input)
+}
+
+fn parse_additive(input: &str) -> PResult<'_, Expression> {
+    let operator = alt((
+        value(BinaryOperator::Add, char('+')),
+        value(BinaryOperator::Subtract, char('-')),
+    ));
+    parse_binary(operator, parse_multiplicative)(input)
+}
+
+fn parse_multiplicative(input: &str) -> PResult<'_, Expression> {
+    let operator = alt((
+        value(BinaryOperator::Multiply, char('*')),
+        value(BinaryOperator::Divide, char('/')),
+    ));
+    parse_binary(operator, parse_power)(input)
+}
+
+fn parse_power(input: &str) -> PResult<'_, Expression> {
+    let operator = value(BinaryOperator::Power, char('^'));
+    parse_binary(operator, parse_percent)(input)
+}
+
+fn parse_percent(input: &str) -> PResult<'_, Expression> {
+    let percent = preceded(space0, char('%'));
+
+    map(
+        pair(parse_prefix, many0(percent)),
+        |(mut expression, percents)| {
+            for _ in percents {
+                expression = Expression::Unary {
+                    operator: UnaryOperator::Percent,
+                    operand: Box::new(expression),
+                };
+            }
+            expression
+        },
+    )(input)
+}
+
+fn parse_prefix(input: &str) -> PResult<'_, Expression> {
+    let operator = alt((
+        value(UnaryOperator::Plus, char('+')),
+        value(UnaryOperator::Minus, char('-')),
+    ));
+
     alt((
-        map(parse_range, Expression::Reference),
-        map(parse_literal, Expression::Literal),
-        parse_call,
+        map(
+            separated_pair(operator, space0, cut(nested(parse_prefix))),
+            |(operator, operand)| Expression::Unary {
+                operator,
+                operand: Box::new(operand),
+            },
+        ),
+        parse_primary,
     ))(input)
 }
 
+fn parse_primary(input: &str) -> PResult<'_, Expression> {
+    let parse_primary = alt((
+        parse_group,
+        token(parse_reference),
+        // calls come before literals so that e.g. `true()` is not a boolean followed by garbage
+        parse_call,
+        parse_text,
+        map(token(parse_literal), Expression::Literal),
+        // any other identifier is a name; this comes last so that e.g. `TRUE` is a boolean
+        map(token(parse_identifier), |name| {
+            Expression::Name(name.to_string())
+        }),
+    ));
+    expect("an operand", parse_primary)(input)
+}
+
+/// Parses an expression in parentheses.
+fn parse_group(input: &str) -> PResult<'_, Expression> {
+    let (rest, _) = pair(char('('), space0)(input)?;
+    let (rest, expression) = cut(nested(parse_expression))(rest)?;
+    let (rest, _) = space0(rest)?;
+
+    match char::<_, SyntaxError>(')')(rest) {
+        Ok((rest, _)) => Ok((rest, expression)),
+        Err(_) => Err(unclosed(
+            rest,
+            "')' to close the parenthesis".to_string(),
+            input,
+        )),
+    }
+}
+
+/// Parses a string literal, reporting a missing closing quote.
+fn parse_text(input: &str) -> PResult<'_, Expression> {
+    char('"')(input)?;
+
+    match token(parse_string)(input) {
+        Ok((rest, string)) => Ok((rest, Expression::Literal(Value::String(string)))),
+        Err(_) => Err(unclosed(
+            &input[input.len()..],
+            "'\"' to close the text".to_string(),
+            input,
+        )),
+    }
+}
+
+/// Parses a cell or range reference, optionally qualified by a sheet name as in `Sheet2!A1`.
+/// A reference must not be followed by more characters of an identifier or by parentheses,
+/// so that names such as `Q1Total` and calls such as `log10(2)` are not taken as references.
+fn parse_reference(input: &str) -> IResult<&str, Expression> {
+    fn sheet(input: &str) -> IResult<&str, Option<String>> {
+        opt(terminated(parse_sheet_name, char('!')))(input)
+    }
+
+    let end = not(alt((
+        satisfy(|c| c.is_alphanumeric() || c == '_' || c == '.'),
+        preceded(space0, char('(')),
+    )));
+
+    terminated(
+        alt((
+            map(pair(sheet, range_reference), |(sheet, reference)| {
+                Expression::Range { sheet, reference }
+            }),
+            map(pair(sheet, cell_reference), |(sheet, reference)| {
+                Expression::Reference { sheet, reference }
+            }),
+        )),
+        end,
+    )(input)
+}
+
 pub fn parse_literal(input: &str) -> IResult<&str, Value> {
     alt((
         map(parse_number, Value::Number),
         map(parse_string, Value::String),
+        map(parse_error, Value::Error),
+        map(parse_boolean, Value::Boolean),
     ))(input)
 }
 
-pub fn parse_call(input: &str) -> IResult<&str, Expression> {
-    let args = map(
-        // (arg ,)* arg?
-        tuple((
-            many0(terminated(
-                parse_expression,
-                delimited(space0, char(','), space0),
-            )),
-            opt(parse_expression),
-        )),
-        // combine the repeated args with the optional trailing arg
-        |(mut args, trailing_arg)| {
-            args.extend(trailing_arg);
-            args
-        },
-    );
-    let args = delimited(tuple((char('('), space0)), args, tuple((space0, char(')'))));
+/// Parses `TRUE` or `FALSE`, case-insensitively.
+pub fn parse_boolean(input: &str) -> IResult<&str, bool> {
+    map_opt(parse_identifier, |identifier| {
+        if identifier.eq_ignore_ascii_case("true") {
+            Some(true)
+        } else if identifier.eq_ignore_ascii_case("false") {
+            Some(false)
+        } else {
+            None
+        }
+    })(input)
+}
 
-    map(
-        separated_pair(parse_identifier, space0, args),
-        |(name, arguments)| {
+/// Parses an error literal such as `#N/A`; error codes are case-insensitive.
+pub fn parse_error(input: &str) -> IResult<&str, Error> {
+    for error in Error::ALL {
+        if let Ok((rest, _)) = tag_no_case::<_, _, ()>(error.code())(input) {
+            return Ok((rest, error));
+        }
+    }
+    Err(nom::Err::Error(nom::error::Error::new(
+        input,
+        ErrorKind::Tag,
+    )))
+}
+
+/// Parses a function call such as `sum(A1:A3, 2)`; a trailing comma is allowed.
+/// Once the opening parenthesis is parsed, any error is unrecoverable.
+pub fn parse_call(input: &str) -> PResult<'_, Expression> {
+    let (rest, name) = token(parse_identifier)(input)?;
+    let (opened, _) = space0(rest)?;
+    let (mut rest, _) = pair(char('('), space0)(opened)?;
+
+    let mut arguments = Vec::new();
+    loop {
+        // after the opening parenthesis, an argument or a comma, the call may end
+        if let Ok((rest, _)) = char::<_, SyntaxError>(')')(rest) {
             let name = name.to_string();
-            Expression::Call { name, arguments }
-        },
-    )(input)
+            return Ok((rest, Expression::Call { name, arguments }));
+        }
+
+        let (after, argument) = cut(nested(parse_expression))(rest)?;
+        arguments.push(argument);
+        let (after, _) = space0(after)?;
+
+        match pair(char::<_, SyntaxError>(','), space0)(after) {
+            Ok((after, _)) => rest = after,
+            // without a comma, the call has to end here
+            Err(_) if after.starts_with(')') => rest = after,
+            Err(_) => {
+                let description = format!("')' to close call to {}", name);
+                return Err(unclosed(after, description, opened));
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use crate::address::CellAddress;
+    use crate::address::{CellAddress, CellRange, CellReference};
     use crate::parser::parse_complete;
     use crate::value::Value;
 
@@ -82,7 +313,50 @@ mod tests {
             Value::String("foo".into())
         );
 
+        assert_eq!(
+            parse_literal(" #n/a ").unwrap(),
+            Value::Error(Error::NotAvailable)
+        );
+        assert_eq!(
+            parse_literal("#DIV/0!").unwrap(),
+            Value::Error(Error::DivisionByZero)
+        );
+
+        assert_eq!(parse_literal("TRUE").unwrap(), Value::Boolean(true));
+        assert_eq!(parse_literal(" false ").unwrap(), Value::Boolean(false));
+
         assert!(parse_literal("foo").is_err());
+        assert!(parse_literal("#FOO").is_err());
+        assert!(parse_literal("trueish").is_err());
+    }
+
+    #[test]
+    fn test_parse_name() {
+        let parse_expression = |s| parse_complete(parse_expression, s);
+
+        for (input, name) in [
+            ("TaxRate", "TaxRate"),
+            (" Q1Total ", "Q1Total"),
+            ("a1b", "a1b"),
+        ] {
+            assert_eq!(
+                parse_expression(input).unwrap(),
+                Expression::Name(name.to_string())
+            );
+        }
+        assert!(matches!(
+            parse_expression("log10(100)").unwrap(),
+            Expression::Call { name, .. } if name == "log10",
+        ));
+        assert!(matches!(
+            parse_expression("A1").unwrap(),
+            Expression::Reference { .. }
+        ));
+        assert!(matches!(
+            parse_expression("true").unwrap(),
+            Expression::Literal(Value::Boolean(true))
+        ));
+        assert!(parse_expression("Tax Rate").is_err());
     }
 
     #[test]
@@ -91,7 +365,7 @@ mod tests {
 
         assert!(matches!(
             parse_call(" foo ( ) ").unwrap(),
-            Expression::Call { name, arguments } if name == "foo" && arguments.len() == 0,
+            Expression::Call { name, arguments } if name == "foo" && arguments.is_empty(),
         ));
         assert!(matches!(
             parse_call(" foo ( 1 ) ").unwrap(),
@@ -116,6 +390,132 @@ mod tests {
         assert!(parse_call("foo(,1)").is_err());
     }
 
+    #[test]
+    fn test_parse_operators() {
+        use BinaryOperator::*;
+
+        let parse_expression = |s| parse_complete(parse_expression, s);
+
+        fn is_binary(expression: &Expression, op: BinaryOperator) -> bool {
+            matches!(expression, Expression::Binary { operator, .. } if *operator == op)
+        }
+
+        // precedence: 1 + (2 * 3)
+        match parse_expression(" 1 + 2 * 3 ").unwrap() {
+            Expression::Binary {
+                operator: Add,
+                right,
+                ..
+            } => assert!(is_binary(&right, Multiply)),
+            expression => panic!("unexpected expression {:?}", expression),
+        }
+
+        // parentheses: (1 + 2) * 3
+        match parse_expression("(1+2)*3").unwrap() {
+            Expression::Binary {
+                operator: Multiply,
+                left,
+                ..
+            } => assert!(is_binary(&left, Add)),
+            expression => panic!("unexpected expression {:?}", expression),
+        }
+
+        // left associativity: (1 - 2) - 3 and (2 ^ 3) ^ 2
+        match parse_expression("1-2-3").unwrap() {
+            Expression::Binary {
+                operator: Subtract,
+                left,
+                ..
+            } => assert!(is_binary(&left, Subtract)),
+            expression => panic!("unexpected expression {:?}", expression),
+        }
+        match parse_expression("2^3^2").unwrap() {
+            Expression::Binary {
+                operator: Power,
+                left,
+                ..
+            } => assert!(is_binary(&left, Power)),
+            expression => panic!("unexpected expression {:?}", expression),
+        }
+
+        // prefix operators bind more tightly than `^`: (-2) ^ 2
+        match parse_expression("-2^2").unwrap() {
+            Expression::Binary {
+                operator: Power,
+                left,
+                ..
+            } => assert!(matches!(
+                *left,
+                Expression::Unary {
+                    operator: UnaryOperator::Minus,
+                    ..
+                }
+            )),
+            expression => panic!("unexpected expression {:?}", expression),
+        }
+
+        // percent binds more tightly than `^`: 2 ^ (50%)
+        match parse_expression("2^50%").unwrap() {
+            Expression::Binary {
+                operator: Power,
+                right,
+                ..
+            } => assert!(matches!(
+                *right,
+                Expression::Unary {
+                    operator: UnaryOperator::Percent,
+                    ..
+                }
+            )),
+            expression => panic!("unexpected expression {:?}", expression),
+        }
+
+        // comparisons bind least tightly: (1 + 2) >= (3 * 4)
+        match parse_expression("1 + 2 >= 3 * 4").unwrap() {
+            Expression::Binary {
+                operator: GreaterEqual,
+                left,
+                right,
+            } => {
+                assert!(is_binary(&left, Add));
+                assert!(is_binary(&right, Multiply));
+            }
+            expression => panic!("unexpected expression {:?}", expression),
+        }
+        // concatenation binds less tightly than arithmetic, but more than comparisons
+        match parse_expression("1 + 2 & 3 = \"33\"").unwrap() {
+            Expression::Binary {
+                operator: Equal,
+                left,
+                ..
+            } => match *left {
+                Expression::Binary {
+                    operator: Concatenate,
+                    left,
+                    ..
+                } => assert!(is_binary(&left, Add)),
+                expression => panic!("unexpected expression {:?}", expression),
+            },
+            expression => panic!("unexpected expression {:?}", expression),
+        }
+        for (input, operator) in [
+            ("1=2", Equal),
+            ("1<>2", NotEqual),
+            ("1<2", Less),
+            ("1<=2", LessEqual),
+            ("1>2", Greater),
+        ] {
+            assert!(is_binary(&parse_expression(input).unwrap(), operator));
+        }
+
+        assert!(parse_expression("A1 * -foo(B2, 2%)").is_ok());
+        assert!(parse_expression("true()").is_ok());
+        assert!(parse_expression("1 +").is_err());
+        assert!(parse_expression("(1").is_err());
+        assert!(parse_expression("1)").is_err());
+        assert!(parse_expression("* 1").is_err());
+    }
+
     #[test]
     fn test_parse_formula() {
         fn cell_address(row: u32, col: u32) -> CellAddress {
@@ -134,11 +534,27 @@ mod tests {
         ));
         assert!(matches!(
             parse_formula(" = A1 ").unwrap(),
-            Expression::Reference(address) if address == cell_address(1, 1),
+            Expression::Reference { sheet: None, reference } if reference == CellReference::relative(cell_address(1, 1)),
+        ));
+        assert!(matches!(
+            parse_formula(" = $A1 ").unwrap(),
+            Expression::Reference { sheet: None, reference } if reference == CellReference::new(cell_address(1, 1), false, true),
+        ));
+        assert!(matches!(
+            parse_formula(" = A1:B2 ").unwrap(),
+            Expression::Range { sheet: None, reference } if reference.range() == CellRange::new(cell_address(1, 1), cell_address(2, 2)),
+        ));
+        assert!(matches!(
+            parse_formula(" = Sheet2!A1 ").unwrap(),
+            Expression::Reference { sheet: Some(sheet), reference } if sheet == "Sheet2" && reference.address() == cell_address(1, 1),
+        ));
+        assert!(matches!(
+            parse_formula(" = 'My Sheet'!A1:B2 ").unwrap(),
+            Expression::Range { sheet: Some(sheet), reference } if sheet == "My Sheet" && reference.range() == CellRange::new(cell_address(1, 1), cell_address(2, 2)),
         ));
         assert!(matches!(
             parse_formula(" = foo ( ) ").unwrap(),
-            Expression::Call { name, arguments } if name == "foo" && arguments.len() == 0,
+            Expression::Call { name, arguments } if name == "foo" && arguments.is_empty(),
         ));
 
         assert!(parse_literal(" = foo ").is_err());
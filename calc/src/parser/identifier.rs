@@ -1,16 +1,17 @@
-use nom::branch::alt;
-use nom::bytes::complete::tag;
-use nom::character::complete::{alpha1, alphanumeric1};
+use nom::bytes::complete::take_while;
+use nom::character::complete::satisfy;
 use nom::combinator::recognize;
-use nom::multi::many0;
-use nom::sequence::tuple;
+use nom::sequence::pair;
 use nom::IResult;
 
+/// Parses an identifier such as a function name. Identifiers may contain letters of any language,
+/// as in localized function names such as `zählenwenn`. After the first character, they may also contain digits
+/// and dots, as in `stdev.p`.
 pub fn parse_identifier(input: &str) -> IResult<&str, &str> {
-    let identifier_start = alt((alpha1, tag("_")));
-    let identifier_continue = alt((alphanumeric1, tag("_")));
+    let identifier_start = satisfy(|c| c.is_alphabetic() || c == '_');
+    let identifier_continue = take_while(|c: char| c.is_alphanumeric() || c == '_' || c == '.');
 
-    recognize(tuple((identifier_start, many0(identifier_continue))))(input)
+    recognize(pair(identifier_start, identifier_continue))(input)
 }
 
 #[cfg(test)]
@@ -29,8 +30,11 @@ mod tests {
         assert!(parse_identifier("f00").is_ok());
         assert!(parse_identifier("_f00").is_ok());
         assert!(parse_identifier("_00f").is_ok());
+        assert!(parse_identifier("stdev.p").is_ok());
+        assert!(parse_identifier("zählenwenn").is_ok());
         assert!(parse_identifier("").is_err());
         assert!(parse_identifier("00f").is_err());
+        assert!(parse_identifier(".foo").is_err());
         assert!(parse_identifier("").is_err());
     }
 }
@@ -1,12 +1,26 @@
-use nom::character::complete::{alpha1, digit1};
-use nom::combinator::{map, map_res};
-use nom::sequence::tuple;
+use nom::character::complete::{alpha1, char, digit1};
+use nom::combinator::{map, map_res, opt};
+use nom::sequence::{pair, preceded, separated_pair, tuple};
 use nom::IResult;
 
-use crate::address::{CellAddress, ColAddress, RowAddress};
+use crate::address::{
+    CellAddress, CellRange, CellReference, ColAddress, RangeReference, RowAddress,
+};
 use crate::parser::ParseRowAddressError;
 
-use super::{parse_complete, ParseCellAddressError, ParseColumnAddressError};
+use super::{parse_complete, ParseCellAddressError, ParseCellRangeError, ParseColumnAddressError};
+
+pub fn parse_cell_range_complete(input: &str) -> Result<CellRange, ParseCellRangeError> {
+    parse_complete(parse_range, input).map_err(|_| ParseCellRangeError::Invalid)
+}
+
+pub fn parse_range_reference_complete(input: &str) -> Result<RangeReference, ParseCellRangeError> {
+    parse_complete(range_reference, input).map_err(|_| ParseCellRangeError::Invalid)
+}
+
+pub fn parse_cell_reference_complete(input: &str) -> Result<CellReference, ParseCellAddressError> {
+    parse_complete(cell_reference, input).map_err(|_| ParseCellAddressError::Invalid)
+}
 
 pub fn parse_cell_address_complete(input: &str) -> Result<CellAddress, ParseCellAddressError> {
     parse_complete(cell_address, input).map_err(|_| ParseCellAddressError::Invalid)
@@ -20,8 +34,34 @@ pub fn parse_row_address_complete(input: &str) -> Result<RowAddress, ParseRowAdd
     parse_complete(row_address, input).map_err(|_| ParseRowAddressError::InvalidCharacter)
 }
 
-pub fn parse_range(input: &str) -> IResult<&str, CellAddress> {
-    cell_address(input)
+/// parses a range such as `A1:C10`; a single cell address such as `A1` is also accepted
+pub fn parse_range(input: &str) -> IResult<&str, CellRange> {
+    map(
+        pair(cell_address, opt(preceded(char(':'), cell_address))),
+        |(corner, opposite)| CellRange::new(corner, opposite.unwrap_or(corner)),
+    )(input)
+}
+
+/// parses a range that may contain absolute markers, such as `$A$1:C10`
+pub fn range_reference(input: &str) -> IResult<&str, RangeReference> {
+    map(
+        separated_pair(cell_reference, char(':'), cell_reference),
+        |(corner, opposite)| RangeReference::new(corner, opposite),
+    )(input)
+}
+
+/// parses a cell address that may contain absolute markers, such as `$A$1`, `A$1` or `$A1`
+pub fn cell_reference(input: &str) -> IResult<&str, CellReference> {
+    fn marker(input: &str) -> IResult<&str, bool> {
+        map(opt(char('$')), |marker| marker.is_some())(input)
+    }
+
+    map(
+        tuple((marker, col_address, marker, row_address)),
+        |(col_absolute, c, row_absolute, r)| {
+            CellReference::new(CellAddress::new(r, c), row_absolute, col_absolute)
+        },
+    )(input)
 }
 
 pub fn cell_address(input: &str) -> IResult<&str, CellAddress> {
@@ -96,6 +136,65 @@ mod tests {
     }
 
     #[test]
+    fn test_parse_cell_range() {
+        fn cell_range(start: &str, end: &str) -> CellRange {
+            CellRange::new(start.parse().unwrap(), end.parse().unwrap())
+        }
+
+        assert_eq!(
+            parse_cell_range_complete("A1:C10").unwrap(),
+            cell_range("A1", "C10")
+        );
+        assert_eq!(
+            parse_cell_range_complete("c10:a1").unwrap(),
+            cell_range("A1", "C10")
+        );
+        assert_eq!(
+            parse_cell_range_complete("B2:B2").unwrap(),
+            cell_range("B2", "B2")
+        );
+        assert_eq!(
+            parse_cell_range_complete("B2").unwrap(),
+            cell_range("B2", "B2")
+        );
+        assert!(parse_cell_range_complete("A1:").is_err());
+        assert!(parse_cell_range_complete("A1 : B2").is_err());
+    }
+
+    #[test]
+    fn test_parse_cell_reference() {
+        fn cell_reference(address: &str, row_absolute: bool, col_absolute: bool) -> CellReference {
+            CellReference::new(address.parse().unwrap(), row_absolute, col_absolute)
+        }
+
+        assert_eq!(
+            parse_cell_reference_complete("A1").unwrap(),
+            cell_reference("A1", false, false)
+        );
+        assert_eq!(
+            parse_cell_reference_complete("$A$1").unwrap(),
+            cell_reference("A1", true, true)
+        );
+        assert_eq!(
+            parse_cell_reference_complete("A$1").unwrap(),
+            cell_reference("A1", true, false)
+        );
+        assert_eq!(
+            parse_cell_reference_complete("$a1").unwrap(),
+            cell_reference("A1", false, true)
+        );
+        assert!(parse_cell_reference_complete("$$A1").is_err());
+        assert!(parse_cell_reference_complete("A1$").is_err());
+        assert!(parse_cell_reference_complete("$ A1").is_err());
+
+        // plain addresses and ranges don't accept markers
+        assert!(parse_cell_address_complete("$A$1").is_err());
+        assert!(parse_cell_range_complete("$A$1:B2").is_err());
+        assert!(parse_range_reference_complete("$A$1:B2").is_ok());
+    }
+
+    #[test]
+    #[allow(clippy::identity_op)]
     fn test_parse_col_address() {
         fn col_address(col: u32) -> ColAddress {
             ColAddress::new(col.try_into().unwrap())
@@ -0,0 +1,80 @@
+//! Sheet names, as used in references to other sheets such as `Sheet2!A1` or `'My Sheet'!A1:B3`.
+
+use nom::branch::alt;
+use nom::bytes::complete::{is_not, tag};
+use nom::character::complete::{alpha1, alphanumeric1, char};
+use nom::combinator::{map, recognize, value, verify};
+use nom::multi::{fold_many0, many0};
+use nom::sequence::{delimited, pair};
+use nom::IResult;
+
+/// Parses a sheet name that can be written without quotes:
+/// a letter or underscore, followed by letters, digits, underscores or dots.
+fn parse_unquoted_sheet_name(input: &str) -> IResult<&str, &str> {
+    let start = alt((alpha1, tag("_")));
+    let rest = alt((alphanumeric1, tag("_"), tag(".")));
+
+    recognize(pair(start, many0(rest)))(input)
+}
+
+/// Parses a sheet name in single quotes; a quote inside the name is written as two quotes.
+fn parse_quoted_sheet_name(input: &str) -> IResult<&str, String> {
+    let build_name = fold_many0(
+        alt((value("'", tag("''")), is_not("'"))),
+        String::new,
+        |mut name, fragment| {
+            name.push_str(fragment);
+            name
+        },
+    );
+
+    verify(
+        delimited(char('\''), build_name, char('\'')),
+        |name: &str| !name.is_empty(),
+    )(input)
+}
+
+pub fn parse_sheet_name(input: &str) -> IResult<&str, String> {
+    alt((
+        map(parse_unquoted_sheet_name, str::to_string),
+        parse_quoted_sheet_name,
+    ))(input)
+}
+
+/// Formats a sheet name so that it can be parsed by [`parse_sheet_name`], quoting it if necessary.
+pub fn format_sheet_name(name: &str) -> String {
+    match parse_unquoted_sheet_name(name) {
+        Ok(("", _)) => name.to_string(),
+        _ => format!("'{}'", name.replace('\'', "''")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::parse_complete;
+
+    #[test]
+    fn test_parse_sheet_name() {
+        let parse_sheet_name = |s| parse_complete(parse_sheet_name, s);
+
+        assert_eq!(parse_sheet_name("Sheet1").unwrap(), "Sheet1");
+        assert_eq!(parse_sheet_name("_data.2024").unwrap(), "_data.2024");
+        assert_eq!(parse_sheet_name("'My Sheet'").unwrap(), "My Sheet");
+        assert_eq!(parse_sheet_name("'It''s'").unwrap(), "It's");
+        assert_eq!(parse_sheet_name("'2024'").unwrap(), "2024");
+        assert!(parse_sheet_name("2024").is_err());
+        assert!(parse_sheet_name("My Sheet").is_err());
+        assert!(parse_sheet_name("''").is_err());
+        assert!(parse_sheet_name("'foo").is_err());
+    }
+
+    #[test]
+    fn test_format_sheet_name() {
+        assert_eq!(format_sheet_name("Sheet1"), "Sheet1");
+        assert_eq!(format_sheet_name("My Sheet"), "'My Sheet'");
+        assert_eq!(format_sheet_name("It's"), "'It''s'");
+        assert_eq!(format_sheet_name("2024"), "'2024'");
+    }
+}
@@ -0,0 +1,123 @@
+//! Parsing of function signatures such as `round(number: number, [digits: number]) -> number`,
+//! see [`crate::formula::signature`].
+
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::{char, not_line_ending, space0};
+use nom::combinator::{map, map_opt, opt, value};
+use nom::multi::separated_list0;
+use nom::sequence::{delimited, preceded, separated_pair, terminated, tuple};
+use nom::IResult;
+
+use crate::formula::signature::{Parameter, ParameterType, Signature};
+
+use super::identifier::parse_identifier;
+
+fn parse_type(input: &str) -> IResult<&str, ParameterType> {
+    alt((
+        value(ParameterType::Any, tag("any")),
+        value(ParameterType::Number, tag("number")),
+        value(ParameterType::Text, tag("text")),
+        value(ParameterType::Boolean, tag("boolean")),
+        value(ParameterType::Date, tag("date")),
+        value(ParameterType::Range, tag("range")),
+    ))(input)
+}
+
+/// Parses a parameter such as `digits: number`, without brackets
+fn parse_parameter(input: &str) -> IResult<&str, Parameter> {
+    let name_and_type = separated_pair(
+        parse_identifier,
+        delimited(space0, char(':'), space0),
+        parse_type,
+    );
+    map(
+        tuple((name_and_type, opt(tag("...")))),
+        |((name, kind), variadic)| Parameter {
+            name: name.to_string(),
+            kind,
+            optional: false,
+            variadic: variadic.is_some(),
+        },
+    )(input)
+}
+
+/// Parses a parameter that may be in brackets, making it optional
+fn parse_optional_parameter(input: &str) -> IResult<&str, Parameter> {
+    alt((
+        map(
+            delimited(
+                terminated(char('['), space0),
+                parse_parameter,
+                preceded(space0, char(']')),
+            ),
+            |parameter| Parameter {
+                optional: true,
+                ..parameter
+            },
+        ),
+        parse_parameter,
+    ))(input)
+}
+
+pub fn parse_signature(input: &str) -> IResult<&str, Signature> {
+    let parameters = delimited(
+        terminated(char('('), space0),
+        separated_list0(
+            delimited(space0, char(','), space0),
+            parse_optional_parameter,
+        ),
+        preceded(space0, char(')')),
+    );
+    let returns = preceded(delimited(space0, tag("->"), space0), not_line_ending);
+    map_opt(
+        tuple((parse_identifier, parameters, returns)),
+        |(name, parameters, returns)| Signature::new(name, parameters, returns.trim_end()),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::parse_complete;
+
+    #[test]
+    fn test_parse_signature() {
+        let parse_signature = |s| parse_complete(parse_signature, s);
+
+        let round = parse_signature("round(number: number, [digits: number]) -> number").unwrap();
+        assert_eq!(round.name(), "round");
+        assert_eq!(round.returns(), "number");
+        assert_eq!(
+            round.parameters(),
+            &[
+                Parameter {
+                    name: "number".to_string(),
+                    kind: ParameterType::Number,
+                    optional: false,
+                    variadic: false,
+                },
+                Parameter {
+                    name: "digits".to_string(),
+                    kind: ParameterType::Number,
+                    optional: true,
+                    variadic: false,
+                },
+            ]
+        );
+
+        let sum = parse_signature("sum(number: range...) -> number").unwrap();
+        assert!(sum.parameters()[0].variadic);
+        let pi = parse_signature("pi() -> number").unwrap();
+        assert!(pi.parameters().is_empty());
+        let stdev =
+            parse_signature("stdev.p( number : range... ) -> the standard deviation").unwrap();
+        assert_eq!(stdev.name(), "stdev.p");
+        assert_eq!(stdev.returns(), "the standard deviation");
+
+        assert!(parse_signature("round(number, digits) -> number").is_err());
+        assert!(parse_signature("round(number: number").is_err());
+        assert!(parse_signature("round() number").is_err());
+    }
+}
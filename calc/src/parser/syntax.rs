@@ -0,0 +1,207 @@
+//! The error type of the formula parser, which keeps track of where parsing failed and what was
+//! expected there, so that it can be turned into a [`ParseFormulaError`] for the user.
+
+use std::cell::Cell;
+use std::cmp::Ordering;
+
+use nom::error::{ErrorKind, ParseError};
+use nom::{Err, IResult, Parser};
+
+use super::ParseFormulaError;
+
+/// The deepest nesting of parentheses, calls and prefix operators that is accepted, as in other spreadsheets,
+/// so that parsing and evaluating a formula doesn't run out of stack.
+pub const MAX_DEPTH: usize = 64;
+
+thread_local! {
+    /// the current nesting depth of [`nested`] parsers
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The result of a formula parser.
+pub type PResult<'a, O> = IResult<&'a str, O, SyntaxError<'a>>;
+
+/// Something the parser expected at the position of a [`SyntaxError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expectation<'a> {
+    /// e.g. "an operand" or "')' to close call to sum"
+    pub description: String,
+    /// the input starting at the delimiter that the expected token would close, if any
+    pub opened: Option<&'a str>,
+}
+
+/// A parse error, recording where the parser failed and what it expected there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError<'a> {
+    /// the remaining input at the position where parsing failed
+    pub at: &'a str,
+    /// what would have been accepted there; empty if there is no useful description
+    pub expected: Vec<Expectation<'a>>,
+}
+
+impl<'a> SyntaxError<'a> {
+    pub fn new(at: &'a str, description: impl Into<String>, opened: Option<&'a str>) -> Self {
+        let expectation = Expectation {
+            description: description.into(),
+            opened,
+        };
+        Self {
+            at,
+            expected: vec![expectation],
+        }
+    }
+
+    /// Converts this error into the public error type. `input` is the complete input the parser was
+    /// called with, `otherwise` what is reported as expected if nothing more specific is known.
+    pub fn into_parse_error(self, input: &str, otherwise: &str) -> ParseFormulaError {
+        let offset = offset_of(input, self.at);
+
+        let expected = if self.expected.is_empty() {
+            otherwise.to_string()
+        } else {
+            let descriptions: Vec<_> = self
+                .expected
+                .into_iter()
+                .map(|expectation| match expectation.opened {
+                    Some(opened) => format!(
+                        "{} started at column {}",
+                        expectation.description,
+                        column(input, offset_of(input, opened)),
+                    ),
+                    None => expectation.description,
+                })
+                .collect();
+            descriptions.join(" or ")
+        };
+
+        ParseFormulaError {
+            offset,
+            column: column(input, offset),
+            token: token_at(self.at),
+            expected,
+        }
+    }
+}
+
+impl<'a> ParseError<&'a str> for SyntaxError<'a> {
+    fn from_error_kind(input: &'a str, _kind: ErrorKind) -> Self {
+        Self {
+            at: input,
+            expected: Vec::new(),
+        }
+    }
+
+    fn append(_input: &'a str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(mut self, other: Self) -> Self {
+        // the alternative that got further into the input is the one the user most likely meant;
+        // if they failed at the same position, either one's expectations would have been accepted
+        match self.at.len().cmp(&other.at.len()) {
+            Ordering::Less => self,
+            Ordering::Greater => other,
+            Ordering::Equal => {
+                for expectation in other.expected {
+                    if !self.expected.contains(&expectation) {
+                        self.expected.push(expectation);
+                    }
+                }
+                self
+            }
+        }
+    }
+}
+
+/// Wraps a parser using nom's default error type. Its errors are reported at the position where it
+/// was started, since it parses a single token such as a number or a reference.
+pub fn token<'a, O, F>(mut f: F) -> impl FnMut(&'a str) -> PResult<'a, O>
+where
+    F: Parser<&'a str, O, nom::error::Error<&'a str>>,
+{
+    move |input| {
+        f.parse(input)
+            .map_err(|error| error.map(|_| SyntaxError::from_error_kind(input, ErrorKind::Verify)))
+    }
+}
+
+/// Describes what `f` expects if it fails without consuming any input.
+pub fn expect<'a, O, F>(
+    description: &'static str,
+    mut f: F,
+) -> impl FnMut(&'a str) -> PResult<'a, O>
+where
+    F: Parser<&'a str, O, SyntaxError<'a>>,
+{
+    move |input| {
+        f.parse(input).map_err(|error| {
+            error.map(|error| {
+                if error.at.len() == input.len() {
+                    SyntaxError::new(input, description, None)
+                } else {
+                    error
+                }
+            })
+        })
+    }
+}
+
+/// Parses a nested part of an expression, such as the one in parentheses. Once expressions are nested more than
+/// [`MAX_DEPTH`] levels deep, this fails with an unrecoverable error.
+pub fn nested<'a, O, F>(mut f: F) -> impl FnMut(&'a str) -> PResult<'a, O>
+where
+    F: Parser<&'a str, O, SyntaxError<'a>>,
+{
+    move |input| {
+        let depth = DEPTH.with(Cell::get);
+        if depth >= MAX_DEPTH {
+            let description = format!(
+                "at most {} levels of nesting, as the formula is too deeply nested",
+                MAX_DEPTH
+            );
+            return Err(Err::Failure(SyntaxError::new(input, description, None)));
+        }
+
+        DEPTH.with(|current| current.set(depth + 1));
+        let result = f.parse(input);
+        DEPTH.with(|current| current.set(depth));
+        result
+    }
+}
+
+/// Returns an unrecoverable error for a missing delimiter, such as a closing parenthesis, at `at`.
+/// `opened` is the input starting at the delimiter that should have been closed.
+pub fn unclosed<'a>(at: &'a str, description: String, opened: &'a str) -> Err<SyntaxError<'a>> {
+    Err::Failure(SyntaxError::new(at, description, Some(opened)))
+}
+
+/// The byte offset of `rest`, which is a suffix of `input`.
+fn offset_of(input: &str, rest: &str) -> usize {
+    input.len() - rest.len()
+}
+
+/// The 1-based column, in characters, of the byte offset `offset` in `input`.
+fn column(input: &str, offset: usize) -> usize {
+    input[..offset].chars().count() + 1
+}
+
+/// The token at the start of `input`: a run of identifier characters, a two-character operator or
+/// any other single character; `None` at the end of the input.
+fn token_at(input: &str) -> Option<String> {
+    let first = input.chars().next()?;
+
+    let length = if first.is_alphanumeric() || first == '_' {
+        input
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+            .unwrap_or(input.len())
+    } else if ["<=", "<>", ">="]
+        .iter()
+        .any(|operator| input.starts_with(operator))
+    {
+        2
+    } else {
+        first.len_utf8()
+    };
+
+    Some(input[..length].to_string())
+}
@@ -1,19 +1,34 @@
+mod names;
+
 use std::cmp;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
 
-use petgraph::algo::toposort;
-use petgraph::graphmap::DiGraphMap;
-use petgraph::visit::{Dfs, NodeFiltered, VisitMap};
+use petgraph::algo::kosaraju_scc;
+use petgraph::graphmap::{DiGraphMap, NodeTrait};
+use petgraph::Direction;
+use thiserror::Error;
 
-use crate::address::CellAddress;
+use crate::address::{
+    CellAddress, CellRange, CellReference, ColAddress, RangeReference, RowAddress,
+};
 use crate::cell::Cell;
-use crate::formula::{Evaluate, Formula};
+use crate::file::{FileError, WorkbookFile};
+use crate::formula::function::Argument;
+use crate::formula::registry::{Definition, FunctionRegistry, RegistryError};
+use crate::formula::{Context, Evaluate, Formula};
+use crate::history::History;
 use crate::parser::ParseFormulaError;
-use crate::value::{Error, Value};
+use crate::value::Value;
+use crate::workbook::{Changes, SheetId, Workbook};
+
+use self::names::NameId;
+pub(crate) use self::names::{parse_definition, parse_names};
+pub use self::names::{Name, NameError};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-struct CellAddressOrd(CellAddress);
+pub(crate) struct CellAddressOrd(pub(crate) CellAddress);
 
 impl From<CellAddress> for CellAddressOrd {
     fn from(address: CellAddress) -> Self {
@@ -23,9 +38,7 @@ impl From<CellAddress> for CellAddressOrd {
 
 impl cmp::PartialOrd for CellAddressOrd {
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        let col = self.0.col().partial_cmp(&other.0.col())?;
-        let row = self.0.row().partial_cmp(&other.0.row())?;
-        Some(col.then(row))
+        Some(self.cmp(other))
     }
 }
 
@@ -37,22 +50,288 @@ impl cmp::Ord for CellAddressOrd {
     }
 }
 
-type Function = dyn Send + Sync + Fn(&[Value]) -> Value;
+/// A node of a sheet's dependency graph: a cell, or a name that cells and other names can depend on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum Node {
+    Cell(CellAddressOrd),
+    Name(NameId),
+}
+
+/// The axis along which a sheet is restructured
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Axis {
+    Rows,
+    Cols,
+}
+
+/// Inserting or deleting rows or columns, which moves cells as well as the references pointing to them.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Restructure {
+    axis: Axis,
+    /// the first row or column that is inserted or deleted
+    at: u32,
+    count: u32,
+    delete: bool,
+}
+
+impl Restructure {
+    /// inserting or deleting `count` rows, starting at the row `at`
+    pub(crate) fn rows(at: RowAddress, count: u32, delete: bool) -> Self {
+        Self {
+            axis: Axis::Rows,
+            at: at.get(),
+            count,
+            delete,
+        }
+    }
+
+    /// inserting or deleting `count` columns, starting at the column `at`
+    pub(crate) fn cols(at: ColAddress, count: u32, delete: bool) -> Self {
+        Self {
+            axis: Axis::Cols,
+            at: at.get(),
+            count,
+            delete,
+        }
+    }
+
+    /// the row or column index of an address along the restructured axis
+    fn index_of(&self, address: CellAddress) -> u32 {
+        match self.axis {
+            Axis::Rows => address.row().get(),
+            Axis::Cols => address.col().get(),
+        }
+    }
+
+    /// the address with the row or column index along the restructured axis replaced
+    fn with_index(&self, address: CellAddress, index: u32) -> Option<CellAddress> {
+        let address = match self.axis {
+            Axis::Rows => CellAddress::new(index.try_into().ok()?, address.col()),
+            Axis::Cols => CellAddress::new(address.row(), index.try_into().ok()?),
+        };
+        Some(address)
+    }
+
+    /// the new index of a row or column, or `None` if it was deleted or pushed out of the sheet
+    fn index(&self, index: u32) -> Option<u32> {
+        if index < self.at {
+            Some(index)
+        } else if !self.delete {
+            index.checked_add(self.count)
+        } else if index - self.at < self.count {
+            None
+        } else {
+            Some(index - self.count)
+        }
+    }
+
+    /// the new start and end indices of a span of rows or columns, or `None` if the whole span was deleted.
+    /// When inserting into a span, it grows; when deleting parts of a span, it shrinks.
+    fn span(&self, start: u32, end: u32) -> Option<(u32, u32)> {
+        if !self.delete {
+            return Some((self.index(start)?, self.index(end)?));
+        }
+
+        // deleted start and end indices are moved to the first and last remaining row or column
+        let start = self.index(start).unwrap_or(self.at);
+        let end = self.index(end).unwrap_or(self.at - 1);
+        (start <= end).then_some((start, end))
+    }
+
+    pub(crate) fn address(&self, address: CellAddress) -> Option<CellAddress> {
+        let index = self.index(self.index_of(address))?;
+        self.with_index(address, index)
+    }
+
+    pub(crate) fn reference(&self, reference: CellReference) -> Option<CellReference> {
+        let address = self.address(reference.address())?;
+        Some(CellReference::new(
+            address,
+            reference.is_row_absolute(),
+            reference.is_col_absolute(),
+        ))
+    }
+
+    pub(crate) fn range(&self, reference: RangeReference) -> Option<RangeReference> {
+        let (start, end) = (reference.start(), reference.end());
+        let (start_index, end_index) =
+            self.span(self.index_of(start.address()), self.index_of(end.address()))?;
+
+        let start = CellReference::new(
+            self.with_index(start.address(), start_index)?,
+            start.is_row_absolute(),
+            start.is_col_absolute(),
+        );
+        let end = CellReference::new(
+            self.with_index(end.address(), end_index)?,
+            end.is_row_absolute(),
+            end.is_col_absolute(),
+        );
+        Some(RangeReference::new(start, end))
+    }
+}
+
+/// The order in which cells need to be evaluated after some cells (the roots) have changed.
+///
+/// This contains all cells that (transitively) depend on the roots or on volatile cells, including those cells
+/// themselves, grouped into strongly connected components in topological order.
+/// A component is a cycle if it has more than one cell, or a single cell depending on itself.
+pub(crate) struct EvaluationOrder<N: NodeTrait> {
+    roots: HashSet<N>,
+    /// cells that are always evaluated, but only count as changed if their values change
+    volatile: HashSet<N>,
+    /// the dependencies between the cells, in the same direction as [`Sheet`]'s dependency graph
+    graph: DiGraphMap<N, ()>,
+    /// the components, each flagged whether it is a cycle
+    components: Vec<(Vec<N>, bool)>,
+}
+
+impl<N: NodeTrait> EvaluationOrder<N> {
+    /// Determines the evaluation order; `successors` returns the cells that directly depend on a cell.
+    pub(crate) fn new<R, V, F, S>(roots: R, volatile: V, mut successors: F) -> Self
+    where
+        R: IntoIterator<Item = N>,
+        V: IntoIterator<Item = N>,
+        F: FnMut(N) -> S,
+        S: IntoIterator<Item = N>,
+    {
+        // - make a graph only containing the dependent cells
+        let roots: HashSet<_> = roots.into_iter().collect();
+        let volatile: HashSet<_> = volatile.into_iter().collect();
+        let mut graph = DiGraphMap::new();
+        let mut stack = Vec::new();
+        for &root in roots.union(&volatile) {
+            graph.add_node(root);
+            stack.push(root);
+        }
+        while let Some(node) = stack.pop() {
+            for successor in successors(node) {
+                if !graph.contains_node(successor) {
+                    stack.push(successor);
+                }
+                graph.add_edge(node, successor, ());
+            }
+        }
+
+        // - determine the strongly connected components of this graph.
+        //   The components are returned in reverse topological order.
+        let mut components = kosaraju_scc(&graph);
+        components.reverse();
+        let components = components
+            .into_iter()
+            .map(|component| {
+                let is_cycle = match component.as_slice() {
+                    [node] => graph.contains_edge(*node, *node),
+                    _ => true,
+                };
+                (component, is_cycle)
+            })
+            .collect();
 
+        Self {
+            roots,
+            volatile,
+            graph,
+            components,
+        }
+    }
+
+    /// Walks the components in topological order and calls `update` for each cell whose value may have changed,
+    /// together with whether it is part of a cycle. `update` returns whether the cell's value actually changed.
+    /// The roots and volatile cells are always updated; other cells only if a cell they depend on has changed.
+    /// Returns the roots and the cells whose values have changed.
+    pub(crate) fn propagate<F: FnMut(N, bool) -> bool>(self, mut update: F) -> Vec<N> {
+        let mut dirty: HashSet<_> = self.roots.union(&self.volatile).copied().collect();
+        let mut changed = Vec::new();
+        for (component, is_cycle) in self.components {
+            if !component.iter().any(|node| dirty.contains(node)) {
+                continue;
+            }
+
+            for node in component {
+                // the roots' previous values are not known, so they always count as changed
+                if update(node, is_cycle) || self.roots.contains(&node) {
+                    dirty.extend(self.graph.neighbors(node));
+                    changed.push(node);
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// The inputs of some cells in a batch could not be parsed, so none of the cells were changed.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("the inputs of {} cell(s) are invalid", .errors.len())]
+pub struct SetCellsError {
+    /// the addresses of the invalid cells, in the order they were given, with the respective errors
+    pub errors: Vec<(CellAddress, ParseFormulaError)>,
+}
+
+impl SetCellsError {
+    /// the addresses of the invalid cells
+    pub fn addresses(&self) -> impl Iterator<Item = CellAddress> + '_ {
+        self.errors.iter().map(|(address, _)| *address)
+    }
+}
+
+/// Parses the inputs of a batch of cells, failing if any of them is invalid.
+pub(crate) fn parse_cells<I: IntoIterator<Item = (CellAddress, String)>>(
+    cells: I,
+) -> Result<Vec<(CellAddress, String, Formula)>, SetCellsError> {
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+    for (address, input) in cells {
+        match input.parse() {
+            Ok(formula) => parsed.push((address, input, formula)),
+            Err(error) => errors.push((address, error)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(parsed)
+    } else {
+        Err(SetCellsError { errors })
+    }
+}
+
+/// The name a function was registered with, if it differs from `name`
+pub(crate) fn canonical_function(functions: &FunctionRegistry, name: &str) -> Option<String> {
+    let canonical = functions.canonical_name(name)?;
+    (canonical != name).then(|| canonical.to_string())
+}
+
+/// Replaces the names of the functions called in a cell's formula by the names they were registered with,
+/// see [`FunctionRegistry::canonical_name`]; unknown names are kept. If any name changes,
+/// the cell's input is written anew from its formula, so that e.g. `=SUM(A1:A3)` is shown as `=sum(A1:A3)`.
+pub(crate) fn normalize_functions(
+    functions: &FunctionRegistry,
+    (address, input, formula): (CellAddress, String, Formula),
+) -> (CellAddress, String, Formula) {
+    let normalized = formula.rename_functions(&|name| canonical_function(functions, name));
+    if normalized == formula {
+        (address, input, formula)
+    } else {
+        (address, normalized.to_string(), normalized)
+    }
+}
+
+/// The cells of one sheet, the names defined on it and the dependencies between them.
+/// Each sheet of a [`Workbook`] is a worksheet; the workbook evaluates its cells with the registered functions
+/// and records the edits in its undo history.
 #[derive(Default)]
-pub struct Sheet {
+pub struct Worksheet {
     cells: HashMap<CellAddress, Cell>,
     /// An edge from a to b means that cell b depends on a, or that data flows from a to b.
     /// E.g. if A2 contains `=A1`, there will be an edge from A1 to A2.
-    dependents: DiGraphMap<CellAddressOrd, ()>,
-    functions: HashMap<String, Box<Function>>,
+    /// Names are nodes as well: if A2 contains `=TaxRate` and `TaxRate` is defined as `$B$1`,
+    /// there will be edges from B1 to `TaxRate` and from `TaxRate` to A2.
+    dependents: DiGraphMap<Node, ()>,
+    names: HashMap<NameId, Name>,
+    next_name_id: NameId,
 }
 
-impl Sheet {
-    pub fn new() -> Self {
-        Default::default()
-    }
-
+impl Worksheet {
     pub fn cell(&self, address: &CellAddress) -> Option<&Cell> {
         self.cells.get(address)
     }
@@ -61,33 +340,265 @@ impl Sheet {
         self.cell(address).map(Cell::value)
     }
 
-    pub fn evaluate<T: Evaluate>(&self, expression: &T) -> Value {
-        expression.evaluate(self)
+    /// The inputs and formulas of the cells that [`Sheet::copy_range`] pastes, without changing anything.
+    pub(crate) fn pasted_cells(
+        &self,
+        source: CellRange,
+        destination: CellRange,
+    ) -> Vec<(CellAddress, String, Formula)> {
+        // copy the source cells first, as source and destination may overlap
+        let cells: Vec<_> = source
+            .iter()
+            .map(|address| (address, self.cell(&address).cloned()))
+            .collect();
+
+        fn repetitions(source: u32, destination: u32) -> u32 {
+            if destination.is_multiple_of(source) {
+                destination / source
+            } else {
+                1
+            }
+        }
+
+        let rows =
+            i64::from(destination.start().row().get()) - i64::from(source.start().row().get());
+        let cols =
+            i64::from(destination.start().col().get()) - i64::from(source.start().col().get());
+
+        let mut targets = Vec::new();
+        for i in 0..repetitions(source.height(), destination.height()) {
+            for j in 0..repetitions(source.width(), destination.width()) {
+                let rows = rows + i64::from(i) * i64::from(source.height());
+                let cols = cols + i64::from(j) * i64::from(source.width());
+
+                for (address, cell) in &cells {
+                    // parts of the source that would end up outside the sheet are not pasted
+                    let target = match address.offset(rows, cols) {
+                        Some(target) => target,
+                        None => continue,
+                    };
+
+                    let (input, formula) = match cell {
+                        // literal values are copied verbatim
+                        Some(Cell {
+                            input,
+                            formula: formula @ Formula::Literal(_),
+                            ..
+                        }) => (input.clone(), formula.clone()),
+                        // formulas are moved and then formatted
+                        Some(Cell { formula, .. }) => {
+                            let formula = formula.offset(rows, cols);
+                            (formula.to_string(), formula)
+                        }
+                        None => (String::new(), Formula::default()),
+                    };
+
+                    targets.push((target, input, formula));
+                }
+            }
+        }
+
+        targets
     }
 
-    pub fn set_cell(
+    /// Moves the cells as described by `change` and adjusts references to them, without recalculating anything.
+    /// References to other sheets are left unchanged.
+    /// Returns all addresses whose value may have changed because cells were moved,
+    /// and the cells that need to be recalculated: moved formulas and those whose references have changed.
+    pub(crate) fn move_cells(
         &mut self,
-        address: CellAddress,
-        input: String,
-    ) -> Result<HashMap<CellAddress, Value>, ParseFormulaError> {
-        let formula: Formula = input.parse()?;
+        change: Restructure,
+    ) -> (Vec<CellAddress>, Vec<CellAddress>) {
+        // all addresses whose value may change, either because cells are moved or recalculated
+        let mut changed = Vec::new();
+        // cells that need to be recalculated because they were moved or the cells they refer to have changed
+        let mut affected = Vec::new();
+
+        let cells = std::mem::take(&mut self.cells);
+        for (address, mut cell) in cells {
+            let new_address = change.address(address);
+            if new_address != Some(address) {
+                changed.push(address);
+                changed.extend(new_address);
+            }
+            let new_address = match new_address {
+                Some(new_address) => new_address,
+                // the cell was deleted
+                None => continue,
+            };
+            // a moved formula is recalculated, as its value may depend on its position, e.g. `=row()`
+            let mut recalculate =
+                new_address != address && matches!(cell.formula, Formula::Formula(_));
+
+            let formula = cell.formula.map_references(
+                &|sheet, r| match sheet {
+                    None => change.reference(r),
+                    Some(_) => Some(r),
+                },
+                &|sheet, r| match sheet {
+                    None => change.range(r),
+                    Some(_) => Some(r),
+                },
+            );
+            if formula != cell.formula {
+                // the references in the formula were adjusted. If the formula now refers to different cells
+                // than before (because cells were deleted or inserted into a range), it needs to be recalculated
+                let mut before = HashSet::new();
+                let mut deleted = false;
+                cell.formula.visit_dependecies(
+                    &mut |dependency| match change.address(dependency) {
+                        Some(dependency) => {
+                            before.insert(dependency);
+                        }
+                        None => deleted = true,
+                    },
+                );
+                let mut after = HashSet::new();
+                formula.visit_dependecies(&mut |dependency| {
+                    after.insert(dependency);
+                });
+                recalculate |= deleted || before != after;
+
+                cell.input = formula.to_string();
+                cell.formula = formula;
+            }
+
+            if recalculate {
+                affected.push(new_address);
+            }
+            self.cells.insert(new_address, cell);
+        }
+
+        // adjust the references in the names' definitions; cells using a changed name need to be recalculated
+        let mut changed_names = Vec::new();
+        for (id, name) in &mut self.names {
+            let expression = name
+                .expression
+                .map_references(&|_sheet, r| change.reference(r), &|_sheet, r| {
+                    change.range(r)
+                });
+            if expression != name.expression {
+                name.expression = expression;
+                changed_names.push(*id);
+            }
+        }
 
-        let mut cell = self.cells.entry(address);
+        // rebuild the dependency graph for the moved cells
+        self.rebuild_dependents();
+        for id in changed_names {
+            affected.extend(self.cell_dependents(Node::Name(id)));
+        }
+
+        (changed, affected)
+    }
 
-        // remove old dependencies of this cell's formula
-        if let Entry::Occupied(cell) = &mut cell {
-            let cell = cell.get_mut();
-            cell.formula.visit_dependecies(&mut |dependency| {
-                self.dependents
-                    .remove_edge(dependency.into(), address.into());
-            });
+    /// Replaces every cell's formula by the result of `rewrite`, reformatting the inputs of changed formulas.
+    /// `rewrite` must only change references to other sheets, as the dependency graph is not updated.
+    /// Returns the addresses of the changed cells, which still need to be recalculated.
+    pub(crate) fn rewrite_formulas<F: Fn(&Formula) -> Formula>(
+        &mut self,
+        rewrite: F,
+    ) -> Vec<CellAddress> {
+        let mut changed = Vec::new();
+        for (address, cell) in &mut self.cells {
+            let formula = rewrite(&cell.formula);
+            if formula != cell.formula {
+                cell.input = formula.to_string();
+                cell.formula = formula;
+                changed.push(*address);
+            }
         }
+        changed
+    }
+
+    /// All non-empty cells of this sheet
+    pub(crate) fn cells(&self) -> impl Iterator<Item = (&CellAddress, &Cell)> {
+        self.cells.iter()
+    }
 
-        // add new dependencies of this cell's formula
-        formula.visit_dependecies(&mut |dependency| {
-            self.dependents
-                .add_edge(dependency.into(), address.into(), ());
+    /// The cells on this sheet that directly depend on the given cell, including via names
+    pub(crate) fn dependents(&self, address: CellAddress) -> Vec<CellAddress> {
+        self.cell_dependents(Node::Cell(address.into()))
+    }
+
+    /// The cells that directly depend on a node of the dependency graph, looking through names:
+    /// a cell using a name depends on the cells the name refers to.
+    fn cell_dependents(&self, node: Node) -> Vec<CellAddress> {
+        let mut cells = Vec::new();
+        for dependent in self.dependents.neighbors(node) {
+            match dependent {
+                Node::Cell(CellAddressOrd(address)) => cells.push(address),
+                // names can't depend on themselves, so this ends
+                Node::Name(_) => cells.extend(self.cell_dependents(dependent)),
+            }
+        }
+        cells
+    }
+
+    /// The cells and names a formula or a name's definition directly depends on, as nodes of the dependency graph.
+    /// Names that are not defined are ignored.
+    fn dependencies<T: Evaluate>(&self, formula: &T) -> Vec<Node> {
+        let mut dependencies = Vec::new();
+        formula.visit_dependecies(&mut |address| dependencies.push(Node::Cell(address.into())));
+        formula.visit_names(&mut |name| {
+            dependencies.extend(self.find_name(name).map(|(id, _)| Node::Name(id)));
         });
+        dependencies
+    }
+
+    /// Rebuilds the dependency graph from scratch, after cells have been moved or names have changed.
+    pub(crate) fn rebuild_dependents(&mut self) {
+        let mut dependents = DiGraphMap::new();
+        for (address, cell) in &self.cells {
+            let node = Node::Cell((*address).into());
+            dependents.add_node(node);
+            for dependency in self.dependencies(&cell.formula) {
+                dependents.add_edge(dependency, node, ());
+            }
+        }
+        for (id, name) in &self.names {
+            let node = Node::Name(*id);
+            dependents.add_node(node);
+            for dependency in self.dependencies(&name.expression) {
+                dependents.add_edge(dependency, node, ());
+            }
+        }
+        self.dependents = dependents;
+    }
+
+    /// Replaces all names, e.g. by those read from a file, and rebuilds the dependency graph.
+    pub(crate) fn replace_names(&mut self, names: HashMap<NameId, Name>) {
+        self.next_name_id = names.keys().max().map_or(0, |id| id + 1);
+        self.names = names;
+        self.rebuild_dependents();
+    }
+
+    /// The names' definitions as they are stored in files
+    pub(crate) fn names_to_save(&self) -> BTreeMap<String, String> {
+        self.names
+            .values()
+            .map(|name| (name.name().to_string(), name.definition()))
+            .collect()
+    }
+
+    /// Stores the input and formula of a cell and updates the dependency graph accordingly.
+    /// The cell and its dependents still need to be recalculated afterwards.
+    pub(crate) fn store_cell(&mut self, address: CellAddress, input: String, formula: Formula) {
+        let node = Node::Cell(address.into());
+
+        // replace the dependencies of this cell's formula
+        let previous: Vec<_> = self
+            .dependents
+            .neighbors_directed(node, Direction::Incoming)
+            .collect();
+        for dependency in previous {
+            self.dependents.remove_edge(dependency, node);
+        }
+        for dependency in self.dependencies(&formula) {
+            self.dependents.add_edge(dependency, node, ());
+        }
+
+        let cell = self.cells.entry(address);
 
         // update the cell's input and formula.
         if let ("", Formula::Literal(Value::Empty)) = (input.as_ref(), &formula) {
@@ -102,81 +613,292 @@ impl Sheet {
             cell.formula = formula;
 
             // also make sure it exists in the dependency graph
-            self.dependents.add_node(address.into());
+            self.dependents.add_node(node);
         };
+    }
 
-        // evaluate this and dependent cells
-        // TODO ignore cells that have not actually changed
+    /// Stores the value of a cell, and whether calculating it called a volatile function.
+    pub(crate) fn set_value(&mut self, address: &CellAddress, value: Value, volatile: bool) {
+        if let Some(cell) = self.cells.get_mut(address) {
+            cell.value = value;
+            cell.volatile = volatile;
+        }
+    }
 
-        // - determine all dependent cells
-        let mut dfs = Dfs::new(&self.dependents, address.into());
-        while dfs.next(&self.dependents).is_some() {}
+    /// The cells whose values were calculated using volatile functions, which are recalculated every time
+    pub(crate) fn volatile_cells(&self) -> impl Iterator<Item = CellAddress> + '_ {
+        self.cells
+            .iter()
+            .filter(|(_, cell)| cell.volatile)
+            .map(|(address, _)| *address)
+    }
+}
 
-        // - make a subgraph only containing those
-        let dependent_cells = dfs.discovered;
-        let filtered_cells = NodeFiltered::from_fn(&self.dependents, |address| {
-            dependent_cells.is_visited(&address)
-        });
+/// A standalone sheet, which is a [`Workbook`] with a single sheet: cells are edited, undone and recalculated
+/// exactly as in a workbook, and the changes are reported by their addresses only.
+#[derive(Default)]
+pub struct Sheet {
+    workbook: Workbook,
+}
 
-        // - topologically walk this graph
-        match toposort(&filtered_cells, None) {
-            Ok(cells) => {
-                for CellAddressOrd(cell) in &cells {
-                    self.reevaluate(cell);
-                }
-            }
-            Err(_cycle) => {
-                todo!("cycle");
-            }
+impl Sheet {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// A sheet with all functions of the [standard library](crate::functions) registered
+    pub fn with_standard_library() -> Self {
+        Self {
+            workbook: Workbook::with_standard_library(),
         }
+    }
 
-        let dependent_cells = dependent_cells
-            .into_iter()
-            .map(|CellAddressOrd(ord)| (ord, self.value(&ord).into()))
-            .collect();
+    /// The id of the workbook's only sheet
+    fn id(&self) -> SheetId {
+        self.workbook.first_sheet().0
+    }
 
-        Ok(dependent_cells)
+    /// The cells and names of the workbook's only sheet
+    fn worksheet(&self) -> &Worksheet {
+        self.workbook.first_sheet().1
     }
 
-    fn reevaluate(&mut self, address: &CellAddress) {
-        let cell = match self.cell(address) {
-            Some(cell) => cell,
-            None => return,
-        };
+    pub fn cell(&self, address: &CellAddress) -> Option<&Cell> {
+        self.worksheet().cell(address)
+    }
+
+    pub fn value(&self, address: &CellAddress) -> Option<&Value> {
+        self.worksheet().value(address)
+    }
+
+    /// Evaluates an expression on the sheet, outside of any cell.
+    pub fn evaluate<T: Evaluate>(&self, expression: &T) -> Value {
+        self.workbook.evaluate_on(self.id(), expression)
+    }
 
-        let value = self.evaluate(&cell.formula);
+    pub fn set_cell(
+        &mut self,
+        address: CellAddress,
+        input: String,
+    ) -> Result<HashMap<CellAddress, Value>, ParseFormulaError> {
+        let formula: Formula = input.parse()?;
 
-        let cell = self.cells.get_mut(address).expect("cell doesn't exist");
-        cell.value = value;
+        Ok(self.edit(vec![(address, input, formula)]))
     }
 
-    pub fn function(&self, name: &str) -> Option<&Function> {
-        self.functions.get(name).map(Box::as_ref)
+    /// Sets the inputs of many cells at once, recalculating all affected cells in a single pass.
+    /// All inputs are parsed first; if any of them is invalid, no cell is changed.
+    /// If an address occurs more than once, the last input for it is used.
+    /// Returns the new values of all changed and recalculated cells.
+    pub fn set_cells<I: IntoIterator<Item = (CellAddress, String)>>(
+        &mut self,
+        cells: I,
+    ) -> Result<HashMap<CellAddress, Value>, SetCellsError> {
+        let cells = parse_cells(cells)?;
+
+        Ok(self.edit(cells))
     }
 
-    pub fn call(&self, name: &str, arguments: &[Value]) -> Value {
-        match self.function(name) {
-            Some(function) => function(arguments),
-            None => Value::Error(Error::Undefined),
-        }
+    /// Stores the inputs and formulas of cells as one step of the undo history,
+    /// and recalculates them as well as the cells depending on them.
+    pub(crate) fn edit(
+        &mut self,
+        cells: Vec<(CellAddress, String, Formula)>,
+    ) -> HashMap<CellAddress, Value> {
+        let id = self.id();
+        sheet_values(self.workbook.edit(id, cells))
+    }
+
+    /// Reverts the most recent edit, returning the new values of all reverted and recalculated cells,
+    /// or `None` if there is nothing to undo.
+    /// Inserting or deleting rows and columns, renaming names and loading a file can't be undone;
+    /// they clear the history.
+    pub fn undo(&mut self) -> Option<HashMap<CellAddress, Value>> {
+        self.workbook.undo().map(sheet_values)
+    }
+
+    /// Applies the most recently undone edit again, returning the new values of all changed and recalculated cells,
+    /// or `None` if there is nothing to redo.
+    pub fn redo(&mut self) -> Option<HashMap<CellAddress, Value>> {
+        self.workbook.redo().map(sheet_values)
+    }
+
+    pub fn history(&self) -> &History<(SheetId, CellAddress)> {
+        self.workbook.history()
+    }
+
+    /// The undo history, e.g. for changing its depth
+    pub fn history_mut(&mut self) -> &mut History<(SheetId, CellAddress)> {
+        self.workbook.history_mut()
+    }
+
+    /// Writes the sheet's cell inputs in the native file format (see [`crate::file`]), as a workbook with a single sheet.
+    /// That sheet is named `Sheet1`, or like the sheet that was loaded from a file.
+    pub fn save<W: io::Write>(&self, writer: W) -> Result<(), FileError> {
+        self.workbook.save(writer)
+    }
+
+    /// Replaces the sheet's cells by those read from a file in the native format, keeping the sheet's functions.
+    /// All inputs are parsed and evaluated again; if any of them is invalid, the sheet is left unchanged.
+    /// Only the file's first sheet is loaded; files with multiple sheets can be loaded into a [`Workbook`].
+    /// Returns the values of all loaded cells.
+    pub fn load<R: io::Read>(
+        &mut self,
+        reader: R,
+    ) -> Result<HashMap<CellAddress, Value>, FileError> {
+        let mut file = WorkbookFile::read(reader)?;
+        file.sheets.truncate(1);
+
+        self.workbook.load_file(file).map(sheet_values)
+    }
+
+    /// Copies the cells in `source` to `destination`, as pasting in a spreadsheet application would.
+    /// If the destination is a multiple of the source's size, the source is repeated to fill it;
+    /// otherwise, the source is pasted once, starting at the destination's top left cell.
+    /// Relative references in the copied formulas are adjusted (see [`Formula::offset`]).
+    pub fn copy_range(
+        &mut self,
+        source: CellRange,
+        destination: CellRange,
+    ) -> HashMap<CellAddress, Value> {
+        let id = self.id();
+        sheet_values(self.workbook.copy_cells(id, source, destination))
+    }
+
+    /// Inserts `count` empty rows before the row `at`.
+    /// Cells below are moved down, and references to them are adjusted.
+    /// Returns the new values of all cells that were moved or recalculated.
+    pub fn insert_rows(&mut self, at: RowAddress, count: u32) -> HashMap<CellAddress, Value> {
+        self.restructure(Restructure::rows(at, count, false))
+    }
+
+    /// Deletes `count` rows starting at the row `at`.
+    /// Cells below are moved up, and references to them are adjusted;
+    /// references to deleted cells become `#REF!` errors.
+    /// Returns the new values of all cells that were moved, deleted or recalculated.
+    pub fn delete_rows(&mut self, at: RowAddress, count: u32) -> HashMap<CellAddress, Value> {
+        self.restructure(Restructure::rows(at, count, true))
     }
 
+    /// Inserts `count` empty columns before the column `at`.
+    /// Cells to the right are moved right, and references to them are adjusted.
+    /// Returns the new values of all cells that were moved or recalculated.
+    pub fn insert_cols(&mut self, at: ColAddress, count: u32) -> HashMap<CellAddress, Value> {
+        self.restructure(Restructure::cols(at, count, false))
+    }
+
+    /// Deletes `count` columns starting at the column `at`.
+    /// Cells to the right are moved left, and references to them are adjusted;
+    /// references to deleted cells become `#REF!` errors.
+    /// Returns the new values of all cells that were moved, deleted or recalculated.
+    pub fn delete_cols(&mut self, at: ColAddress, count: u32) -> HashMap<CellAddress, Value> {
+        self.restructure(Restructure::cols(at, count, true))
+    }
+
+    fn restructure(&mut self, change: Restructure) -> HashMap<CellAddress, Value> {
+        let id = self.id();
+        sheet_values(self.workbook.restructure(id, change))
+    }
+
+    /// The definition of a name, if it exists
+    pub fn defined_name(&self, name: &str) -> Option<&Name> {
+        self.worksheet().defined_name(name)
+    }
+
+    /// All names defined on this sheet, ordered by name
+    pub fn defined_names(&self) -> Vec<&Name> {
+        self.worksheet().defined_names()
+    }
+
+    /// Defines a name as standing for an expression such as `$B$1`, `A2:A13` or `0.19`, replacing any previous
+    /// definition. Returns the new values of all cells whose values changed because they use the name.
+    pub fn define_name(
+        &mut self,
+        name: &str,
+        definition: &str,
+    ) -> Result<HashMap<CellAddress, Value>, NameError> {
+        let id = self.id();
+        self.workbook
+            .store_name(id, name, definition)
+            .map(sheet_values)
+    }
+
+    /// Renames a name, updating all formulas that use it.
+    /// Returns the new values of all cells whose values changed, e.g. because they used the new name before.
+    pub fn rename_name(
+        &mut self,
+        from: &str,
+        to: &str,
+    ) -> Result<HashMap<CellAddress, Value>, NameError> {
+        let id = self.id();
+        self.workbook.change_name(id, from, to).map(sheet_values)
+    }
+
+    /// Deletes a name; formulas using it result in `#NAME?` errors.
+    /// Returns the new values of all cells whose values changed.
+    pub fn delete_name(&mut self, name: &str) -> Result<HashMap<CellAddress, Value>, NameError> {
+        let id = self.id();
+        self.workbook.remove_name(id, name).map(sheet_values)
+    }
+
+    pub fn function(&self, name: &str) -> Option<&Definition> {
+        self.workbook.function(name)
+    }
+
+    pub fn functions(&self) -> &FunctionRegistry {
+        self.workbook.functions()
+    }
+
+    /// Registers a function with its signature, which its arguments are checked against before it's called.
+    pub fn register_function(&mut self, definition: Definition) {
+        self.workbook.register_function(definition);
+    }
+
+    /// Makes the function `name` callable as `alias` as well, see [`FunctionRegistry::register_alias`].
+    pub fn register_alias(&mut self, alias: &str, name: &str) -> Result<(), RegistryError> {
+        self.workbook.register_alias(alias, name)
+    }
+
+    /// Adds the name of the function `name` in a locale, see [`FunctionRegistry::register_localized_name`].
+    pub fn register_localized_name(
+        &mut self,
+        locale: &str,
+        name: &str,
+        localized: &str,
+    ) -> Result<(), RegistryError> {
+        self.workbook
+            .register_localized_name(locale, name, localized)
+    }
+
+    /// Registers a function that receives the values of its arguments, which aren't checked.
     pub fn set_function<S: ToString, F: 'static + Send + Sync + Fn(&[Value]) -> Value>(
         &mut self,
         name: S,
         function: F,
     ) {
-        self.functions.insert(name.to_string(), Box::new(function));
+        self.workbook.set_function(name, function);
+    }
+
+    /// Registers a function that receives its unevaluated arguments, see [`Function::Lazy`](crate::formula::function::Function::Lazy).
+    pub fn set_lazy_function<S, F>(&mut self, name: S, function: F)
+    where
+        S: ToString,
+        F: 'static + Send + Sync + Fn(&[Argument], &dyn Context) -> Value,
+    {
+        self.workbook.set_lazy_function(name, function);
     }
 }
 
+/// The new values of the cells on the only sheet of a standalone [`Sheet`]'s workbook
+fn sheet_values(changes: Changes) -> HashMap<CellAddress, Value> {
+    changes.into_values().next().unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
-    use bigdecimal::BigDecimal;
-
     use super::*;
 
-    use crate::value::Value;
+    use crate::value::{Error, Value};
 
     fn set_and_get_cell<S: ToString>(input: S) -> Value {
         let mut sheet = Sheet::new();
@@ -216,6 +938,44 @@ mod tests {
         assert_eq!(value, Value::String("foo".into()));
     }
 
+    #[test]
+    fn test_operators() {
+        fn number(value: &str) -> Value {
+            Value::Number(value.parse().unwrap())
+        }
+
+        assert_eq!(set_and_get_cell("=1+2*3"), number("7"));
+        assert_eq!(set_and_get_cell("=(1+2)*3"), number("9"));
+        assert_eq!(set_and_get_cell("=10-4-3"), number("3"));
+        assert_eq!(set_and_get_cell("=2^3^2"), number("64"));
+        assert_eq!(set_and_get_cell("=-2^2"), number("4"));
+        assert_eq!(set_and_get_cell("=7/2"), number("3.5"));
+        assert_eq!(set_and_get_cell("=50%"), number("0.5"));
+        assert_eq!(set_and_get_cell("=-(1-3)"), number("2"));
+        assert_eq!(
+            set_and_get_cell("=1/0"),
+            Value::Error(Error::DivisionByZero)
+        );
+        assert_eq!(set_and_get_cell("=1+\"foo\""), Value::Error(Error::Value));
+    }
+
+    #[test]
+    fn test_operators_with_references() {
+        let mut sheet = Sheet::new();
+        sheet
+            .set_cell("A1".parse().unwrap(), "2".to_string())
+            .unwrap();
+        sheet
+            .set_cell("A2".parse().unwrap(), "=A1 * A1 + 1".to_string())
+            .unwrap();
+        sheet
+            .set_cell("A1".parse().unwrap(), "3".to_string())
+            .unwrap();
+
+        let value: Value = sheet.value(&"A2".parse().unwrap()).into();
+        assert_eq!(value, Value::Number(10.into()));
+    }
+
     #[test]
     fn test_reference() {
         let mut sheet = Sheet::new();
@@ -230,6 +990,23 @@ mod tests {
         assert_eq!(value, Value::Number(1.into()));
     }
 
+    #[test]
+    fn test_absolute_reference() {
+        let mut sheet = Sheet::new();
+        sheet
+            .set_cell("A1".parse().unwrap(), "1".to_string())
+            .unwrap();
+        sheet
+            .set_cell("A2".parse().unwrap(), "=$A$1 + A$1 + $A1".to_string())
+            .unwrap();
+        sheet
+            .set_cell("A1".parse().unwrap(), "2".to_string())
+            .unwrap();
+
+        let value: Value = sheet.value(&"A2".parse().unwrap()).into();
+        assert_eq!(value, Value::Number(6.into()));
+    }
+
     #[test]
     fn test_reference_reevaluate() {
         let mut sheet = Sheet::new();
@@ -244,12 +1021,470 @@ mod tests {
         assert_eq!(value, Value::Number(1.into()));
     }
 
+    #[test]
+    fn test_set_cells() {
+        let mut sheet = Sheet::new();
+        sheet
+            .set_cell("B1".parse().unwrap(), "=A1 + A2".to_string())
+            .unwrap();
+
+        let values = sheet
+            .set_cells([
+                ("A1".parse().unwrap(), "1".to_string()),
+                ("A2".parse().unwrap(), "=A1 * 2".to_string()),
+                ("A3".parse().unwrap(), "=A2".to_string()),
+            ])
+            .unwrap();
+        assert_eq!(values.len(), 4);
+        assert_eq!(value(&sheet, "B1"), Value::Number(3.into()));
+        assert_eq!(value(&sheet, "A3"), Value::Number(2.into()));
+
+        // nothing is changed if any input is invalid
+        let error = sheet
+            .set_cells([
+                ("A1".parse().unwrap(), "2".to_string()),
+                ("A2".parse().unwrap(), "=1 +".to_string()),
+                ("A3".parse().unwrap(), "=(".to_string()),
+            ])
+            .unwrap_err();
+        assert_eq!(
+            error.addresses().collect::<Vec<_>>(),
+            ["A2".parse().unwrap(), "A3".parse().unwrap()]
+        );
+        assert_eq!(value(&sheet, "A1"), Value::Number(1.into()));
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut sheet = Sheet::new();
+        sheet
+            .set_cell("A1".parse().unwrap(), "1".to_string())
+            .unwrap();
+        sheet
+            .set_cell("A2".parse().unwrap(), "=A1 * 2".to_string())
+            .unwrap();
+        sheet
+            .set_cells([
+                ("A1".parse().unwrap(), "2".to_string()),
+                ("B1".parse().unwrap(), "3".to_string()),
+            ])
+            .unwrap();
+
+        // the batch is undone as a whole
+        let values = sheet.undo().unwrap();
+        assert_eq!(values[&"A2".parse().unwrap()], Value::Number(2.into()));
+        assert_eq!(values[&"B1".parse().unwrap()], Value::Empty);
+        assert_eq!(input(&sheet, "A1"), "1");
+
+        sheet.undo().unwrap();
+        assert_eq!(input(&sheet, "A2"), "");
+
+        let values = sheet.redo().unwrap();
+        assert_eq!(values[&"A2".parse().unwrap()], Value::Number(2.into()));
+        sheet.redo().unwrap();
+        assert_eq!(value(&sheet, "A2"), Value::Number(4.into()));
+        assert!(sheet.redo().is_none());
+
+        // copying is one step as well
+        sheet.copy_range("A1:A2".parse().unwrap(), "C1".parse().unwrap());
+        assert_eq!(input(&sheet, "C2"), "=C1 * 2");
+        sheet.undo().unwrap();
+        assert_eq!(input(&sheet, "C2"), "");
+
+        // restructuring clears the history
+        sheet.insert_rows("1".parse().unwrap(), 1);
+        assert!(sheet.undo().is_none());
+    }
+
+    #[test]
+    fn test_undo_depth() {
+        let mut sheet = Sheet::new();
+        sheet.history_mut().set_depth(2);
+        for input in ["1", "2", "3"] {
+            sheet
+                .set_cell("A1".parse().unwrap(), input.to_string())
+                .unwrap();
+        }
+
+        sheet.undo().unwrap();
+        sheet.undo().unwrap();
+        assert!(sheet.undo().is_none());
+        assert_eq!(input(&sheet, "A1"), "1");
+    }
+
+    #[test]
+    fn test_unchanged_values_pruned() {
+        let mut sheet = Sheet::new();
+        sheet.set_function("count", |_| Value::Number(1.into()));
+        for (address, input) in [
+            ("A1", "1"),
+            ("A2", "=A1 * 0"),
+            ("A3", "=A2 + 1"),
+            ("B1", "=count(A1)"),
+        ] {
+            sheet
+                .set_cell(address.parse().unwrap(), input.to_string())
+                .unwrap();
+        }
+
+        // A2 stays zero, so A3 is not recalculated; B1's function gives the same result
+        let values = sheet
+            .set_cell("A1".parse().unwrap(), "2".to_string())
+            .unwrap();
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[&"A1".parse().unwrap()], Value::Number(2.into()));
+
+        // the edited cell is always reported, even if its value stays the same
+        let values = sheet
+            .set_cell("A1".parse().unwrap(), "=2".to_string())
+            .unwrap();
+        assert_eq!(values.len(), 1);
+
+        // a cleared cell counts as zero, so A2 stays the same
+        let values = sheet
+            .set_cell("A1".parse().unwrap(), "".to_string())
+            .unwrap();
+        assert_eq!(values.len(), 1);
+
+        // errors propagate
+        let values = sheet
+            .set_cell("A1".parse().unwrap(), "\"foo\"".to_string())
+            .unwrap();
+        assert_eq!(values[&"A2".parse().unwrap()], Value::Error(Error::Value));
+        assert_eq!(values[&"A3".parse().unwrap()], Value::Error(Error::Value));
+    }
+
+    #[test]
+    fn test_self_reference() {
+        let value = set_and_get_cell("=A1");
+        assert_eq!(value, Value::Error(Error::Cycle));
+    }
+
+    #[test]
+    fn test_cycle() {
+        let mut sheet = Sheet::new();
+        sheet
+            .set_cell("A1".parse().unwrap(), "=A2".to_string())
+            .unwrap();
+        sheet
+            .set_cell("A3".parse().unwrap(), "=A2".to_string())
+            .unwrap();
+        let values = sheet
+            .set_cell("A2".parse().unwrap(), "=A1".to_string())
+            .unwrap();
+
+        // both cells of the cycle as well as the dependent cell were updated
+        assert_eq!(values.len(), 3);
+        for address in ["A1", "A2", "A3"] {
+            let value: Value = sheet.value(&address.parse().unwrap()).into();
+            assert_eq!(value, Value::Error(Error::Cycle));
+        }
+    }
+
+    #[test]
+    fn test_cycle_removed() {
+        let mut sheet = Sheet::new();
+        sheet
+            .set_cell("A1".parse().unwrap(), "=A2".to_string())
+            .unwrap();
+        sheet
+            .set_cell("A2".parse().unwrap(), "=A1".to_string())
+            .unwrap();
+        sheet
+            .set_cell("B1".parse().unwrap(), "1".to_string())
+            .unwrap();
+
+        // unrelated cells are still calculated normally
+        sheet
+            .set_cell("B2".parse().unwrap(), "=B1".to_string())
+            .unwrap();
+        let value: Value = sheet.value(&"B2".parse().unwrap()).into();
+        assert_eq!(value, Value::Number(1.into()));
+
+        // breaking the cycle clears the error from all of its cells
+        sheet
+            .set_cell("A2".parse().unwrap(), "2".to_string())
+            .unwrap();
+        for address in ["A1", "A2"] {
+            let value: Value = sheet.value(&address.parse().unwrap()).into();
+            assert_eq!(value, Value::Number(2.into()));
+        }
+    }
+
+    #[test]
+    fn test_copy_range() {
+        let mut sheet = Sheet::new();
+        for (address, input) in [("A1", "1"), ("A2", "2"), ("A3", "3"), ("A4", "0b100")] {
+            sheet
+                .set_cell(address.parse().unwrap(), input.to_string())
+                .unwrap();
+        }
+        sheet
+            .set_cell("B1".parse().unwrap(), "=A1 * $A$1".to_string())
+            .unwrap();
+
+        let values = sheet.copy_range("B1".parse().unwrap(), "B2:B4".parse().unwrap());
+        assert_eq!(values.len(), 3);
+        for (address, input, value) in [
+            ("B2", "=A2 * $A$1", 2),
+            ("B3", "=A3 * $A$1", 3),
+            ("B4", "=A4 * $A$1", 4),
+        ] {
+            let address = address.parse().unwrap();
+            assert_eq!(sheet.cell(&address).unwrap().input(), input);
+            assert_eq!(values[&address], Value::Number(value.into()));
+        }
+
+        // the copies also depend on their new references
+        let values = sheet
+            .set_cell("A1".parse().unwrap(), "2".to_string())
+            .unwrap();
+        assert_eq!(values[&"B4".parse().unwrap()], Value::Number(8.into()));
+
+        // literal values are copied verbatim; empty cells are copied as well
+        sheet.copy_range("A3:A5".parse().unwrap(), "C1".parse().unwrap());
+        assert_eq!(sheet.cell(&"C1".parse().unwrap()).unwrap().input(), "3");
+        assert_eq!(sheet.cell(&"C2".parse().unwrap()).unwrap().input(), "0b100");
+        assert!(sheet.cell(&"C3".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_copy_range_repeated() {
+        let mut sheet = Sheet::new();
+        sheet
+            .set_cell("A1".parse().unwrap(), "1".to_string())
+            .unwrap();
+        sheet
+            .set_cell("A2".parse().unwrap(), "=A1 + 1".to_string())
+            .unwrap();
+
+        // the destination is twice the source's size, so the source is repeated
+        sheet.copy_range("A1:A2".parse().unwrap(), "B1:C4".parse().unwrap());
+        for (address, input) in [
+            ("B1", "1"),
+            ("B2", "=B1 + 1"),
+            ("B3", "1"),
+            ("B4", "=B3 + 1"),
+            ("C4", "=C3 + 1"),
+        ] {
+            let address = address.parse().unwrap();
+            assert_eq!(sheet.cell(&address).unwrap().input(), input);
+        }
+
+        // the destination is not a multiple of the source's size, so the source is pasted once
+        sheet.copy_range("A1:A2".parse().unwrap(), "D1:D3".parse().unwrap());
+        assert!(sheet.cell(&"D2".parse().unwrap()).is_some());
+        assert!(sheet.cell(&"D3".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_copy_range_invalid_reference() {
+        let mut sheet = Sheet::new();
+        sheet
+            .set_cell("B2".parse().unwrap(), "=A1 + 1".to_string())
+            .unwrap();
+
+        let values = sheet.copy_range("B2".parse().unwrap(), "A1".parse().unwrap());
+        let address = "A1".parse().unwrap();
+        assert_eq!(sheet.cell(&address).unwrap().input(), "=#REF! + 1");
+        assert_eq!(values[&address], Value::Error(Error::Reference));
+    }
+
+    fn input(sheet: &Sheet, address: &str) -> String {
+        sheet
+            .cell(&address.parse().unwrap())
+            .map_or("", Cell::input)
+            .to_string()
+    }
+
+    fn value(sheet: &Sheet, address: &str) -> Value {
+        sheet.value(&address.parse().unwrap()).into()
+    }
+
+    #[test]
+    fn test_insert_rows() {
+        let mut sheet = Sheet::with_standard_library();
+        for (address, input) in [
+            ("A1", "1"),
+            ("A2", "2"),
+            ("A3", "=A1 + $A$2"),
+            ("B1", "=sum(A1:A2)"),
+            ("B2", "=sum(A2:A3)"),
+        ] {
+            sheet
+                .set_cell(address.parse().unwrap(), input.to_string())
+                .unwrap();
+        }
+
+        let values = sheet.insert_rows("2".parse().unwrap(), 2);
+
+        // cells were moved, and references to moved cells adjusted
+        assert_eq!(input(&sheet, "A1"), "1");
+        assert_eq!(input(&sheet, "A2"), "");
+        assert_eq!(input(&sheet, "A4"), "2");
+        assert_eq!(input(&sheet, "A5"), "=A1 + $A$4");
+        assert_eq!(input(&sheet, "B1"), "=sum(A1:A4)");
+        assert_eq!(input(&sheet, "B4"), "=sum(A4:A5)");
+        assert_eq!(value(&sheet, "A5"), Value::Number(3.into()));
+        assert_eq!(value(&sheet, "B1"), Value::Number(3.into()));
+        assert_eq!(value(&sheet, "B4"), Value::Number(5.into()));
+
+        // the moved cells' old and new addresses are reported
+        assert_eq!(values[&"A2".parse().unwrap()], Value::Empty);
+        assert_eq!(values[&"A4".parse().unwrap()], Value::Number(2.into()));
+        assert!(!values.contains_key(&"A1".parse().unwrap()));
+
+        // the moved cells still update their dependents
+        sheet
+            .set_cell("A4".parse().unwrap(), "5".to_string())
+            .unwrap();
+        assert_eq!(value(&sheet, "A5"), Value::Number(6.into()));
+        assert_eq!(value(&sheet, "B1"), Value::Number(6.into()));
+    }
+
+    #[test]
+    fn test_restructure_position() {
+        let mut sheet = Sheet::with_standard_library();
+        for (address, input) in [
+            ("B2", "=row()"),
+            ("C2", "=column() * 10"),
+            ("D3", "=B2 + C2"),
+        ] {
+            sheet
+                .set_cell(address.parse().unwrap(), input.to_string())
+                .unwrap();
+        }
+
+        // moved formulas are recalculated even though their references didn't change
+        let values = sheet.insert_rows("1".parse().unwrap(), 1);
+        assert_eq!(values[&"B3".parse().unwrap()], Value::Number(3.into()));
+        assert_eq!(value(&sheet, "D4"), Value::Number(33.into()));
+
+        sheet.insert_cols("A".parse().unwrap(), 2);
+        assert_eq!(value(&sheet, "E3"), Value::Number(50.into()));
+        assert_eq!(value(&sheet, "F4"), Value::Number(53.into()));
+
+        sheet.delete_rows("1".parse().unwrap(), 2);
+        sheet.delete_cols("A".parse().unwrap(), 3);
+        assert_eq!(value(&sheet, "A1"), Value::Number(1.into()));
+        assert_eq!(value(&sheet, "B1"), Value::Number(20.into()));
+        assert_eq!(value(&sheet, "C2"), Value::Number(21.into()));
+    }
+
+    #[test]
+    fn test_delete_rows() {
+        let mut sheet = Sheet::with_standard_library();
+        for (address, input) in [
+            ("A1", "1"),
+            ("A2", "2"),
+            ("A3", "3"),
+            ("A4", "=A2 + 1"),
+            ("A5", "=A3"),
+            ("B1", "=sum(A1:A3)"),
+            ("B2", "=sum(A2:A2)"),
+        ] {
+            sheet
+                .set_cell(address.parse().unwrap(), input.to_string())
+                .unwrap();
+        }
+
+        let values = sheet.delete_rows("2".parse().unwrap(), 1);
+
+        // references to deleted cells are errors; ranges shrink
+        assert_eq!(input(&sheet, "A2"), "3");
+        assert_eq!(input(&sheet, "A3"), "=#REF! + 1");
+        assert_eq!(input(&sheet, "A4"), "=A2");
+        assert_eq!(input(&sheet, "B1"), "=sum(A1:A2)");
+        assert_eq!(input(&sheet, "B2"), "");
+        assert_eq!(input(&sheet, "A5"), "");
+        assert_eq!(value(&sheet, "A3"), Value::Error(Error::Reference));
+        assert_eq!(value(&sheet, "A4"), Value::Number(3.into()));
+        assert_eq!(value(&sheet, "B1"), Value::Number(4.into()));
+
+        assert_eq!(
+            values[&"A3".parse().unwrap()],
+            Value::Error(Error::Reference)
+        );
+        assert_eq!(values[&"A5".parse().unwrap()], Value::Empty);
+        assert_eq!(values[&"B1".parse().unwrap()], Value::Number(4.into()));
+
+        // a range that was deleted completely is an error
+        sheet
+            .set_cell("C1".parse().unwrap(), "=sum(A1:A2)".to_string())
+            .unwrap();
+        sheet.delete_rows("1".parse().unwrap(), 2);
+        assert_eq!(input(&sheet, "C1"), "");
+        assert_eq!(input(&sheet, "B1"), "");
+    }
+
+    #[test]
+    fn test_insert_delete_cols() {
+        let mut sheet = Sheet::with_standard_library();
+        for (address, input) in [
+            ("A1", "1"),
+            ("B1", "2"),
+            ("C1", "=$A$1 + B1"),
+            ("A2", "=sum(A1:C1)"),
+        ] {
+            sheet
+                .set_cell(address.parse().unwrap(), input.to_string())
+                .unwrap();
+        }
+
+        sheet.insert_cols("A".parse().unwrap(), 1);
+        assert_eq!(input(&sheet, "D1"), "=$B$1 + C1");
+        assert_eq!(input(&sheet, "B2"), "=sum(B1:D1)");
+        assert_eq!(value(&sheet, "B2"), Value::Number(6.into()));
+
+        sheet.delete_cols("C".parse().unwrap(), 1);
+        assert_eq!(input(&sheet, "C1"), "=$B$1 + #REF!");
+        assert_eq!(input(&sheet, "B2"), "=sum(B1:C1)");
+        assert_eq!(value(&sheet, "B2"), Value::Error(Error::Reference));
+    }
+
+    #[test]
+    fn test_save_load() {
+        let mut sheet = Sheet::new();
+        for (address, input) in [("A1", "1"), ("A2", "=A1 * 2"), ("B1", "\"foo\"")] {
+            sheet
+                .set_cell(address.parse().unwrap(), input.to_string())
+                .unwrap();
+        }
+        let mut buffer = Vec::new();
+        sheet.save(&mut buffer).unwrap();
+
+        let mut loaded = Sheet::new();
+        loaded
+            .set_cell("C1".parse().unwrap(), "1".to_string())
+            .unwrap();
+        let values = loaded.load(buffer.as_slice()).unwrap();
+        assert_eq!(values.len(), 3);
+        assert_eq!(input(&loaded, "A2"), "=A1 * 2");
+        assert_eq!(value(&loaded, "A2"), Value::Number(2.into()));
+        assert_eq!(input(&loaded, "C1"), "");
+
+        // the dependency graph was restored as well
+        loaded
+            .set_cell("A1".parse().unwrap(), "2".to_string())
+            .unwrap();
+        assert_eq!(value(&loaded, "A2"), Value::Number(4.into()));
+
+        // invalid files leave the sheet unchanged
+        let file =
+            r#"{ "version": 1, "sheets": [{ "name": "Sheet1", "cells": { "A1": "=1 +" } }] }"#;
+        assert!(matches!(
+            loaded.load(file.as_bytes()),
+            Err(FileError::InvalidCell { .. })
+        ));
+        assert_eq!(value(&loaded, "A2"), Value::Number(4.into()));
+    }
+
     #[test]
     fn test_function() {
         let mut sheet = Sheet::new();
         sheet.set_function("fn", |arguments| match arguments {
             [arg] => arg.clone(),
-            _ => Value::Error(Error::Type),
+            _ => Value::Error(Error::Value),
         });
 
         sheet
@@ -261,27 +1496,107 @@ mod tests {
     }
 
     #[test]
-    fn test_sum_function() {
-        fn sum(arguments: &[Value]) -> Value {
-            fn sum(args: &[Value]) -> Result<BigDecimal, Error> {
-                let mut sum = 0.into();
+    fn test_lazy_function() {
+        use crate::formula::expression::Expression;
 
-                for arg in args {
-                    let arg = arg.as_number()?;
-                    sum += arg;
+        let mut sheet = Sheet::new();
+        // the first argument that is not an error; later arguments are not evaluated
+        sheet.set_lazy_function("first", |arguments, _context| {
+            arguments
+                .iter()
+                .map(Argument::evaluate)
+                .find(|value| !matches!(value, Value::Error(_)))
+                .unwrap_or(Value::Error(Error::NotAvailable))
+        });
+        // the number of cells a range refers to, without evaluating it
+        sheet.set_lazy_function("size", |arguments, _context| match arguments {
+            [argument] => match argument.expression() {
+                Expression::Range { reference, .. } => {
+                    let range = reference.range();
+                    Value::Number((range.width() * range.height()).into())
                 }
+                _ => Value::Error(Error::Value),
+            },
+            _ => Value::Error(Error::Value),
+        });
+        // the row of the cell that is evaluated
+        sheet.set_lazy_function("here", |_arguments, context| match context.cell() {
+            Some(cell) => Value::Number(cell.row().get().into()),
+            None => Value::Error(Error::Value),
+        });
 
-                Ok(sum)
-            }
+        sheet
+            .set_cell("A1".parse().unwrap(), "=first(1/0, 2, 3)".to_string())
+            .unwrap();
+        sheet
+            .set_cell("A2".parse().unwrap(), "=size(B1:C10)".to_string())
+            .unwrap();
+        sheet
+            .set_cell("A3".parse().unwrap(), "=here()".to_string())
+            .unwrap();
+        assert_eq!(value(&sheet, "A1"), Value::Number(2.into()));
+        assert_eq!(value(&sheet, "A2"), Value::Number(20.into()));
+        assert_eq!(value(&sheet, "A3"), Value::Number(3.into()));
 
-            match sum(arguments) {
-                Ok(value) => Value::Number(value),
-                Err(error) => Value::Error(error),
-            }
+        // outside of a cell, there is no current cell
+        let here: Formula = "=here()".parse().unwrap();
+        assert_eq!(sheet.evaluate(&here), Value::Error(Error::Value));
+    }
+
+    #[test]
+    fn test_volatile_function() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        use crate::formula::signature::Signature;
+
+        let mut sheet = Sheet::with_standard_library();
+        assert!(sheet.function("now").unwrap().signature().is_volatile());
+        assert!(!sheet.function("sum").unwrap().signature().is_volatile());
+
+        // a function whose result changes on every call
+        static TICKS: AtomicU32 = AtomicU32::new(0);
+        sheet.register_function(Definition::value(
+            Signature::any("tick").volatile(),
+            "",
+            |_| Value::Number(TICKS.fetch_add(1, Ordering::SeqCst).into()),
+        ));
+        sheet.define_name("Ticks", "tick() * 10").unwrap();
+        for (address, input) in [
+            ("A1", "=tick()"),
+            ("A2", "=A1 + 1"),
+            ("A3", "=if(FALSE, tick(), 0)"),
+            ("B1", "=Ticks"),
+        ] {
+            sheet
+                .set_cell(address.parse().unwrap(), input.to_string())
+                .unwrap();
         }
 
-        let mut sheet = Sheet::new();
-        sheet.set_function("sum", sum);
+        // cells calling a volatile function, directly or via a name, and their dependents are recalculated on any change
+        let before = value(&sheet, "A1");
+        let values = sheet
+            .set_cell("C1".parse().unwrap(), "1".to_string())
+            .unwrap();
+        assert_ne!(value(&sheet, "A1"), before);
+        for address in ["A1", "A2", "B1", "C1"] {
+            assert!(values.contains_key(&address.parse().unwrap()));
+        }
+        // a volatile function that isn't called doesn't count
+        assert!(!values.contains_key(&"A3".parse().unwrap()));
+
+        sheet
+            .set_cell("A1".parse().unwrap(), "1".to_string())
+            .unwrap();
+        let values = sheet
+            .set_cell("C1".parse().unwrap(), "2".to_string())
+            .unwrap();
+        assert_eq!(values.len(), 2);
+        assert!(values.contains_key(&"B1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_sum_function() {
+        let mut sheet = Sheet::with_standard_library();
 
         sheet
             .set_cell("A1".parse().unwrap(), "=sum(1,2,3)".to_string())
@@ -290,4 +1605,102 @@ mod tests {
         let value: Value = sheet.value(&"A1".parse().unwrap()).into();
         assert_eq!(value, Value::Number(6.into()));
     }
+
+    #[test]
+    fn test_function_names() {
+        let mut sheet = Sheet::with_standard_library();
+        sheet.register_localized_name("de", "sum", "Summe").unwrap();
+
+        let cells = [
+            ("A1", "=SUM(1, 2)", "=sum(1, 2)"),
+            (
+                "A2",
+                "=Concatenate(\"a\", IF(TRUE, \"b\"))",
+                "=concat(\"a\", if(TRUE, \"b\"))",
+            ),
+            ("A3", "=summe(A1,  1)", "=sum(A1, 1)"),
+            // inputs without names to normalize are kept as they are
+            ("A4", "=sum(A1,  1)", "=sum(A1,  1)"),
+            ("A5", "=unknown(1)", "=unknown(1)"),
+        ];
+        for (address, input, _) in cells {
+            sheet
+                .set_cell(address.parse().unwrap(), input.to_string())
+                .unwrap();
+        }
+        for (address, _, normalized) in cells {
+            let cell = sheet.cell(&address.parse().unwrap()).unwrap();
+            assert_eq!(cell.input(), normalized);
+        }
+
+        let value = |address: &str| -> Value { sheet.value(&address.parse().unwrap()).into() };
+        assert_eq!(value("A1"), Value::Number(3.into()));
+        assert_eq!(value("A2"), Value::String("ab".into()));
+        assert_eq!(value("A3"), Value::Number(4.into()));
+        assert_eq!(value("A5"), Value::Error(Error::Name));
+    }
+
+    #[test]
+    fn test_range() {
+        let mut sheet = Sheet::with_standard_library();
+
+        sheet
+            .set_cell("C1".parse().unwrap(), "=sum(B2:A1)".to_string())
+            .unwrap();
+        sheet
+            .set_cell("A1".parse().unwrap(), "1".to_string())
+            .unwrap();
+        sheet
+            .set_cell("B1".parse().unwrap(), "\"foo\"".to_string())
+            .unwrap();
+        let values = sheet
+            .set_cell("B2".parse().unwrap(), "2".to_string())
+            .unwrap();
+
+        // changing any cell in the range updates the sum
+        assert!(values.contains_key(&"C1".parse().unwrap()));
+        let value: Value = sheet.value(&"C1".parse().unwrap()).into();
+        assert_eq!(value, Value::Number(3.into()));
+
+        // a range that is not passed to a function can't be the value of a cell
+        sheet
+            .set_cell("C2".parse().unwrap(), "=A1:B2".to_string())
+            .unwrap();
+        let value: Value = sheet.value(&"C2".parse().unwrap()).into();
+        assert_eq!(value, Value::Error(Error::Value));
+
+        // ranges that are too large aren't evaluated
+        sheet
+            .set_cell("D1".parse().unwrap(), "=rows(L1:U10000)".to_string())
+            .unwrap();
+        sheet
+            .set_cell("D2".parse().unwrap(), "=sum(A2:XFD1048576)".to_string())
+            .unwrap();
+        let value: Value = sheet.value(&"D1".parse().unwrap()).into();
+        assert_eq!(value, Value::Number(10000.into()));
+        let value: Value = sheet.value(&"D2".parse().unwrap()).into();
+        assert_eq!(value, Value::Error(Error::Number));
+        assert!(sheet
+            .worksheet()
+            .dependents("Z5".parse().unwrap())
+            .is_empty());
+
+        // errors in the range's cells are kept as they are
+        sheet
+            .set_cell("A2".parse().unwrap(), "=#REF!".to_string())
+            .unwrap();
+        sheet
+            .set_cell("C3".parse().unwrap(), "=counta(A1:A3)".to_string())
+            .unwrap();
+        sheet
+            .set_cell(
+                "C4".parse().unwrap(),
+                "=iferror(index(A1:A2, 1), 0)".to_string(),
+            )
+            .unwrap();
+        let value: Value = sheet.value(&"C3".parse().unwrap()).into();
+        assert_eq!(value, Value::Number(2.into()));
+        let value: Value = sheet.value(&"C4".parse().unwrap()).into();
+        assert_eq!(value, Value::Number(1.into()));
+    }
 }
@@ -0,0 +1,387 @@
+//! Names that formulas can use instead of references and values, as in `=sum(Revenue) * TaxRate`.
+//!
+//! Names are defined per sheet. A name stands for an expression: usually a cell such as `$B$1`
+//! or a range such as `A2:A13`, but also a constant such as `0.19` or a formula such as `=B1 / 100`.
+//! Names are nodes of the sheet's dependency graph, so formulas using a name are recalculated
+//! when its definition or the cells it refers to change. Names are case-insensitive.
+
+use std::collections::{HashMap, HashSet};
+
+use thiserror::Error;
+
+use crate::address::CellAddress;
+use crate::file::{FileError, SheetFile};
+use crate::formula::expression::Expression;
+use crate::formula::registry::FunctionRegistry;
+use crate::formula::{same_name, Evaluate};
+use crate::parser::{parse_name_definition_complete, ParseFormulaError};
+
+use super::{canonical_function, Node, Worksheet};
+
+/// Identifies a name independently of its spelling, which may change.
+pub(crate) type NameId = u32;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum NameError {
+    #[error("'{0}' is not a valid name")]
+    InvalidName(String),
+    #[error("there is no name '{0}'")]
+    UnknownName(String),
+    #[error("there already is a name '{0}'")]
+    DuplicateName(String),
+    #[error("the definition of '{0}' refers to itself")]
+    Cycle(String),
+    #[error("the definition of '{0}' refers to another sheet")]
+    ExternalReference(String),
+    #[error(transparent)]
+    Parse(#[from] ParseFormulaError),
+}
+
+/// A name and the expression it stands for
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Name {
+    name: String,
+    pub(super) expression: Expression,
+}
+
+impl Name {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    /// The definition as it can be entered again, e.g. `$B$1` or `0.19`
+    pub fn definition(&self) -> String {
+        self.expression.to_string()
+    }
+}
+
+/// Names must be identifiers that are neither references nor booleans, so that formulas can tell them apart.
+fn check_name(name: &str) -> Result<(), NameError> {
+    match parse_name_definition_complete(name) {
+        Ok(Expression::Name(parsed)) if parsed == name => Ok(()),
+        _ => Err(NameError::InvalidName(name.to_string())),
+    }
+}
+
+/// Parses the definition of a name, with the functions it calls normalized as in cells.
+/// Definitions must not refer to other sheets.
+pub(crate) fn parse_definition(
+    functions: &FunctionRegistry,
+    name: &str,
+    definition: &str,
+) -> Result<Expression, NameError> {
+    check_name(name)?;
+    let expression = parse_name_definition_complete(definition)?
+        .rename_functions(&|function| canonical_function(functions, function));
+
+    let mut external = false;
+    expression.visit_external_dependencies(&mut |_, _| external = true);
+    if external {
+        return Err(NameError::ExternalReference(name.to_string()));
+    }
+    Ok(expression)
+}
+
+/// Whether `expression` uses `name`, either directly or via the definitions of other names,
+/// which `definition` looks up.
+fn uses(
+    expression: &Expression,
+    name: &str,
+    definition: &dyn Fn(&str) -> Option<Expression>,
+) -> bool {
+    fn visit(
+        expression: &Expression,
+        name: &str,
+        definition: &dyn Fn(&str) -> Option<Expression>,
+        visited: &mut HashSet<String>,
+    ) -> bool {
+        let mut found = false;
+        expression.visit_names(&mut |used| {
+            if found || same_name(used, name) {
+                found = true;
+            } else if visited.insert(used.to_lowercase()) {
+                if let Some(expression) = definition(used) {
+                    found = visit(&expression, name, definition, visited);
+                }
+            }
+        });
+        found
+    }
+
+    visit(expression, name, definition, &mut HashSet::new())
+}
+
+/// Parses and checks the names stored in a sheet file, failing at the first invalid one.
+pub(crate) fn parse_names(
+    functions: &FunctionRegistry,
+    sheet: &SheetFile,
+) -> Result<HashMap<NameId, Name>, FileError> {
+    let invalid = |name: &str| FileError::InvalidName {
+        sheet: sheet.name.clone(),
+        name: name.to_string(),
+    };
+
+    let mut names = HashMap::new();
+    for (id, (name, definition)) in (0..).zip(&sheet.names) {
+        let expression =
+            parse_definition(functions, name, definition).map_err(|_| invalid(name))?;
+        let name = Name {
+            name: name.to_string(),
+            expression,
+        };
+        names.insert(id, name);
+    }
+
+    let definition = |used: &str| {
+        names
+            .values()
+            .find(|name| same_name(&name.name, used))
+            .map(|name| name.expression.clone())
+    };
+    for name in names.values() {
+        if uses(&name.expression, &name.name, &definition) {
+            return Err(invalid(&name.name));
+        }
+    }
+    Ok(names)
+}
+
+impl Worksheet {
+    /// The definition of a name, if it exists
+    pub fn defined_name(&self, name: &str) -> Option<&Name> {
+        self.find_name(name).map(|(_, name)| name)
+    }
+
+    /// All names defined on this sheet, ordered by name
+    pub fn defined_names(&self) -> Vec<&Name> {
+        let mut names: Vec<_> = self.names.values().collect();
+        names.sort_by_key(|name| name.name.to_lowercase());
+        names
+    }
+
+    pub(crate) fn find_name(&self, name: &str) -> Option<(NameId, &Name)> {
+        self.names
+            .iter()
+            .find(|(_, defined)| same_name(&defined.name, name))
+            .map(|(id, defined)| (*id, defined))
+    }
+
+    /// Stores the definition of a name and updates the dependency graph accordingly.
+    /// Returns the cells using the name, which still need to be recalculated.
+    pub(crate) fn store_name(
+        &mut self,
+        name: &str,
+        expression: Expression,
+    ) -> Result<Vec<CellAddress>, NameError> {
+        let definition = |used: &str| self.defined_name(used).map(|name| name.expression.clone());
+        if uses(&expression, name, &definition) {
+            return Err(NameError::Cycle(name.to_string()));
+        }
+
+        let id = match self.find_name(name) {
+            Some((id, _)) => id,
+            None => {
+                let id = self.next_name_id;
+                self.next_name_id += 1;
+                id
+            }
+        };
+        self.names.insert(
+            id,
+            Name {
+                name: name.to_string(),
+                expression,
+            },
+        );
+
+        // cells and names may have used the name before it was defined, so the whole graph is rebuilt
+        self.rebuild_dependents();
+        Ok(self.cell_dependents(Node::Name(id)))
+    }
+
+    /// Renames a name and updates all formulas and definitions using it.
+    /// Returns the cells using the name under either spelling, which still need to be recalculated.
+    pub(crate) fn change_name(
+        &mut self,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<CellAddress>, NameError> {
+        check_name(to)?;
+        let (id, renamed) = self
+            .find_name(from)
+            .ok_or_else(|| NameError::UnknownName(from.to_string()))?;
+        if !same_name(from, to) && self.find_name(to).is_some() {
+            return Err(NameError::DuplicateName(to.to_string()));
+        }
+
+        // formulas that used `to` while it was undefined now use the renamed definition, which must not use them
+        let expression = renamed.expression.rename_name(from, to);
+        let definition = |used: &str| {
+            if same_name(used, to) {
+                Some(expression.clone())
+            } else if same_name(used, from) {
+                None
+            } else {
+                self.defined_name(used)
+                    .map(|name| name.expression.rename_name(from, to))
+            }
+        };
+        if uses(&expression, to, &definition) {
+            return Err(NameError::Cycle(to.to_string()));
+        }
+
+        let mut users = self.cell_dependents(Node::Name(id));
+        for name in self.names.values_mut() {
+            name.expression = name.expression.rename_name(from, to);
+        }
+        self.names.get_mut(&id).expect("the name exists").name = to.to_string();
+        for (address, cell) in &mut self.cells {
+            let formula = cell.formula.rename_name(from, to);
+            if formula != cell.formula {
+                cell.input = formula.to_string();
+                cell.formula = formula;
+                users.push(*address);
+            }
+        }
+
+        self.rebuild_dependents();
+        users.extend(self.cell_dependents(Node::Name(id)));
+        Ok(users)
+    }
+
+    /// Removes a name and updates the dependency graph accordingly.
+    /// Returns the cells that used the name, which still need to be recalculated.
+    pub(crate) fn remove_name(&mut self, name: &str) -> Result<Vec<CellAddress>, NameError> {
+        let (id, _) = self
+            .find_name(name)
+            .ok_or_else(|| NameError::UnknownName(name.to_string()))?;
+
+        let users = self.cell_dependents(Node::Name(id));
+        self.names.remove(&id);
+        self.rebuild_dependents();
+        Ok(users)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::sheet::Sheet;
+    use crate::value::{Error, Value};
+
+    fn sheet(cells: &[(&str, &str)]) -> Sheet {
+        let mut sheet = Sheet::with_standard_library();
+        for (address, input) in cells {
+            sheet
+                .set_cell(address.parse().unwrap(), input.to_string())
+                .unwrap();
+        }
+        sheet
+    }
+
+    fn value(sheet: &Sheet, address: &str) -> Value {
+        sheet.value(&address.parse().unwrap()).into()
+    }
+
+    fn number(n: i32) -> Value {
+        Value::Number(n.into())
+    }
+
+    #[test]
+    fn test_define_name() {
+        let mut sheet = sheet(&[
+            ("A1", "10"),
+            ("A2", "20"),
+            ("B1", "=sum(Revenue) * TaxRate"),
+            ("B2", "=Revenue"),
+        ]);
+        assert_eq!(value(&sheet, "B1"), Value::Error(Error::Name));
+
+        sheet.define_name("Revenue", "A1:A2").unwrap();
+        let changes = sheet.define_name("TaxRate", "=2").unwrap();
+        assert_eq!(changes[&"B1".parse().unwrap()], number(60));
+        // a range can't be the value of a cell
+        assert_eq!(value(&sheet, "B2"), Value::Error(Error::Value));
+
+        // redefining a name and changing the cells it refers to recalculates its users
+        let changes = sheet.define_name("taxrate", "A1 / 10").unwrap();
+        assert_eq!(changes[&"B1".parse().unwrap()], number(30));
+        sheet
+            .set_cell("A1".parse().unwrap(), "20".to_string())
+            .unwrap();
+        assert_eq!(value(&sheet, "B1"), number(80));
+
+        // names can use other names, but not themselves
+        sheet.define_name("Total", "sum(Revenue)").unwrap();
+        assert_eq!(
+            sheet.define_name("Revenue", "Total"),
+            Err(NameError::Cycle("Revenue".to_string()))
+        );
+        assert_eq!(
+            sheet.defined_name("TaxRate").unwrap().definition(),
+            "A1 / 10"
+        );
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        let mut sheet = Sheet::new();
+        for name in ["A1", "TRUE", "1x", "tax rate", "sum()"] {
+            assert_eq!(
+                sheet.define_name(name, "1"),
+                Err(NameError::InvalidName(name.to_string()))
+            );
+        }
+        assert!(matches!(
+            sheet.define_name("x", "1 +"),
+            Err(NameError::Parse(_))
+        ));
+        assert_eq!(
+            sheet.define_name("x", "Sheet2!A1"),
+            Err(NameError::ExternalReference("x".to_string()))
+        );
+        assert!(sheet.define_name("Q1Total", "1").is_ok());
+    }
+
+    #[test]
+    fn test_rename_delete_name() {
+        let mut sheet = sheet(&[("A1", "=Rate * 2"), ("A2", "=NewRate")]);
+        sheet.define_name("Rate", "3").unwrap();
+        sheet.define_name("Double", "Rate * 2").unwrap();
+        assert_eq!(value(&sheet, "A1"), number(6));
+
+        let changes = sheet.rename_name("rate", "NewRate").unwrap();
+        assert_eq!(changes[&"A2".parse().unwrap()], number(3));
+        assert_eq!(
+            sheet.cell(&"A1".parse().unwrap()).unwrap().input(),
+            "=NewRate * 2"
+        );
+        assert_eq!(
+            sheet.defined_name("Double").unwrap().definition(),
+            "NewRate * 2"
+        );
+        assert_eq!(
+            sheet.rename_name("Rate", "x"),
+            Err(NameError::UnknownName("Rate".to_string()))
+        );
+        assert_eq!(
+            sheet.rename_name("NewRate", "double"),
+            Err(NameError::DuplicateName("double".to_string()))
+        );
+
+        let changes = sheet.delete_name("NEWRATE").unwrap();
+        assert_eq!(changes[&"A1".parse().unwrap()], Value::Error(Error::Name));
+        assert_eq!(changes[&"A2".parse().unwrap()], Value::Error(Error::Name));
+        let names: Vec<_> = sheet
+            .defined_names()
+            .iter()
+            .map(|name| name.name())
+            .collect();
+        assert_eq!(names, ["Double"]);
+    }
+}
@@ -2,22 +2,37 @@
 //! For example, if `=1+1` is put into a cell, although that is a formula,
 //! the *value* of the cell would be two.
 
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::fmt;
+use std::sync::OnceLock;
 
-use bigdecimal::BigDecimal;
+use bigdecimal::{BigDecimal, Zero};
 use serde::{Serialize, Serializer};
 
+use crate::datetime;
+
 /// The value of a cell
-#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
 #[serde(tag = "type", content = "value")]
 pub enum Value {
     /// empty cells have this value
+    #[default]
     Empty,
     /// the value of the cell is a number
     #[serde(serialize_with = "serialize_bigdecimal")]
     Number(BigDecimal),
     /// the value of the cell is a string
     String(String),
+    /// the value of the cell is `TRUE` or `FALSE`
+    Boolean(bool),
+    /// a date and/or time, represented by its serial number (see [`datetime`]).
+    /// It can be used like a number, but is displayed as a date or time.
+    #[serde(serialize_with = "serialize_datetime")]
+    DateTime(BigDecimal),
+    /// a rectangular array of values, as a range such as `A1:B3` evaluates to.
+    /// Arrays are passed to functions but can't be the value of a cell.
+    Array(Array),
     /// any kind of error
     Error(Error),
 }
@@ -29,21 +44,117 @@ where
     serializer.serialize_newtype_struct("$tauri_calc::bigdecimal", &number.to_string())
 }
 
-/// The value of a cell
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+/// Dates are serialized as they are displayed, e.g. `2024-02-29`
+fn serialize_datetime<S>(serial: &BigDecimal, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let formatted = Value::DateTime(serial.clone()).to_string();
+    serializer.serialize_newtype_struct("$tauri_calc::datetime", &formatted)
+}
+
+/// A rectangular array of values, stored row by row
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Array {
+    rows: u32,
+    cols: u32,
+    values: Vec<Value>,
+}
+
+impl Array {
+    pub fn new(rows: u32, cols: u32, values: Vec<Value>) -> Self {
+        assert_eq!(
+            values.len(),
+            rows as usize * cols as usize,
+            "number of values doesn't match array dimensions"
+        );
+        Self { rows, cols, values }
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    pub fn cols(&self) -> u32 {
+        self.cols
+    }
+
+    /// all values of the array, row by row
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// the value at the given zero-based row and column, if it is inside the array
+    pub fn get(&self, row: u32, col: u32) -> Option<&Value> {
+        if row >= self.rows || col >= self.cols {
+            return None;
+        }
+        self.values
+            .get(row as usize * self.cols as usize + col as usize)
+    }
+}
+
+/// An error value, which is the result of a calculation that failed.
+/// Errors propagate through most calculations, so that a cell depending on an error cell is an error as well.
+/// Each kind of error has a code such as `#DIV/0!` that it is displayed as, and that can be used in formulas.
+/// Errors are serialized by their variant names, e.g. `"DivisionByZero"`;
+/// [`Error::Value`] and [`Error::Name`] keep their original names `"Type"` and `"Undefined"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
 pub enum Error {
-    /// a value could not be interpreted as a certain type as necessary
-    Type,
-    /// a nonexistent identifier was used
-    Undefined,
+    /// `#VALUE!`: a value could not be interpreted as a certain type as necessary
+    #[serde(rename = "Type")]
+    Value,
+    /// `#NAME?`: a nonexistent identifier, such as an unknown function, was used
+    #[serde(rename = "Undefined")]
+    Name,
+    /// `#CYCLE`: the cell is part of a circular reference
+    Cycle,
+    /// `#DIV/0!`: a number was divided by zero
+    DivisionByZero,
+    /// `#NUM!`: the result of a calculation is not a valid number
+    Number,
+    /// `#REF!`: a reference points to a cell that does not exist
+    Reference,
+    /// `#N/A`: a value is not available, e.g. because a lookup didn't find anything
+    NotAvailable,
+}
+
+impl Error {
+    /// all kinds of errors
+    pub const ALL: [Error; 7] = [
+        Error::Value,
+        Error::Name,
+        Error::Cycle,
+        Error::DivisionByZero,
+        Error::Number,
+        Error::Reference,
+        Error::NotAvailable,
+    ];
+
+    /// the code the error is displayed as, such as `#DIV/0!`
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Value => "#VALUE!",
+            Self::Name => "#NAME?",
+            Self::Cycle => "#CYCLE",
+            Self::DivisionByZero => "#DIV/0!",
+            Self::Number => "#NUM!",
+            Self::Reference => "#REF!",
+            Self::NotAvailable => "#N/A",
+        }
+    }
 }
 
 impl Value {
+    /// The value as a number; dates and times are their serial numbers, and empty values are zero.
     pub fn as_number(&self) -> Result<&BigDecimal, Error> {
+        static ZERO: OnceLock<BigDecimal> = OnceLock::new();
+
         match self {
-            Self::Number(value) => Ok(value),
+            Self::Number(value) | Self::DateTime(value) => Ok(value),
+            Self::Empty => Ok(ZERO.get_or_init(BigDecimal::zero)),
             Self::Error(error) => Err(*error),
-            _ => Err(Error::Type),
+            _ => Err(Error::Value),
         }
     }
 
@@ -51,14 +162,78 @@ impl Value {
         match self {
             Self::String(value) => Ok(value),
             Self::Error(error) => Err(*error),
-            _ => Err(Error::Type),
+            _ => Err(Error::Value),
         }
     }
-}
 
-impl Default for Value {
-    fn default() -> Self {
-        Value::Empty
+    /// The value as a condition: booleans are used as they are, numbers are true unless they are zero,
+    /// and empty values are false.
+    pub fn as_boolean(&self) -> Result<bool, Error> {
+        match self {
+            Self::Boolean(value) => Ok(*value),
+            Self::Number(value) | Self::DateTime(value) => Ok(!value.is_zero()),
+            Self::Empty => Ok(false),
+            Self::Error(error) => Err(*error),
+            _ => Err(Error::Value),
+        }
+    }
+
+    /// The value as text, as the `&` operator and text functions use it: numbers are written without
+    /// trailing zeros, dates and times as they are displayed, booleans as `TRUE` or `FALSE`,
+    /// and empty values are empty text.
+    pub fn to_text(&self) -> Result<Cow<'_, str>, Error> {
+        match self {
+            Self::Empty => Ok(Cow::Borrowed("")),
+            Self::Number(value) => Ok(Cow::Owned(value.normalized().to_string())),
+            Self::String(value) => Ok(Cow::Borrowed(value)),
+            Self::Boolean(_) | Self::DateTime(_) => Ok(Cow::Owned(self.to_string())),
+            Self::Error(error) => Err(*error),
+            Self::Array(_) => Err(Error::Value),
+        }
+    }
+
+    /// Compares two values as the comparison operators do.
+    ///
+    /// Values of the same type are compared naturally, strings case-insensitively and `FALSE` before `TRUE`;
+    /// dates and times are compared as numbers. Values of different types are ordered numbers first,
+    /// then strings, then booleans.
+    /// An empty value is compared as the other value's type's "zero", i.e. `0`, `""` or `FALSE`.
+    /// Errors are propagated, and arrays can't be compared.
+    pub fn compare(&self, other: &Value) -> Result<Ordering, Error> {
+        fn rank(value: &Value) -> u8 {
+            match value {
+                Value::Number(_) | Value::DateTime(_) => 0,
+                Value::String(_) => 1,
+                _ => 2,
+            }
+        }
+
+        match (self, other) {
+            (Self::Error(error), _) | (_, Self::Error(error)) => Err(*error),
+            (Self::Array(_), _) | (_, Self::Array(_)) => Err(Error::Value),
+            (Self::Empty, Self::Empty) => Ok(Ordering::Equal),
+            (Self::Empty, other) => other.zero().compare(other),
+            (value, Self::Empty) => value.compare(&value.zero()),
+            (
+                Self::Number(left) | Self::DateTime(left),
+                Self::Number(right) | Self::DateTime(right),
+            ) => Ok(left.cmp(right)),
+            (Self::String(left), Self::String(right)) => {
+                Ok(left.to_lowercase().cmp(&right.to_lowercase()))
+            }
+            (Self::Boolean(left), Self::Boolean(right)) => Ok(left.cmp(right)),
+            (left, right) => Ok(rank(left).cmp(&rank(right))),
+        }
+    }
+
+    /// the value an empty value is compared as, for values of this type
+    fn zero(&self) -> Value {
+        match self {
+            Self::Number(_) | Self::DateTime(_) => Self::Number(BigDecimal::zero()),
+            Self::String(_) => Self::String(String::new()),
+            Self::Boolean(_) => Self::Boolean(false),
+            value => value.clone(),
+        }
     }
 }
 
@@ -74,17 +249,41 @@ impl fmt::Display for Value {
             Self::Empty => write!(f, ""),
             Self::Number(value) => write!(f, "{}", value),
             Self::String(value) => write!(f, "{:?}", value),
+            Self::Boolean(true) => write!(f, "TRUE"),
+            Self::Boolean(false) => write!(f, "FALSE"),
+            // serial numbers outside of the supported range are shown as they are
+            Self::DateTime(serial) => match datetime::format(serial) {
+                Some(formatted) => write!(f, "{}", formatted),
+                None => write!(f, "{}", serial),
+            },
+            Self::Array(array) => write!(f, "{}", array),
             Self::Error(error) => write!(f, "{}", error),
         }
     }
 }
 
-impl fmt::Display for Error {
+impl fmt::Display for Array {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::Type => write!(f, "#TYPE"),
-            Self::Undefined => write!(f, "#UNDEFINED"),
+        // the same notation as array constants in other spreadsheet applications: `{1, 2; 3, 4}`
+        write!(f, "{{")?;
+        for (i, row) in self.values.chunks(self.cols.max(1) as usize).enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            for (j, value) in row.iter().enumerate() {
+                if j > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", value)?;
+            }
         }
+        write!(f, "}}")
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.code())
     }
 }
 
@@ -97,7 +296,49 @@ mod tests {
         assert_eq!(format!("{}", Value::Empty), "");
         assert_eq!(format!("{}", Value::Number(1.into())), "1");
         assert_eq!(format!("{}", Value::String("foo".into())), "\"foo\"");
-        assert_eq!(format!("{}", Value::Error(Error::Type)), "#TYPE");
+        assert_eq!(format!("{}", Value::Boolean(true)), "TRUE");
+        assert_eq!(
+            format!("{}", Value::DateTime("45351.5".parse().unwrap())),
+            "2024-02-29 12:00:00"
+        );
+        assert_eq!(format!("{}", Value::Error(Error::Value)), "#VALUE!");
+        assert_eq!(format!("{}", Value::Error(Error::NotAvailable)), "#N/A");
+        assert_eq!(format!("{}", Value::Error(Error::Cycle)), "#CYCLE");
+
+        let array = Array::new(
+            2,
+            2,
+            vec![
+                Value::Number(1.into()),
+                Value::String("foo".into()),
+                Value::Empty,
+                Value::Error(Error::Value),
+            ],
+        );
+        assert_eq!(array.get(0, 1), Some(&Value::String("foo".into())));
+        assert_eq!(array.get(1, 0), Some(&Value::Empty));
+        assert_eq!(array.get(2, 0), None);
+        assert_eq!(
+            format!("{}", Value::Array(array)),
+            r#"{1, "foo"; , #VALUE!}"#
+        );
+    }
+
+    #[test]
+    fn test_to_text() {
+        let number = |value: &str| Value::Number(value.parse().unwrap());
+
+        assert_eq!(number("1.50").to_text().unwrap(), "1.5");
+        assert_eq!(number("1e3").to_text().unwrap(), "1000");
+        assert_eq!(number("-0.25").to_text().unwrap(), "-0.25");
+        assert_eq!(Value::String("foo".into()).to_text().unwrap(), "foo");
+        assert_eq!(Value::Boolean(false).to_text().unwrap(), "FALSE");
+        assert_eq!(Value::Empty.to_text().unwrap(), "");
+        assert_eq!(Value::Error(Error::Name).to_text(), Err(Error::Name));
+        assert_eq!(
+            Value::Array(Array::new(0, 0, vec![])).to_text(),
+            Err(Error::Value)
+        );
     }
 
     #[test]
@@ -115,8 +356,66 @@ mod tests {
             r#"{"type":"String","value":"foo"}"#
         );
         assert_eq!(
-            serde_json::to_string(&Value::Error(Error::Type)).unwrap(),
+            serde_json::to_string(&Value::Boolean(false)).unwrap(),
+            r#"{"type":"Boolean","value":false}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Value::DateTime(45351.into())).unwrap(),
+            r#"{"type":"DateTime","value":"2024-02-29"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Value::Error(Error::Value)).unwrap(),
             r#"{"type":"Error","value":"Type"}"#
         );
+        assert_eq!(
+            serde_json::to_string(&Value::Error(Error::Name)).unwrap(),
+            r#"{"type":"Error","value":"Undefined"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Value::Error(Error::DivisionByZero)).unwrap(),
+            r#"{"type":"Error","value":"DivisionByZero"}"#
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        let number = |value: i32| Value::Number(value.into());
+        let string = |value: &str| Value::String(value.into());
+
+        assert_eq!(number(1).compare(&number(2)), Ok(Ordering::Less));
+        assert_eq!(string("foo").compare(&string("FOO")), Ok(Ordering::Equal));
+        assert_eq!(string("a").compare(&string("B")), Ok(Ordering::Less));
+        assert_eq!(
+            Value::Boolean(true).compare(&Value::Boolean(false)),
+            Ok(Ordering::Greater)
+        );
+
+        // dates are compared as numbers
+        assert_eq!(
+            Value::DateTime(45351.into()).compare(&number(45351)),
+            Ok(Ordering::Equal)
+        );
+
+        // numbers < strings < booleans
+        assert_eq!(number(100).compare(&string("1")), Ok(Ordering::Less));
+        assert_eq!(
+            string("zzz").compare(&Value::Boolean(false)),
+            Ok(Ordering::Less)
+        );
+
+        // empty values are compared as the other value's "zero"
+        assert_eq!(Value::Empty.compare(&number(0)), Ok(Ordering::Equal));
+        assert_eq!(number(-1).compare(&Value::Empty), Ok(Ordering::Less));
+        assert_eq!(Value::Empty.compare(&string("")), Ok(Ordering::Equal));
+        assert_eq!(
+            Value::Boolean(false).compare(&Value::Empty),
+            Ok(Ordering::Equal)
+        );
+        assert_eq!(Value::Empty.compare(&Value::Empty), Ok(Ordering::Equal));
+
+        assert_eq!(
+            number(1).compare(&Value::Error(Error::NotAvailable)),
+            Err(Error::NotAvailable)
+        );
     }
 }
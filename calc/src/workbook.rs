@@ -0,0 +1,1217 @@
+//! Workbooks, which consist of multiple named sheets whose formulas can refer to each other,
+//! as in `=Sheet2!A1` or `=sum('My Sheet'!A1:B3)`.
+
+use std::collections::HashMap;
+use std::io;
+
+use petgraph::graphmap::DiGraphMap;
+use petgraph::Direction;
+use thiserror::Error;
+
+use crate::address::{CellAddress, CellRange, ColAddress, RowAddress};
+use crate::file::{FileError, SheetFile, WorkbookFile};
+use crate::formula::expression::Expression;
+use crate::formula::function::Argument;
+use crate::formula::registry::{Definition, FunctionRegistry, RegistryError};
+use crate::formula::signature::Signature;
+use crate::formula::{same_name, Context, Evaluate, Formula};
+use crate::functions;
+use crate::history::{Edit, History};
+use crate::parser::ParseFormulaError;
+use crate::sheet::{
+    normalize_functions, parse_cells, parse_definition, parse_names, CellAddressOrd,
+    EvaluationOrder, Name, NameError, Restructure, SetCellsError, Worksheet,
+};
+use crate::value::{Error, Value};
+
+/// The new values of changed cells, grouped by the name of the sheet they are on.
+pub type Changes = HashMap<String, HashMap<CellAddress, Value>>;
+
+/// Identifies a sheet independently of its name, which may change.
+pub(crate) type SheetId = u32;
+
+/// A cell in the workbook's dependency graph
+type Node = (SheetId, CellAddressOrd);
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WorkbookError {
+    #[error("there is no sheet named '{0}'")]
+    UnknownSheet(String),
+    #[error("there already is a sheet named '{0}'")]
+    DuplicateSheet(String),
+    #[error("'{0}' is not a valid sheet name")]
+    InvalidSheetName(String),
+    #[error("the last sheet of a workbook can't be deleted")]
+    LastSheet,
+    #[error(transparent)]
+    Parse(#[from] ParseFormulaError),
+    #[error(transparent)]
+    InvalidCells(#[from] SetCellsError),
+    #[error(transparent)]
+    Name(#[from] NameError),
+}
+
+struct NamedSheet {
+    id: SheetId,
+    name: String,
+    sheet: Worksheet,
+}
+
+pub struct Workbook {
+    /// the sheets, in the order they are shown
+    sheets: Vec<NamedSheet>,
+    next_id: SheetId,
+    /// Dependencies between cells on different sheets, in the same direction as [`Worksheet`]'s dependency graph.
+    /// Dependencies within a sheet are tracked by the sheet itself.
+    dependents: DiGraphMap<Node, ()>,
+    functions: FunctionRegistry,
+    history: History<(SheetId, CellAddress)>,
+}
+
+impl Default for Workbook {
+    /// A workbook with a single empty sheet named `Sheet1`
+    fn default() -> Self {
+        let mut workbook = Self {
+            sheets: Vec::new(),
+            next_id: 0,
+            dependents: DiGraphMap::new(),
+            functions: FunctionRegistry::new(),
+            history: History::default(),
+        };
+        workbook
+            .add_sheet("Sheet1")
+            .expect("the first sheet name is valid");
+        workbook
+    }
+}
+
+impl Workbook {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// A workbook with all functions of the [standard library](crate::functions) registered
+    pub fn with_standard_library() -> Self {
+        Self {
+            functions: functions::standard_library(),
+            ..Default::default()
+        }
+    }
+
+    /// The names of all sheets, in order
+    pub fn sheet_names(&self) -> impl Iterator<Item = &str> {
+        self.sheets.iter().map(|sheet| sheet.name.as_str())
+    }
+
+    /// The sheet with the given name; sheet names are case-insensitive.
+    pub fn sheet(&self, name: &str) -> Option<&Worksheet> {
+        self.find(name).map(|sheet| &sheet.sheet)
+    }
+
+    /// Adds an empty sheet after the existing ones.
+    /// Returns the new values of cells that already referred to a sheet of that name.
+    pub fn add_sheet<S: Into<String>>(&mut self, name: S) -> Result<Changes, WorkbookError> {
+        let name = name.into();
+        self.check_new_name(&name)?;
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.sheets.push(NamedSheet {
+            id,
+            name: name.clone(),
+            sheet: Worksheet::default(),
+        });
+
+        self.rebuild_dependents();
+        let referring = self.cells_referring_to(&name);
+        Ok(self.recalculate(referring))
+    }
+
+    /// Renames a sheet; references to it in formulas are changed to the new name.
+    /// Returns the new values of cells that referred to the old or new name.
+    pub fn rename_sheet(&mut self, from: &str, to: &str) -> Result<Changes, WorkbookError> {
+        let id = self.id(from)?;
+        // changing only the case of the name doesn't conflict with the sheet itself
+        if same_name(from, to) {
+            check_sheet_name(to)?;
+        } else {
+            self.check_new_name(to)?;
+        }
+
+        // the recorded inputs would still refer to the old name
+        self.history.clear();
+        let from = std::mem::replace(&mut self.sheet_mut(id).name, to.to_string());
+        for sheet in &mut self.sheets {
+            sheet
+                .sheet
+                .rewrite_formulas(|formula| formula.rename_sheet(&from, to));
+        }
+
+        self.rebuild_dependents();
+        let referring = self.cells_referring_to(to);
+        Ok(self.recalculate(referring))
+    }
+
+    /// Deletes a sheet; references to it in formulas become `#REF!` errors.
+    /// Returns the new values of the cells that referred to it.
+    pub fn delete_sheet(&mut self, name: &str) -> Result<Changes, WorkbookError> {
+        let id = self.id(name)?;
+        if self.sheets.len() == 1 {
+            return Err(WorkbookError::LastSheet);
+        }
+
+        self.history.clear();
+        let deleted = self.sheets.iter().position(|sheet| sheet.id == id);
+        let deleted = self.sheets.remove(deleted.expect("the sheet exists"));
+
+        let mut affected = Vec::new();
+        for sheet in &mut self.sheets {
+            let is_deleted =
+                |other: Option<&str>| other.is_some_and(|other| same_name(other, &deleted.name));
+            let changed = sheet.sheet.rewrite_formulas(|formula| {
+                formula.map_references(
+                    &|other, r| (!is_deleted(other)).then_some(r),
+                    &|other, r| (!is_deleted(other)).then_some(r),
+                )
+            });
+            affected.extend(changed.into_iter().map(|address| (sheet.id, address)));
+        }
+
+        self.rebuild_dependents();
+        Ok(self.recalculate(affected))
+    }
+
+    /// Writes all sheets' cell inputs in the native file format (see [`crate::file`]).
+    pub fn save<W: io::Write>(&self, writer: W) -> Result<(), FileError> {
+        let sheets = self
+            .sheets
+            .iter()
+            .map(|sheet| SheetFile {
+                name: sheet.name.clone(),
+                cells: sheet
+                    .sheet
+                    .cells()
+                    .map(|(address, cell)| (*address, cell.input().to_string()))
+                    .collect(),
+                names: sheet.sheet.names_to_save(),
+            })
+            .collect();
+        WorkbookFile::new(sheets).write(writer)
+    }
+
+    /// Replaces all sheets by those read from a file in the native format, keeping the workbook's functions.
+    /// All inputs are parsed and evaluated again; if any of them is invalid, the workbook is left unchanged.
+    /// Returns the values of all loaded cells.
+    pub fn load<R: io::Read>(&mut self, reader: R) -> Result<Changes, FileError> {
+        let file = WorkbookFile::read(reader)?;
+        self.load_file(file)
+    }
+
+    /// Replaces all sheets by those of a file that was read, see [`Workbook::load`].
+    pub(crate) fn load_file(&mut self, file: WorkbookFile) -> Result<Changes, FileError> {
+        let mut loaded = Workbook {
+            sheets: Vec::new(),
+            next_id: 0,
+            dependents: DiGraphMap::new(),
+            functions: FunctionRegistry::new(),
+            history: History::default(),
+        };
+        for file in file.sheets {
+            loaded.add_sheet(file.name.clone())?;
+            let sheet = loaded.sheets.last_mut().expect("the sheet was just added");
+            sheet
+                .sheet
+                .replace_names(parse_names(&self.functions, &file)?);
+            for cell in file.parse()? {
+                let (address, input, formula) = normalize_functions(&self.functions, cell);
+                sheet.sheet.store_cell(address, input, formula);
+            }
+        }
+        if loaded.sheets.is_empty() {
+            loaded.add_sheet("Sheet1")?;
+        }
+
+        self.sheets = loaded.sheets;
+        self.next_id = loaded.next_id;
+        self.history.clear();
+        self.rebuild_dependents();
+        let cells: Vec<_> = self
+            .sheets
+            .iter()
+            .flat_map(|sheet| sheet.sheet.cells().map(|(address, _)| (sheet.id, *address)))
+            .collect();
+        Ok(self.recalculate(cells))
+    }
+
+    pub fn set_cell(
+        &mut self,
+        sheet: &str,
+        address: CellAddress,
+        input: String,
+    ) -> Result<Changes, WorkbookError> {
+        let id = self.id(sheet)?;
+        let formula: Formula = input.parse()?;
+
+        Ok(self.edit(id, vec![(address, input, formula)]))
+    }
+
+    /// Sets the inputs of many cells on a sheet at once, see [`Sheet::set_cells`](crate::sheet::Sheet::set_cells).
+    pub fn set_cells<I: IntoIterator<Item = (CellAddress, String)>>(
+        &mut self,
+        sheet: &str,
+        cells: I,
+    ) -> Result<Changes, WorkbookError> {
+        let id = self.id(sheet)?;
+        let cells = parse_cells(cells)?;
+
+        Ok(self.edit(id, cells))
+    }
+
+    /// Stores the inputs and formulas of cells on a sheet as one step of the undo history,
+    /// and recalculates them as well as the cells depending on them.
+    pub(crate) fn edit(
+        &mut self,
+        id: SheetId,
+        cells: Vec<(CellAddress, String, Formula)>,
+    ) -> Changes {
+        let mut step = Vec::new();
+        let mut addresses = Vec::new();
+        for cell in cells {
+            let (address, input, formula) = normalize_functions(&self.functions, cell);
+            let sheet = &self.sheet_mut(id).sheet;
+            let before = sheet.cell(&address).map_or_else(Default::default, |cell| {
+                (cell.input().to_string(), cell.formula().clone())
+            });
+            step.push(Edit {
+                cell: (id, address),
+                before,
+                after: (input.clone(), formula.clone()),
+            });
+
+            self.store_cell(id, address, input, formula);
+            addresses.push((id, address));
+        }
+
+        self.history.record(step);
+        self.recalculate(addresses)
+    }
+
+    /// Reverts the most recent edit, see [`Sheet::undo`](crate::sheet::Sheet::undo).
+    /// Renaming or deleting sheets, inserting or deleting rows and columns and loading a file
+    /// can't be undone; they clear the history.
+    pub fn undo(&mut self) -> Option<Changes> {
+        let step = self.history.undo()?.to_vec();
+
+        let mut cells = Vec::new();
+        for Edit { cell, before, .. } in step.into_iter().rev() {
+            let ((id, address), (input, formula)) = (cell, before);
+            self.store_cell(id, address, input, formula);
+            cells.push(cell);
+        }
+        Some(self.recalculate(cells))
+    }
+
+    /// Applies the most recently undone edit again, see [`Sheet::redo`](crate::sheet::Sheet::redo).
+    pub fn redo(&mut self) -> Option<Changes> {
+        let step = self.history.redo()?.to_vec();
+
+        let mut cells = Vec::new();
+        for Edit { cell, after, .. } in step {
+            let ((id, address), (input, formula)) = (cell, after);
+            self.store_cell(id, address, input, formula);
+            cells.push(cell);
+        }
+        Some(self.recalculate(cells))
+    }
+
+    pub fn history(&self) -> &History<(SheetId, CellAddress)> {
+        &self.history
+    }
+
+    /// The undo history, e.g. for changing its depth
+    pub fn history_mut(&mut self) -> &mut History<(SheetId, CellAddress)> {
+        &mut self.history
+    }
+
+    /// Stores a cell's input and formula on a sheet and updates the dependencies between sheets accordingly.
+    /// The cell and its dependents still need to be recalculated afterwards.
+    fn store_cell(&mut self, id: SheetId, address: CellAddress, input: String, formula: Formula) {
+        // replace the cell's dependencies on other sheets
+        let node = (id, address.into());
+        let previous: Vec<_> = self
+            .dependents
+            .neighbors_directed(node, Direction::Incoming)
+            .collect();
+        for dependency in previous {
+            self.dependents.remove_edge(dependency, node);
+        }
+        for dependency in self.external_dependencies(&formula) {
+            self.dependents.add_edge(dependency, node, ());
+        }
+
+        self.sheet_mut(id).sheet.store_cell(address, input, formula);
+    }
+
+    /// Copies cells within a sheet, see [`Sheet::copy_range`](crate::sheet::Sheet::copy_range).
+    pub fn copy_range(
+        &mut self,
+        sheet: &str,
+        source: CellRange,
+        destination: CellRange,
+    ) -> Result<Changes, WorkbookError> {
+        let id = self.id(sheet)?;
+        Ok(self.copy_cells(id, source, destination))
+    }
+
+    /// Copies cells within the sheet with the given id as one step of the undo history.
+    pub(crate) fn copy_cells(
+        &mut self,
+        id: SheetId,
+        source: CellRange,
+        destination: CellRange,
+    ) -> Changes {
+        let cells = self.sheet_mut(id).sheet.pasted_cells(source, destination);
+        self.edit(id, cells)
+    }
+
+    /// Inserts rows into a sheet, see [`Sheet::insert_rows`](crate::sheet::Sheet::insert_rows).
+    /// References from other sheets are adjusted as well.
+    pub fn insert_rows(
+        &mut self,
+        sheet: &str,
+        at: RowAddress,
+        count: u32,
+    ) -> Result<Changes, WorkbookError> {
+        let id = self.id(sheet)?;
+        Ok(self.restructure(id, Restructure::rows(at, count, false)))
+    }
+
+    /// Deletes rows from a sheet, see [`Sheet::delete_rows`](crate::sheet::Sheet::delete_rows).
+    /// References from other sheets are adjusted as well.
+    pub fn delete_rows(
+        &mut self,
+        sheet: &str,
+        at: RowAddress,
+        count: u32,
+    ) -> Result<Changes, WorkbookError> {
+        let id = self.id(sheet)?;
+        Ok(self.restructure(id, Restructure::rows(at, count, true)))
+    }
+
+    /// Inserts columns into a sheet, see [`Sheet::insert_cols`](crate::sheet::Sheet::insert_cols).
+    /// References from other sheets are adjusted as well.
+    pub fn insert_cols(
+        &mut self,
+        sheet: &str,
+        at: ColAddress,
+        count: u32,
+    ) -> Result<Changes, WorkbookError> {
+        let id = self.id(sheet)?;
+        Ok(self.restructure(id, Restructure::cols(at, count, false)))
+    }
+
+    /// Deletes columns from a sheet, see [`Sheet::delete_cols`](crate::sheet::Sheet::delete_cols).
+    /// References from other sheets are adjusted as well.
+    pub fn delete_cols(
+        &mut self,
+        sheet: &str,
+        at: ColAddress,
+        count: u32,
+    ) -> Result<Changes, WorkbookError> {
+        let id = self.id(sheet)?;
+        Ok(self.restructure(id, Restructure::cols(at, count, true)))
+    }
+
+    /// Inserts or deletes rows or columns of the sheet with the given id, adjusting references from all sheets.
+    pub(crate) fn restructure(&mut self, id: SheetId, change: Restructure) -> Changes {
+        self.history.clear();
+        let (changed, affected) = self.sheet_mut(id).sheet.move_cells(change);
+        let mut affected: Vec<_> = affected.into_iter().map(|address| (id, address)).collect();
+
+        // adjust references to the restructured sheet, including qualified references on the sheet itself
+        let name = self.sheet_mut(id).name.clone();
+        for sheet in &mut self.sheets {
+            let is_restructured =
+                |other: Option<&str>| other.is_some_and(|other| same_name(other, &name));
+            let rewritten = sheet.sheet.rewrite_formulas(|formula| {
+                formula.map_references(
+                    &|other, r| match is_restructured(other) {
+                        true => change.reference(r),
+                        false => Some(r),
+                    },
+                    &|other, r| match is_restructured(other) {
+                        true => change.range(r),
+                        false => Some(r),
+                    },
+                )
+            });
+            affected.extend(rewritten.into_iter().map(|address| (sheet.id, address)));
+        }
+
+        self.rebuild_dependents();
+        let mut changes = self.recalculate(affected);
+        let values = changes.entry(name).or_default();
+        let sheet = &self.sheet_mut(id).sheet;
+        for address in changed {
+            values
+                .entry(address)
+                .or_insert_with(|| sheet.value(&address).into());
+        }
+        changes
+    }
+
+    /// Defines a name on a sheet, see [`Sheet::define_name`](crate::sheet::Sheet::define_name).
+    pub fn define_name(
+        &mut self,
+        sheet: &str,
+        name: &str,
+        definition: &str,
+    ) -> Result<Changes, WorkbookError> {
+        let id = self.id(sheet)?;
+        Ok(self.store_name(id, name, definition)?)
+    }
+
+    /// Defines a name on the sheet with the given id and recalculates the cells using it.
+    pub(crate) fn store_name(
+        &mut self,
+        id: SheetId,
+        name: &str,
+        definition: &str,
+    ) -> Result<Changes, NameError> {
+        let expression = parse_definition(&self.functions, name, definition)?;
+        let users = self.sheet_mut(id).sheet.store_name(name, expression)?;
+        Ok(self.recalculate(users.into_iter().map(|address| (id, address))))
+    }
+
+    /// Renames a name on a sheet, see [`Sheet::rename_name`](crate::sheet::Sheet::rename_name).
+    pub fn rename_name(
+        &mut self,
+        sheet: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<Changes, WorkbookError> {
+        let id = self.id(sheet)?;
+        Ok(self.change_name(id, from, to)?)
+    }
+
+    /// Renames a name on the sheet with the given id and recalculates the cells using it under either spelling.
+    pub(crate) fn change_name(
+        &mut self,
+        id: SheetId,
+        from: &str,
+        to: &str,
+    ) -> Result<Changes, NameError> {
+        let users = self.sheet_mut(id).sheet.change_name(from, to)?;
+        // the recorded inputs would still use the old name
+        self.history.clear();
+        Ok(self.recalculate(users.into_iter().map(|address| (id, address))))
+    }
+
+    /// Deletes a name on a sheet, see [`Sheet::delete_name`](crate::sheet::Sheet::delete_name).
+    pub fn delete_name(&mut self, sheet: &str, name: &str) -> Result<Changes, WorkbookError> {
+        let id = self.id(sheet)?;
+        Ok(self.remove_name(id, name)?)
+    }
+
+    /// Deletes a name on the sheet with the given id and recalculates the cells that used it.
+    pub(crate) fn remove_name(&mut self, id: SheetId, name: &str) -> Result<Changes, NameError> {
+        let users = self.sheet_mut(id).sheet.remove_name(name)?;
+        Ok(self.recalculate(users.into_iter().map(|address| (id, address))))
+    }
+
+    pub fn function(&self, name: &str) -> Option<&Definition> {
+        self.functions.get(name)
+    }
+
+    pub fn functions(&self) -> &FunctionRegistry {
+        &self.functions
+    }
+
+    /// Registers a function with its signature, which its arguments are checked against before it's called.
+    pub fn register_function(&mut self, definition: Definition) {
+        self.functions.register(definition);
+    }
+
+    /// Makes the function `name` callable as `alias` as well, see [`FunctionRegistry::register_alias`].
+    pub fn register_alias(&mut self, alias: &str, name: &str) -> Result<(), RegistryError> {
+        self.functions.register_alias(alias, name)
+    }
+
+    /// Adds the name of the function `name` in a locale, see [`FunctionRegistry::register_localized_name`].
+    pub fn register_localized_name(
+        &mut self,
+        locale: &str,
+        name: &str,
+        localized: &str,
+    ) -> Result<(), RegistryError> {
+        self.functions
+            .register_localized_name(locale, name, localized)
+    }
+
+    /// Registers a function that receives the values of its arguments, which aren't checked.
+    pub fn set_function<S: ToString, F: 'static + Send + Sync + Fn(&[Value]) -> Value>(
+        &mut self,
+        name: S,
+        function: F,
+    ) {
+        self.functions
+            .register(Definition::value(Signature::any(name), "", function));
+    }
+
+    /// Registers a function that receives its unevaluated arguments, see [`Function::Lazy`](crate::formula::function::Function::Lazy).
+    pub fn set_lazy_function<S, F>(&mut self, name: S, function: F)
+    where
+        S: ToString,
+        F: 'static + Send + Sync + Fn(&[Argument], &dyn Context) -> Value,
+    {
+        self.functions
+            .register(Definition::lazy(Signature::any(name), "", function));
+    }
+
+    fn find(&self, name: &str) -> Option<&NamedSheet> {
+        self.sheets
+            .iter()
+            .find(|sheet| same_name(&sheet.name, name))
+    }
+
+    /// The id and cells of the first sheet; a workbook always has at least one sheet.
+    pub(crate) fn first_sheet(&self) -> (SheetId, &Worksheet) {
+        let sheet = &self.sheets[0];
+        (sheet.id, &sheet.sheet)
+    }
+
+    /// Evaluates an expression on the sheet with the given id, outside of any cell.
+    pub(crate) fn evaluate_on<T: Evaluate>(&self, id: SheetId, expression: &T) -> Value {
+        let context = SheetContext {
+            workbook: self,
+            sheet: id,
+            cell: None,
+            volatile: Default::default(),
+        };
+        expression.evaluate(&context)
+    }
+
+    fn id(&self, name: &str) -> Result<SheetId, WorkbookError> {
+        self.find(name)
+            .map(|sheet| sheet.id)
+            .ok_or_else(|| WorkbookError::UnknownSheet(name.to_string()))
+    }
+
+    fn sheet_by_id(&self, id: SheetId) -> Option<&NamedSheet> {
+        self.sheets.iter().find(|sheet| sheet.id == id)
+    }
+
+    fn sheet_mut(&mut self, id: SheetId) -> &mut NamedSheet {
+        self.sheets
+            .iter_mut()
+            .find(|sheet| sheet.id == id)
+            .expect("the sheet exists")
+    }
+
+    fn check_new_name(&self, name: &str) -> Result<(), WorkbookError> {
+        check_sheet_name(name)?;
+        if self.find(name).is_some() {
+            return Err(WorkbookError::DuplicateSheet(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// The cells on existing sheets that a formula refers to via qualified references.
+    /// References to sheets that don't exist are ignored.
+    fn external_dependencies(&self, formula: &Formula) -> Vec<Node> {
+        let mut dependencies = Vec::new();
+        formula.visit_external_dependencies(&mut |name, address| {
+            if let Some(sheet) = self.find(name) {
+                dependencies.push((sheet.id, address.into()));
+            }
+        });
+        dependencies
+    }
+
+    /// All cells that refer to a sheet of the given name via qualified references
+    fn cells_referring_to(&self, name: &str) -> Vec<(SheetId, CellAddress)> {
+        let mut cells = Vec::new();
+        for sheet in &self.sheets {
+            for (address, cell) in sheet.sheet.cells() {
+                let mut refers = false;
+                cell.formula().visit_external_dependencies(&mut |other, _| {
+                    refers |= same_name(other, name);
+                });
+                if refers {
+                    cells.push((sheet.id, *address));
+                }
+            }
+        }
+        cells
+    }
+
+    /// Rebuilds the graph of dependencies between sheets from scratch,
+    /// after sheets have been added, renamed or deleted or cells have been moved.
+    fn rebuild_dependents(&mut self) {
+        let mut dependents = DiGraphMap::new();
+        for sheet in &self.sheets {
+            for (address, cell) in sheet.sheet.cells() {
+                let node = (sheet.id, (*address).into());
+                for dependency in self.external_dependencies(cell.formula()) {
+                    dependents.add_edge(dependency, node, ());
+                }
+            }
+        }
+        self.dependents = dependents;
+    }
+
+    /// Evaluates the given cells and all cells depending on them whose values may have changed, on any sheet,
+    /// and returns the new values of the given cells and all cells whose values actually changed.
+    /// Cells using volatile functions are evaluated as well, as their values may have changed at any time.
+    fn recalculate<I: IntoIterator<Item = (SheetId, CellAddress)>>(&mut self, cells: I) -> Changes {
+        let roots = cells
+            .into_iter()
+            .map(|(id, address)| (id, CellAddressOrd(address)));
+        let volatile: Vec<_> = self
+            .sheets
+            .iter()
+            .flat_map(|sheet| {
+                let id = sheet.id;
+                sheet
+                    .sheet
+                    .volatile_cells()
+                    .map(move |address| (id, CellAddressOrd(address)))
+            })
+            .collect();
+        let order =
+            EvaluationOrder::new(roots, volatile, |node @ (id, CellAddressOrd(address))| {
+                let local = self
+                    .sheet_by_id(id)
+                    .into_iter()
+                    .flat_map(|sheet| sheet.sheet.dependents(address))
+                    .map(move |address| (id, CellAddressOrd(address)));
+                local
+                    .chain(self.dependents.neighbors(node))
+                    .collect::<Vec<_>>()
+            });
+
+        let changed = order.propagate(|(id, CellAddressOrd(address)), is_cycle| {
+            let (value, volatile) = if is_cycle {
+                (Value::Error(Error::Cycle), false)
+            } else {
+                let context = SheetContext {
+                    workbook: self,
+                    sheet: id,
+                    cell: Some(address),
+                    volatile: Default::default(),
+                };
+                let value = match self
+                    .sheet_by_id(id)
+                    .and_then(|sheet| sheet.sheet.cell(&address))
+                {
+                    Some(cell) => cell.formula().evaluate(&context),
+                    None => Value::Empty,
+                };
+                (value, context.volatile.get())
+            };
+
+            let sheet = &mut self.sheet_mut(id).sheet;
+            let before = sheet.value(&address).cloned();
+            sheet.set_value(&address, value, volatile);
+            sheet.value(&address) != before.as_ref()
+        });
+
+        let mut changes = Changes::new();
+        for (id, CellAddressOrd(address)) in changed {
+            let sheet = self.sheet_mut(id);
+            changes
+                .entry(sheet.name.clone())
+                .or_default()
+                .insert(address, sheet.sheet.value(&address).into());
+        }
+        changes
+    }
+}
+
+/// Sheet names must not be empty and must not contain characters that are used in formulas around references.
+fn check_sheet_name(name: &str) -> Result<(), WorkbookError> {
+    let is_valid = !name.trim().is_empty()
+        && !name.contains(['!', ':', '[', ']', '*', '?', '/', '\\'])
+        && !name.starts_with('\'')
+        && !name.ends_with('\'');
+
+    if is_valid {
+        Ok(())
+    } else {
+        Err(WorkbookError::InvalidSheetName(name.to_string()))
+    }
+}
+
+/// Evaluates the formula of a cell on one sheet of a workbook
+struct SheetContext<'a> {
+    workbook: &'a Workbook,
+    sheet: SheetId,
+    cell: Option<CellAddress>,
+    /// whether a volatile function was called, directly or via a name
+    volatile: std::cell::Cell<bool>,
+}
+
+impl Context for SheetContext<'_> {
+    fn value(&self, sheet: Option<&str>, address: &CellAddress) -> Value {
+        let sheet = match sheet {
+            None => self.workbook.sheet_by_id(self.sheet),
+            Some(name) => self.workbook.find(name),
+        };
+        match sheet {
+            Some(sheet) => sheet.sheet.value(address).into(),
+            None => Value::Error(Error::Reference),
+        }
+    }
+
+    fn has_sheet(&self, sheet: &str) -> bool {
+        self.workbook.find(sheet).is_some()
+    }
+
+    fn cell(&self) -> Option<CellAddress> {
+        self.cell
+    }
+
+    fn function(&self, name: &str) -> Option<&Definition> {
+        let definition = self.workbook.function(name)?;
+        if definition.signature().is_volatile() {
+            self.volatile.set(true);
+        }
+        Some(definition)
+    }
+
+    fn named_expression(&self, name: &str) -> Option<&Expression> {
+        self.workbook
+            .sheet_by_id(self.sheet)
+            .and_then(|sheet| sheet.sheet.defined_name(name))
+            .map(Name::expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+
+    use super::*;
+
+    use crate::cell::Cell;
+
+    fn workbook(cells: &[(&str, &str, &str)]) -> Workbook {
+        let mut workbook = Workbook::new();
+        workbook.add_sheet("Sheet2").unwrap();
+        for (sheet, address, input) in cells {
+            workbook
+                .set_cell(sheet, address.parse().unwrap(), input.to_string())
+                .unwrap();
+        }
+        workbook
+    }
+
+    fn input(workbook: &Workbook, sheet: &str, address: &str) -> String {
+        workbook
+            .sheet(sheet)
+            .unwrap()
+            .cell(&address.parse().unwrap())
+            .map_or("", Cell::input)
+            .to_string()
+    }
+
+    fn value(workbook: &Workbook, sheet: &str, address: &str) -> Value {
+        workbook
+            .sheet(sheet)
+            .unwrap()
+            .value(&address.parse().unwrap())
+            .into()
+    }
+
+    #[test]
+    fn test_sheets() {
+        let mut workbook = Workbook::new();
+        assert_eq!(workbook.sheet_names().collect::<Vec<_>>(), ["Sheet1"]);
+
+        workbook.add_sheet("My Sheet").unwrap();
+        assert_eq!(
+            workbook.sheet_names().collect::<Vec<_>>(),
+            ["Sheet1", "My Sheet"]
+        );
+        assert!(workbook.sheet("my sheet").is_some());
+
+        assert_eq!(
+            workbook.add_sheet("SHEET1"),
+            Err(WorkbookError::DuplicateSheet("SHEET1".to_string()))
+        );
+        assert_eq!(
+            workbook.add_sheet("a:b"),
+            Err(WorkbookError::InvalidSheetName("a:b".to_string()))
+        );
+        assert_eq!(
+            workbook.set_cell("Sheet3", "A1".parse().unwrap(), "1".to_string()),
+            Err(WorkbookError::UnknownSheet("Sheet3".to_string()))
+        );
+
+        workbook.delete_sheet("Sheet1").unwrap();
+        assert_eq!(
+            workbook.delete_sheet("My Sheet"),
+            Err(WorkbookError::LastSheet)
+        );
+    }
+
+    #[test]
+    fn test_cross_sheet_reference() {
+        let mut workbook = workbook(&[
+            ("Sheet1", "A1", "1"),
+            ("Sheet2", "A1", "=Sheet1!A1 * 2"),
+            ("Sheet1", "A2", "=sheet2!A1 + A1"),
+        ]);
+        assert_eq!(value(&workbook, "Sheet1", "A2"), Value::Number(3.into()));
+
+        // changes propagate across sheets
+        let changes = workbook
+            .set_cell("Sheet1", "A1".parse().unwrap(), "2".to_string())
+            .unwrap();
+        assert_eq!(
+            changes["Sheet2"][&"A1".parse().unwrap()],
+            Value::Number(4.into())
+        );
+        assert_eq!(
+            changes["Sheet1"][&"A2".parse().unwrap()],
+            Value::Number(6.into())
+        );
+    }
+
+    #[test]
+    fn test_cross_sheet_range() {
+        let mut workbook = workbook(&[("Sheet2", "A1", "1"), ("Sheet2", "B2", "2")]);
+        workbook.set_function("count", |arguments| match arguments {
+            [Value::Array(array)] => Value::Number((array.values().len() as i64).into()),
+            _ => Value::Error(Error::Value),
+        });
+        workbook.add_sheet("My Sheet").unwrap();
+        workbook
+            .set_cell("My Sheet", "A1".parse().unwrap(), "1".to_string())
+            .unwrap();
+
+        workbook
+            .set_cell(
+                "Sheet1",
+                "A1".parse().unwrap(),
+                "=count(Sheet2!A1:B2) + 'My Sheet'!A1".to_string(),
+            )
+            .unwrap();
+        assert_eq!(value(&workbook, "Sheet1", "A1"), Value::Number(5.into()));
+    }
+
+    #[test]
+    fn test_lazy_function() {
+        let mut workbook = workbook(&[("Sheet2", "A1", "2")]);
+        // the value of the first argument, evaluated relative to the current cell's column
+        workbook.set_lazy_function("column_plus", |arguments, context| match arguments {
+            [argument] => match (argument.evaluate(), context.cell()) {
+                (Value::Number(number), Some(cell)) => {
+                    Value::Number(number + BigDecimal::from(cell.col().get()))
+                }
+                _ => Value::Error(Error::Value),
+            },
+            _ => Value::Error(Error::Value),
+        });
+
+        workbook
+            .set_cell(
+                "Sheet1",
+                "C1".parse().unwrap(),
+                "=column_plus(Sheet2!A1)".to_string(),
+            )
+            .unwrap();
+        assert_eq!(value(&workbook, "Sheet1", "C1"), Value::Number(5.into()));
+
+        // lazy functions are recalculated like others
+        workbook
+            .set_cell("Sheet2", "A1".parse().unwrap(), "3".to_string())
+            .unwrap();
+        assert_eq!(value(&workbook, "Sheet1", "C1"), Value::Number(6.into()));
+    }
+
+    #[test]
+    fn test_set_cells() {
+        let mut workbook = workbook(&[("Sheet1", "A1", "=Sheet2!A1 + Sheet2!A2")]);
+
+        let changes = workbook
+            .set_cells(
+                "Sheet2",
+                [
+                    ("A1".parse().unwrap(), "1".to_string()),
+                    ("A2".parse().unwrap(), "2".to_string()),
+                ],
+            )
+            .unwrap();
+        assert_eq!(
+            changes["Sheet1"][&"A1".parse().unwrap()],
+            Value::Number(3.into())
+        );
+
+        let result = workbook.set_cells("Sheet2", [("A1".parse().unwrap(), "=(".to_string())]);
+        assert!(matches!(result, Err(WorkbookError::InvalidCells(_))));
+        assert_eq!(value(&workbook, "Sheet2", "A1"), Value::Number(1.into()));
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut workbook = workbook(&[("Sheet2", "A1", "1"), ("Sheet1", "A1", "=Sheet2!A1")]);
+
+        workbook
+            .set_cell("Sheet2", "A1".parse().unwrap(), "2".to_string())
+            .unwrap();
+        let changes = workbook.undo().unwrap();
+        assert_eq!(
+            changes["Sheet1"][&"A1".parse().unwrap()],
+            Value::Number(1.into())
+        );
+
+        let changes = workbook.redo().unwrap();
+        assert_eq!(
+            changes["Sheet1"][&"A1".parse().unwrap()],
+            Value::Number(2.into())
+        );
+
+        // undoing the very first edit empties the cell
+        workbook.undo().unwrap();
+        workbook.undo().unwrap();
+        workbook.undo().unwrap();
+        assert_eq!(input(&workbook, "Sheet2", "A1"), "");
+        assert!(workbook.undo().is_none());
+
+        // renaming a sheet clears the history
+        workbook.redo().unwrap();
+        workbook.rename_sheet("Sheet2", "Data").unwrap();
+        assert!(workbook.undo().is_none());
+    }
+
+    #[test]
+    fn test_cross_sheet_cycle() {
+        let workbook = workbook(&[
+            ("Sheet1", "A1", "=Sheet2!A1"),
+            ("Sheet1", "B1", "=A1"),
+            ("Sheet2", "A1", "=Sheet1!A1"),
+        ]);
+        for (sheet, address) in [("Sheet1", "A1"), ("Sheet1", "B1"), ("Sheet2", "A1")] {
+            assert_eq!(value(&workbook, sheet, address), Value::Error(Error::Cycle));
+        }
+    }
+
+    #[test]
+    fn test_unknown_sheet_reference() {
+        let mut workbook = Workbook::with_standard_library();
+        workbook.add_sheet("Sheet2").unwrap();
+        for (sheet, address, input) in [
+            ("Sheet2", "A1", "=#REF!"),
+            ("Sheet2", "A2", "2"),
+            ("Sheet1", "A1", "=Sheet3!A1 + 1"),
+            ("Sheet1", "B1", "=counta(Sheet2!A1:A3)"),
+            ("Sheet1", "B2", "=iferror(index(Sheet2!A1:A2, 2), 0)"),
+            ("Sheet1", "B3", "=sum(Sheet3!A1:A2)"),
+        ] {
+            workbook
+                .set_cell(sheet, address.parse().unwrap(), input.to_string())
+                .unwrap();
+        }
+        assert_eq!(
+            value(&workbook, "Sheet1", "A1"),
+            Value::Error(Error::Reference)
+        );
+        assert_eq!(
+            value(&workbook, "Sheet1", "B3"),
+            Value::Error(Error::Reference)
+        );
+        // errors in the cells of a range on an existing sheet are kept as they are
+        assert_eq!(value(&workbook, "Sheet1", "B1"), Value::Number(2.into()));
+        assert_eq!(value(&workbook, "Sheet1", "B2"), Value::Number(2.into()));
+
+        // adding the sheet resolves the reference
+        let changes = workbook.add_sheet("Sheet3").unwrap();
+        assert!(changes["Sheet1"].contains_key(&"A1".parse().unwrap()));
+        workbook
+            .set_cell("Sheet3", "A1".parse().unwrap(), "2".to_string())
+            .unwrap();
+        assert_eq!(value(&workbook, "Sheet1", "A1"), Value::Number(3.into()));
+    }
+
+    #[test]
+    fn test_rename_sheet() {
+        let mut workbook = workbook(&[
+            ("Sheet2", "A1", "2"),
+            ("Sheet1", "A1", "=Sheet2!A1 + SHEET2!A1"),
+        ]);
+
+        workbook.rename_sheet("sheet2", "It's mine").unwrap();
+        assert_eq!(
+            input(&workbook, "Sheet1", "A1"),
+            "='It''s mine'!A1 + 'It''s mine'!A1"
+        );
+        assert_eq!(value(&workbook, "Sheet1", "A1"), Value::Number(4.into()));
+
+        // the renamed sheet's cells still update their dependents
+        workbook
+            .set_cell("It's mine", "A1".parse().unwrap(), "3".to_string())
+            .unwrap();
+        assert_eq!(value(&workbook, "Sheet1", "A1"), Value::Number(6.into()));
+
+        assert_eq!(
+            workbook.rename_sheet("Sheet1", "it's MINE"),
+            Err(WorkbookError::DuplicateSheet("it's MINE".to_string()))
+        );
+        workbook.rename_sheet("Sheet1", "SHEET1").unwrap();
+        assert_eq!(workbook.sheet_names().next(), Some("SHEET1"));
+
+        // sheet names are case-insensitive beyond ASCII, like defined names
+        workbook.rename_sheet("It's mine", "Übersicht").unwrap();
+        workbook
+            .set_cell(
+                "SHEET1",
+                "A2".parse().unwrap(),
+                "='ÜBERSICHT'!A1".to_string(),
+            )
+            .unwrap();
+        assert_eq!(value(&workbook, "Sheet1", "A2"), Value::Number(3.into()));
+        assert_eq!(
+            workbook.add_sheet("übersicht"),
+            Err(WorkbookError::DuplicateSheet("übersicht".to_string()))
+        );
+        workbook.rename_sheet("übersicht", "Summary").unwrap();
+        assert_eq!(input(&workbook, "Sheet1", "A2"), "=Summary!A1");
+    }
+
+    #[test]
+    fn test_delete_sheet() {
+        let mut workbook = workbook(&[
+            ("Sheet2", "A1", "2"),
+            ("Sheet1", "A1", "=Sheet2!A1 + 1"),
+            ("Sheet1", "A2", "=A1"),
+        ]);
+
+        let changes = workbook.delete_sheet("Sheet2").unwrap();
+        assert_eq!(input(&workbook, "Sheet1", "A1"), "=#REF! + 1");
+        assert_eq!(
+            changes["Sheet1"][&"A2".parse().unwrap()],
+            Value::Error(Error::Reference)
+        );
+
+        // a new sheet with the same name is unrelated to the deleted one
+        workbook.add_sheet("Sheet2").unwrap();
+        assert_eq!(
+            value(&workbook, "Sheet1", "A1"),
+            Value::Error(Error::Reference)
+        );
+    }
+
+    #[test]
+    fn test_save_load() {
+        let workbook = workbook(&[("Sheet2", "A1", "2"), ("Sheet1", "A1", "=Sheet2!A1 + 1")]);
+        let mut buffer = Vec::new();
+        workbook.save(&mut buffer).unwrap();
+
+        let mut loaded = Workbook::new();
+        let changes = loaded.load(buffer.as_slice()).unwrap();
+        assert_eq!(
+            loaded.sheet_names().collect::<Vec<_>>(),
+            ["Sheet1", "Sheet2"]
+        );
+        assert_eq!(
+            changes["Sheet1"][&"A1".parse().unwrap()],
+            Value::Number(3.into())
+        );
+
+        // cross-sheet dependencies were restored as well
+        loaded
+            .set_cell("Sheet2", "A1".parse().unwrap(), "3".to_string())
+            .unwrap();
+        assert_eq!(value(&loaded, "Sheet1", "A1"), Value::Number(4.into()));
+
+        // duplicate sheet names are rejected
+        let file = r#"{ "version": 1, "sheets": [{ "name": "a", "cells": {} }, { "name": "A", "cells": {} }] }"#;
+        assert!(matches!(
+            loaded.load(file.as_bytes()),
+            Err(FileError::Workbook(WorkbookError::DuplicateSheet(_)))
+        ));
+        assert_eq!(value(&loaded, "Sheet1", "A1"), Value::Number(4.into()));
+    }
+
+    #[test]
+    fn test_insert_rows_on_other_sheet() {
+        let mut workbook = workbook(&[
+            ("Sheet2", "A1", "1"),
+            ("Sheet2", "A2", "2"),
+            ("Sheet1", "A1", "=Sheet2!A2 + A2"),
+            ("Sheet1", "A2", "3"),
+        ]);
+
+        // only references to the restructured sheet are adjusted
+        let changes = workbook
+            .insert_rows("Sheet2", "1".parse().unwrap(), 1)
+            .unwrap();
+        assert_eq!(input(&workbook, "Sheet1", "A1"), "=Sheet2!A3 + A2");
+        assert_eq!(changes["Sheet2"][&"A1".parse().unwrap()], Value::Empty);
+
+        workbook
+            .set_cell("Sheet2", "A3".parse().unwrap(), "5".to_string())
+            .unwrap();
+        assert_eq!(value(&workbook, "Sheet1", "A1"), Value::Number(8.into()));
+
+        workbook
+            .delete_rows("Sheet2", "3".parse().unwrap(), 1)
+            .unwrap();
+        assert_eq!(input(&workbook, "Sheet1", "A1"), "=#REF! + A2");
+    }
+
+    #[test]
+    fn test_names() {
+        let mut workbook = workbook(&[
+            ("Sheet1", "A1", "2"),
+            ("Sheet1", "A2", "=A1 * Rate"),
+            ("Sheet2", "A1", "=Sheet1!A2 + Rate"),
+        ]);
+        let changes = workbook.define_name("Sheet1", "Rate", "$A$1").unwrap();
+        assert_eq!(
+            changes["Sheet1"][&"A2".parse().unwrap()],
+            Value::Number(4.into())
+        );
+        // names are defined per sheet
+        assert_eq!(value(&workbook, "Sheet2", "A1"), Value::Error(Error::Name));
+        assert_eq!(
+            workbook.define_name("Sheet3", "Rate", "1"),
+            Err(WorkbookError::UnknownSheet("Sheet3".to_string()))
+        );
+
+        // changes of the cell the name refers to reach cells on other sheets
+        workbook.define_name("Sheet2", "Rate", "10").unwrap();
+        workbook
+            .set_cell("Sheet1", "A1".parse().unwrap(), "3".to_string())
+            .unwrap();
+        assert_eq!(value(&workbook, "Sheet2", "A1"), Value::Number(19.into()));
+
+        // the definition follows the cell it refers to
+        workbook
+            .insert_rows("Sheet1", "1".parse().unwrap(), 1)
+            .unwrap();
+        let sheet = workbook.sheet("Sheet1").unwrap();
+        assert_eq!(sheet.defined_name("rate").unwrap().definition(), "$A$2");
+        assert_eq!(value(&workbook, "Sheet1", "A3"), Value::Number(9.into()));
+
+        let mut buffer = Vec::new();
+        workbook.save(&mut buffer).unwrap();
+        let mut loaded = Workbook::new();
+        loaded.load(buffer.as_slice()).unwrap();
+        assert_eq!(value(&loaded, "Sheet2", "A1"), Value::Number(19.into()));
+        loaded
+            .set_cell("Sheet1", "A2".parse().unwrap(), "4".to_string())
+            .unwrap();
+        assert_eq!(value(&loaded, "Sheet1", "A3"), Value::Number(16.into()));
+
+        loaded.rename_name("Sheet1", "Rate", "Factor").unwrap();
+        assert_eq!(input(&loaded, "Sheet1", "A3"), "=A2 * Factor");
+        let changes = loaded.delete_name("Sheet1", "Factor").unwrap();
+        assert_eq!(
+            changes["Sheet1"][&"A3".parse().unwrap()],
+            Value::Error(Error::Name)
+        );
+        assert_eq!(value(&loaded, "Sheet2", "A1"), Value::Error(Error::Name));
+    }
+}